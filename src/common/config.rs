@@ -0,0 +1,97 @@
+use std::env;
+
+use super::constants::{
+    ACCOUNT_BACKUP_DIR, ACCOUNT_DIR, AUDIT_DIR, DATA_DIR, DATA_DIR_ENV, FEED_DIR, JOURNAL_DIR,
+    REGISTRY_DIR, STDIN_DIR, SUMMARY_DIR, TRANSACTION_DIR, TX_INDEX_DIR,
+};
+use crate::models::tx_dispute::DisputePolicy;
+
+// every path of the system data is resolved from the data root held here,
+// along with the rules every worker applies the same way.
+#[derive(Debug, Clone)]
+pub struct Config {
+    data_dir: String,
+    dispute_policy: DisputePolicy,
+}
+
+impl Config {
+    pub fn new(data_dir: &str) -> Self {
+        let mut data_dir = data_dir.trim_end_matches('/');
+        if data_dir.is_empty() {
+            data_dir = DATA_DIR;
+        }
+        Self {
+            data_dir: data_dir.to_string(),
+            dispute_policy: DisputePolicy::default(),
+        }
+    }
+
+    // the data root is taken from the flag, then the env var, then the default.
+    pub fn resolve(data_dir: Option<&str>) -> Self {
+        if let Some(data_dir) = data_dir {
+            return Self::new(data_dir);
+        }
+
+        if let Ok(data_dir) = env::var(DATA_DIR_ENV) {
+            return Self::new(&data_dir);
+        }
+
+        Self::new(DATA_DIR)
+    }
+
+    pub fn data_dir(&self) -> &str {
+        &self.data_dir
+    }
+
+    pub fn dispute_policy(&self) -> &DisputePolicy {
+        &self.dispute_policy
+    }
+
+    pub fn set_dispute_policy(&mut self, dispute_policy: DisputePolicy) {
+        self.dispute_policy = dispute_policy;
+    }
+
+    pub fn account_dir(&self) -> String {
+        self.path(ACCOUNT_DIR)
+    }
+
+    pub fn account_backup_dir(&self) -> String {
+        self.path(ACCOUNT_BACKUP_DIR)
+    }
+
+    pub fn audit_dir(&self) -> String {
+        self.path(AUDIT_DIR)
+    }
+
+    pub fn transaction_dir(&self) -> String {
+        self.path(TRANSACTION_DIR)
+    }
+
+    pub fn summary_dir(&self) -> String {
+        self.path(SUMMARY_DIR)
+    }
+
+    pub fn journal_dir(&self) -> String {
+        self.path(JOURNAL_DIR)
+    }
+
+    pub fn registry_dir(&self) -> String {
+        self.path(REGISTRY_DIR)
+    }
+
+    pub fn tx_index_dir(&self) -> String {
+        self.path(TX_INDEX_DIR)
+    }
+
+    pub fn feed_dir(&self) -> String {
+        self.path(FEED_DIR)
+    }
+
+    pub fn stdin_dir(&self) -> String {
+        self.path(STDIN_DIR)
+    }
+
+    fn path(&self, dir: &str) -> String {
+        [&self.data_dir, dir].join("/")
+    }
+}
@@ -0,0 +1,36 @@
+pub const DATA_DIR: &str = "data";
+pub const DATA_DIR_ENV: &str = "PARSE_DATA_DIR";
+pub const ACCOUNT_DIR: &str = "account";
+pub const ACCOUNT_BACKUP_DIR: &str = "account_backup";
+pub const HISTORY_BACKUP_SUFFIX: &str = "_history.csv";
+pub const TRANSACTION_DIR: &str = "transaction";
+pub const SUMMARY_DIR: &str = "summary";
+pub const SUMMARY_TRANSACTION_DIR: &str = "transaction";
+pub const SUMMARY_ROLLBACK_DIR: &str = "rollback";
+pub const JOURNAL_DIR: &str = "journal";
+pub const REGISTRY_DIR: &str = "registry";
+pub const REGISTRY_FILE: &str = "inputs.csv";
+pub const SUMMARY_REGISTRY_DIR: &str = "registry";
+pub const TX_INDEX_DIR: &str = "tx_index";
+pub const SUMMARY_TX_INDEX_DIR: &str = "tx_index";
+pub const SUMMARY_REJECTED_DIR: &str = "rejected";
+pub const AUDIT_DIR: &str = "audit";
+pub const AUDIT_FILE: &str = "state.csv";
+pub const SUMMARY_AUDIT_DIR: &str = "audit";
+pub const FEED_DIR: &str = "feed";
+pub const STDIN_DIR: &str = "stdin";
+pub const STDIN_PATH: &str = "-";
+pub const STORE_VERSION_FILE: &str = "version";
+pub const FN_NEW: &str = "new";
+
+pub const TYPE_POS: usize = 0;
+pub const CLIENT_POS: usize = 1;
+pub const TX_POS: usize = 2;
+pub const AMOUNT_POS: usize = 3;
+pub const TO_POS: usize = 4;
+
+pub const MAX_CSV_ROW_LEN: usize = 5;
+pub const MIN_CSV_ROW_LEN: usize = 3;
+
+// decimal places amounts are kept at
+pub const AMOUNT_SCALE: u32 = 4;
@@ -0,0 +1,23 @@
+use std::thread;
+use std::time::Duration;
+
+const OPEN_RETRIES: u32 = 50;
+const OPEN_RETRY_DURATION: u64 = 20;
+
+// sled releases the file lock of a dropped db in the background,
+// so opening a db right after another handle was dropped is retried for a while.
+pub fn open(path: &str) -> Result<sled::Db, sled::Error> {
+    let mut retries = 0;
+    loop {
+        match sled::open(path) {
+            Ok(db) => return Ok(db),
+            Err(e) => {
+                retries += 1;
+                if retries == OPEN_RETRIES {
+                    return Err(e);
+                }
+                thread::sleep(Duration::from_millis(OPEN_RETRY_DURATION));
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+use std::string::FromUtf8Error;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ErrorKind {
+    IO,
+    PARSE,
+    STORAGE,
+    RULE,
+    CONCURRENCY,
+}
+
+// where an error was raised + what went wrong.
+#[derive(Debug)]
+pub struct ErrorContext {
+    pub time: String,
+    pub path: String,
+    pub method: String,
+    pub tag: String,
+    pub message: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    // file system, reader + writer failures
+    IO(Box<ErrorContext>),
+    // malformed input data
+    PARSE(Box<ErrorContext>),
+    // transaction history + index db failures
+    STORAGE(Box<ErrorContext>),
+    // a request the system refuses, e.g. reusing the name of an applied input
+    RULE(Box<ErrorContext>),
+    // worker threads + channels
+    CONCURRENCY(Box<ErrorContext>),
+}
+
+impl AppError {
+    pub fn io(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        Self::IO(Box::new(ErrorContext::new(path, method, tag, err)))
+    }
+
+    pub fn parse(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        Self::PARSE(Box::new(ErrorContext::new(path, method, tag, err)))
+    }
+
+    pub fn storage(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        Self::STORAGE(Box::new(ErrorContext::new(path, method, tag, err)))
+    }
+
+    pub fn rule(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        Self::RULE(Box::new(ErrorContext::new(path, method, tag, err)))
+    }
+
+    pub fn concurrency(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        Self::CONCURRENCY(Box::new(ErrorContext::new(path, method, tag, err)))
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::IO(_) => ErrorKind::IO,
+            Self::PARSE(_) => ErrorKind::PARSE,
+            Self::STORAGE(_) => ErrorKind::STORAGE,
+            Self::RULE(_) => ErrorKind::RULE,
+            Self::CONCURRENCY(_) => ErrorKind::CONCURRENCY,
+        }
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        match self {
+            Self::IO(c)
+            | Self::PARSE(c)
+            | Self::STORAGE(c)
+            | Self::RULE(c)
+            | Self::CONCURRENCY(c) => c,
+        }
+    }
+
+    // stdout is kept for the output of a run
+    pub fn show(&self) {
+        eprintln!("{}", self);
+    }
+}
+
+impl ErrorContext {
+    fn new(path: &str, method: &str, tag: &str, err: impl ErrorSource) -> Self {
+        let (message, source) = err.into_source();
+        Self {
+            time: chrono::Utc::now().to_string(),
+            path: path.to_string(),
+            method: method.to_string(),
+            tag: tag.to_string(),
+            message,
+            source,
+        }
+    }
+}
+
+// the rendered form is "time | path | method | tag | message"
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = self.context();
+        write!(
+            f,
+            "{}",
+            [c.time.as_str(), &c.path, &c.method, &c.tag, &c.message].join(" | ")
+        )
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.context().source {
+            Some(source) => Some(source.as_ref()),
+            None => None,
+        }
+    }
+}
+
+// what an error is built from: a message, or an underlying error kept as its source.
+pub trait ErrorSource {
+    fn into_source(self) -> (String, Option<Box<dyn Error + Send + Sync>>);
+}
+
+impl ErrorSource for &str {
+    fn into_source(self) -> (String, Option<Box<dyn Error + Send + Sync>>) {
+        (self.to_string(), None)
+    }
+}
+
+impl ErrorSource for &String {
+    fn into_source(self) -> (String, Option<Box<dyn Error + Send + Sync>>) {
+        (self.to_string(), None)
+    }
+}
+
+impl ErrorSource for String {
+    fn into_source(self) -> (String, Option<Box<dyn Error + Send + Sync>>) {
+        (self, None)
+    }
+}
+
+macro_rules! error_source {
+    ($($t:ty),*) => {
+        $(
+            impl ErrorSource for $t {
+                fn into_source(self) -> (String, Option<Box<dyn Error + Send + Sync>>) {
+                    (self.to_string(), Some(Box::new(self)))
+                }
+            }
+        )*
+    };
+}
+
+error_source!(
+    io::Error,
+    csv::Error,
+    sled::Error,
+    serde_json::Error,
+    ParseIntError,
+    FromUtf8Error,
+    chrono::ParseError,
+    AppError
+);
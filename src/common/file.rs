@@ -0,0 +1,54 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Seek, SeekFrom, Write};
+
+// append data to the file once, so a commit that is run again after a crash never adds it twice.
+// the length of the file before the append is kept at the mark path first,
+// a commit run again truncates the file back to it + appends the data again.
+// a new file starts with the header, if there is one.
+pub fn append_once(
+    path: &str,
+    mark_path: &str,
+    header: Option<&str>,
+    data: &str,
+) -> Result<(), io::Error> {
+    let len = match fs::read_to_string(mark_path) {
+        Ok(string) => string
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let len = match fs::metadata(path) {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+            // write + rename so a crash never leaves a half written mark
+            let tmp_path = [mark_path, ".tmp"].join("");
+            fs::write(&tmp_path, len.to_string())?;
+            fs::rename(&tmp_path, mark_path)?;
+            len
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    file.set_len(len)?;
+    file.seek(SeekFrom::End(0))?;
+
+    let mut string = String::new();
+    if len == 0 {
+        if let Some(header) = header {
+            string = [header, "\n"].join("");
+        }
+    }
+    for line in data.lines().filter(|l| !l.is_empty()) {
+        string.push_str(line);
+        string.push('\n');
+    }
+    file.write_all(string.as_bytes())?;
+    file.sync_all()
+}
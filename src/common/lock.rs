@@ -0,0 +1,110 @@
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+static NEXT_RUN: AtomicU64 = AtomicU64::new(0);
+static PROCESS: OnceLock<String> = OnceLock::new();
+
+// a lock file taken with create_new, holding "pid,process,run": the pid of the process that took it,
+// when that process first took a lock + the run in it the lock was taken for.
+// the pid alone can't tell a live process from a former one with the same pid, as in a container where every run is pid 1,
+// so a lock with the pid of this process but from another process is stale.
+// the lock is released when dropped.
+pub struct Lock {
+    path: String,
+    owner: String,
+}
+
+impl Lock {
+    pub fn new(path: &str) -> Self {
+        let run = NEXT_RUN.fetch_add(1, Ordering::SeqCst);
+        Self {
+            path: path.to_string(),
+            owner: [
+                &process::id().to_string(),
+                Self::process(),
+                &run.to_string(),
+            ]
+            .join(","),
+        }
+    }
+
+    // take the lock, false if a live run holds it.
+    // a lock left by a run of a process that is gone is stale and taken over.
+    pub fn acquire(&self) -> Result<bool, io::Error> {
+        for _ in 0..2 {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&self.path)
+            {
+                Ok(mut f) => {
+                    f.write_all(self.owner.as_bytes())?;
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let holder = match fs::read_to_string(&self.path) {
+                        Ok(holder) => holder,
+                        // released in the meantime
+                        Err(_) => continue,
+                    };
+                    if holder == self.owner {
+                        return Ok(true);
+                    }
+                    if Self::alive(&holder) {
+                        return Ok(false);
+                    }
+                    let _ = fs::remove_file(&self.path);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn held(&self) -> bool {
+        fs::read_to_string(&self.path).is_ok_and(|holder| holder == self.owner)
+    }
+
+    pub fn release(&self) {
+        if self.held() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    // a run of this process, or a run of another process that is still there.
+    // an empty lock is being written by its owner.
+    fn alive(holder: &str) -> bool {
+        let v: Vec<&str> = holder.trim().split(',').collect();
+        if v[0].is_empty() {
+            return true;
+        }
+        let pid = match v[0].parse::<u32>() {
+            Ok(pid) => pid,
+            Err(_) => return false,
+        };
+        if pid == process::id() {
+            return v.get(1) == Some(&Self::process());
+        }
+
+        // without /proc there is no telling, so the process is taken to be alive
+        if !Path::new("/proc/self").exists() {
+            return true;
+        }
+        Path::new(&["/proc/", &pid.to_string()].join("")).exists()
+    }
+
+    fn process() -> &'static str {
+        PROCESS.get_or_init(|| Utc::now().timestamp_nanos().to_string())
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
@@ -0,0 +1,6 @@
+pub mod config;
+pub mod constants;
+pub mod db;
+pub mod error;
+pub mod file;
+pub mod lock;
@@ -0,0 +1,24 @@
+mod common;
+mod models;
+mod tests;
+
+// the stable api, the modules above are the engine internals
+pub use common::config::Config;
+pub use common::error::{AppError, ErrorKind};
+pub use models::account::{Account, AccountState};
+pub use models::engine::Engine;
+pub use models::processor::Processor;
+pub use models::tx_columns::TxColumnMap;
+pub use models::tx_dispute::DisputePolicy;
+pub use models::tx_input::TxInputReport;
+pub use models::tx_reader::{InputFormat, ParseMode, PrecisionPolicy, TxQuarantine};
+pub use models::tx_record::{ClientId, TxId, TxRecordType, TxRow};
+
+// the reports of the cli
+pub use models::account_detail::AccountDetail;
+pub use models::output::OutputFormat;
+pub use models::restore::Restore;
+pub use models::statement::{Statement, StatementLine};
+pub use models::tx_dispute::{DisputeLine, DisputeLog};
+pub use models::tx_input::{InputOrder, TxInput};
+pub use models::tx_schedule::TxSchedule;
@@ -1,13 +0,0 @@
-pub const ACCOUNT_DIR: &str = "data/account";
-pub const ACCOUNT_BACKUP_DIR: &str = "data/account_backup";
-pub const TRANSACTION_DIR: &str = "data/transaction";
-pub const SUMMARY_DIR: &str = "data/summary";
-pub const FN_NEW: &str = "new";
-
-pub const TYPE_POS: usize = 0;
-pub const CLIENT_POS: usize = 1;
-pub const TX_POS: usize = 2;
-pub const AMOUNT_POS: usize = 3;
-
-pub const MAX_CSV_ROW_LEN: usize = 4;
-pub const MIN_CSV_ROW_LEN: usize = 3;
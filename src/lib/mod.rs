@@ -1,2 +0,0 @@
-pub mod constants;
-pub mod error;
\ No newline at end of file
@@ -1,28 +1,210 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-pub(crate) mod lib;
-pub(crate) mod models;
-pub(crate) mod tests;
-
-use models::processor::Processor;
+use parse::{
+    Account, AccountDetail, AppError, ClientId, Config, DisputeLog, DisputePolicy, InputFormat,
+    InputOrder, OutputFormat, ParseMode, PrecisionPolicy, Processor, Restore, Statement,
+    TxColumnMap, TxInput, TxRecordType, TxSchedule,
+};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// csv to parse
-    file: String,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// csv or ndjson files, directories or glob patterns to parse, - for stdin. .gz + .zst files are decompressed
+    #[clap(required_unless_present = "manifest")]
+    files: Vec<String>,
+
+    /// order the input files are processed in
+    #[clap(long, default_value = "name", possible_values = ["name", "mtime"])]
+    order: String,
+
+    /// file listing the input files, one per line, in the order they are processed
+    #[clap(long, conflicts_with = "files")]
+    manifest: Option<String>,
+
+    /// root of the system data, defaults to $PARSE_DATA_DIR or ./data
+    #[clap(long, global = true)]
+    data_dir: Option<String>,
+
+    /// apply input files even if they were already applied
+    #[clap(long)]
+    force: bool,
+
+    /// strict stops on the first malformed row, lenient quarantines it and carries on
+    #[clap(long, default_value = "strict", possible_values = ["strict", "lenient"])]
+    parse_mode: String,
+
+    /// format of every input file, by default taken from its extension (.ndjson + .jsonl are ndjson, anything else csv)
+    #[clap(long, possible_values = ["csv", "ndjson"])]
+    input_format: Option<String>,
+
+    /// what is done with amounts of more than 4 decimal places
+    #[clap(long, default_value = "round", possible_values = ["reject", "round", "truncate"])]
+    precision: String,
+
+    /// file of column aliases, each line a field (type, client, tx, amount) followed by its aliases
+    #[clap(long)]
+    columns: Option<String>,
+
+    /// skip input columns with no known name instead of refusing the file
+    #[clap(long)]
+    ignore_unknown_columns: bool,
+
+    /// file of fee + interest rules applied to every account at the end of the run, each line type,basis,value[,min[,max]]
+    #[clap(long)]
+    schedule: Option<String>,
+
+    /// period the schedule is applied for, e.g. 2022-03, defaults to the current month. a rule is applied once a period
+    #[clap(long)]
+    period: Option<String>,
+
+    /// how many times a transaction can be disputed again once part of it was resolved, 0 for never
+    #[clap(long, default_value = "0")]
+    redispute_limit: u32,
+
+    /// comma separated states of a transaction it can be disputed again from, the state of its latest event
+    #[clap(long, default_value = "resolve", use_value_delimiter = true, possible_values = ["dispute", "resolve", "chargeback"])]
+    redispute_after: Vec<String>,
+
+    /// who is recorded in the audit trail for changes of account state, defaults to $USER
+    #[clap(long)]
+    actor: Option<String>,
+
+    /// file the account balances or statement are written to, defaults to stdout
+    #[clap(long, global = true)]
+    output: Option<String>,
+
+    /// format the account balances or statement are written in
+    #[clap(long, global = true, default_value = "csv", possible_values = ["csv", "json", "ndjson"])]
+    format: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// write the transactions of a client in tx order, with their dispute state and running balances
+    Statement { client: ClientId },
+    /// write the dispute, resolve + chargeback events of a client, by tx in the order they were applied
+    Disputes { client: ClientId },
+    /// write the published account of a client
+    Show {
+        client: ClientId,
+        /// include the transactions under dispute
+        #[clap(long)]
+        disputes: bool,
+        /// include the backups of the account file
+        #[clap(long)]
+        backups: bool,
+        /// include the changes of account state
+        #[clap(long)]
+        audit: bool,
+    },
+    /// put accounts + their transaction history back as they were at a time, undoing the runs published after it
+    Restore {
+        /// rfc 3339 time, e.g. 2022-03-01T12:00:00Z, or epoch millis
+        #[clap(long)]
+        to: String,
+        /// only restore this client
+        #[clap(long)]
+        client: Option<ClientId>,
+    },
 }
 
 fn main() {
     let args = Args::parse();
-    let result = Processor::new(&args.file);
-    if result.is_err() {
-        result.err().unwrap().show();
-        return;
-    }
+    let config = Config::resolve(args.data_dir.as_deref());
+    let format = OutputFormat::from_string(&args.format).unwrap();
 
-    let p = result.unwrap();
-    if let Err(err) = p.process_data(false) {
+    let result = match &args.command {
+        Some(Command::Statement { client }) => {
+            Statement::write(*client, &config, args.output.as_deref(), format)
+        }
+        Some(Command::Disputes { client }) => {
+            DisputeLog::write(*client, &config, args.output.as_deref(), format)
+        }
+        Some(Command::Show {
+            client,
+            disputes,
+            backups,
+            audit,
+        }) => AccountDetail::load(*client, &config, *disputes, *backups, *audit)
+            .and_then(|detail| detail.write(args.output.as_deref(), format)),
+        Some(Command::Restore { to, client }) => Restore::parse_time(to)
+            .and_then(|m| Restore::run(m, *client, &config))
+            .map(|restored| {
+                for client_id in restored {
+                    eprintln!("{},restored", client_id);
+                }
+            }),
+        None => process(&args, &config, format),
+    };
+
+    // errors go to stderr, the exit code tells scripts the run failed
+    if let Err(err) = result {
         err.show();
+        std::process::exit(1);
+    }
+}
+
+fn process(args: &Args, config: &Config, format: OutputFormat) -> Result<(), AppError> {
+    let paths = match &args.manifest {
+        Some(manifest) => TxInput::from_manifest(manifest)?,
+        None => TxInput::resolve(&args.files, InputOrder::from_string(&args.order).unwrap())?,
+    };
+
+    let mut column_map = TxColumnMap::new();
+    if let Some(columns) = &args.columns {
+        column_map = TxColumnMap::from_file(columns)?;
+    }
+    column_map.set_ignore_unknown(args.ignore_unknown_columns);
+
+    let mut schedule = None;
+    if let Some(path) = &args.schedule {
+        let mut s = TxSchedule::from_file(path)?;
+        if let Some(period) = &args.period {
+            s.set_period(period);
+        }
+        schedule = Some(s);
+    }
+
+    let mut p = Processor::new_batch(paths, config)?;
+    p.set_force(args.force);
+    p.set_parse_mode(ParseMode::from_string(&args.parse_mode).unwrap());
+    p.set_column_map(column_map);
+    p.set_precision(PrecisionPolicy::from_string(&args.precision).unwrap());
+    p.set_schedule(schedule);
+    p.set_dispute_policy(DisputePolicy::new(
+        args.redispute_limit,
+        args.redispute_after
+            .iter()
+            .map(|s| TxRecordType::from_binary(s.as_bytes()))
+            .collect(),
+    ));
+    if let Some(actor) = &args.actor {
+        p.set_actor(actor);
+    }
+    p.set_input_format(
+        args.input_format
+            .as_deref()
+            .and_then(InputFormat::from_string),
+    );
+
+    let reports = p.process_data(false)?;
+    p.write_accounts(args.output.as_deref(), format)?;
+
+    // the stray files left out of the accounts
+    for warning in Account::unlisted(config)? {
+        warning.show();
+    }
+
+    // keep stdout for the account balances
+    for report in reports {
+        eprintln!("{}", report);
+        for duplicate in report.duplicates_to_string() {
+            eprintln!("{}", duplicate);
+        }
     }
+    Ok(())
 }
@@ -1,34 +1,100 @@
+use std::fmt;
 use std::fs;
+use std::path::Path;
 
+use chrono::Utc;
 use csv::ByteRecord;
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use super::tx_history::TxHistory;
 use super::tx_reader::TxReader;
-use super::tx_record::{TxConflict, TxRecordType};
+use super::tx_record::{ClientId, TxId, TxRecordType};
+use super::tx_rejection::{TxOutcome, TxRejectReason};
 use super::tx_writer::TxWriter;
-use crate::lib::constants::ACCOUNT_DIR;
-use crate::lib::error::AppError;
+use crate::common::config::Config;
+use crate::common::constants::SUMMARY_ROLLBACK_DIR;
+use crate::common::error::AppError;
 
 const PATH: &str = "model/account";
 
+// active accounts take every transaction. frozen ones refuse deposits + withdrawals but disputes go on,
+// locked ones (after a chargeback) refuse everything but admin transactions, and closed is for good.
+#[derive(PartialEq, Debug, Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountState {
+    #[default]
+    ACTIVE,
+    FROZEN,
+    LOCKED,
+    CLOSED,
+}
+
+impl AccountState {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "active" => Some(Self::ACTIVE),
+            "frozen" => Some(Self::FROZEN),
+            "locked" => Some(Self::LOCKED),
+            "closed" => Some(Self::CLOSED),
+            _ => None,
+        }
+    }
+
+    // why a non admin transaction is refused in this state, if it is
+    pub fn refuses(&self, tx_type: &TxRecordType) -> Option<TxRejectReason> {
+        match self {
+            Self::ACTIVE => None,
+            Self::FROZEN if tx_type.funds_type() => Some(TxRejectReason::ACCOUNT_FROZEN),
+            Self::FROZEN => None,
+            Self::LOCKED => Some(TxRejectReason::ACCOUNT_LOCKED),
+            Self::CLOSED => Some(TxRejectReason::ACCOUNT_CLOSED),
+        }
+    }
+
+    // the state an admin transaction moves to, none if it can't be applied in this state
+    pub fn next(&self, tx_type: &TxRecordType) -> Option<Self> {
+        match (tx_type, self) {
+            (TxRecordType::UNLOCK, Self::FROZEN | Self::LOCKED) => Some(Self::ACTIVE),
+            (TxRecordType::FREEZE, Self::ACTIVE) => Some(Self::FROZEN),
+            (TxRecordType::CLOSE, Self::ACTIVE | Self::FROZEN | Self::LOCKED) => Some(Self::CLOSED),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AccountState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            Self::ACTIVE => "active",
+            Self::FROZEN => "frozen",
+            Self::LOCKED => "locked",
+            Self::CLOSED => "closed",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+// the locked flag is kept for the account files + output from before account states:
+// it is set whenever the account is not active.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Account {
     #[serde(rename(deserialize = "client", serialize = "client"))]
-    pub client_id: u16,
+    pub client_id: ClientId,
     pub available: Decimal,
     pub held: Decimal,
     pub total: Decimal,
     pub locked: bool,
+    #[serde(default)]
+    pub state: AccountState,
 }
 
 impl Account {
-    pub fn new(client_id: u16, summary_dir: &str) -> Self {
+    pub fn new(client_id: ClientId, summary_dir: &str, config: &Config) -> Self {
         let mut user_opt = Self::load_from_file(client_id, summary_dir);
 
         if user_opt.is_none() {
-            user_opt = Self::load_from_file(client_id, ACCOUNT_DIR);
+            user_opt = Self::load_from_file(client_id, &config.account_dir());
         }
 
         if user_opt.is_none() {
@@ -38,184 +104,395 @@ impl Account {
                 held: Decimal::new(0, 0),
                 total: Decimal::new(0, 0),
                 locked: false,
+                state: AccountState::ACTIVE,
             };
         }
         user_opt.unwrap()
     }
 
-    fn load_from_file(client_id: u16, account_dir: &str) -> Option<Account> {
-        let file_path = &[account_dir, "/", &client_id.to_string(), ".csv"].join("");
-        let result = TxReader::new_reader(&file_path);
-        if result.is_err() {
-            return None;
+    // the published account of a client, if it has one
+    pub fn find(client_id: ClientId, config: &Config) -> Option<Account> {
+        Self::load_from_file(client_id, &config.account_dir())
+    }
+
+    // every published account, by client id
+    pub fn all(config: &Config) -> Result<Vec<Account>, AppError> {
+        let account_dir = config.account_dir();
+        if !Path::new(&account_dir).exists() {
+            return Ok(Vec::new());
         }
 
-        let mut reader = result.unwrap();
-        let mut byte_record = ByteRecord::new();
-        let result = reader.read_byte_record(&mut byte_record);
-        if result.is_err() {
-            return None;
+        let mut accounts = Vec::new();
+        for files in AccountPath::paths(&account_dir)? {
+            for entry in files {
+                let client_id = match Self::listed_client_id(&entry) {
+                    Some(client_id) => client_id,
+                    None => continue,
+                };
+                if let Some(account) = Self::load_from_file(client_id, &account_dir) {
+                    accounts.push(account);
+                }
+            }
         }
+        accounts.sort_by_key(|a| a.client_id);
+        Ok(accounts)
+    }
 
-        if byte_record.len() == 0 {
-            return None;
+    // every published account can be read, an error for the first one that can't
+    pub fn validate_all(config: &Config) -> Result<(), AppError> {
+        let account_dir = config.account_dir();
+        if !Path::new(&account_dir).exists() {
+            return Ok(());
+        }
+
+        for files in AccountPath::paths(&account_dir)? {
+            for entry in files {
+                if let Some(client_id) = Self::listed_client_id(&entry) {
+                    Self::read_from_file(client_id, &account_dir)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the files in the account dir that aren't named after a client, e.g. left by an editor.
+    // they are left out of every account, the cli warns about them.
+    pub fn unlisted(config: &Config) -> Result<Vec<AppError>, AppError> {
+        let account_dir = config.account_dir();
+        if !Path::new(&account_dir).exists() {
+            return Ok(Vec::new());
         }
 
-        let result = byte_record.deserialize::<Account>(None);
+        Ok(AccountPath::paths(&account_dir)?
+            .iter()
+            .flatten()
+            .filter_map(|entry| Self::client_id(&entry.file_name).err())
+            .collect())
+    }
+
+    // every client with an account staged in the summary dir or published, by client id
+    pub fn client_ids(summary_dir: &str, config: &Config) -> Result<Vec<ClientId>, AppError> {
+        let mut client_ids: Vec<ClientId> =
+            Self::all(config)?.iter().map(|a| a.client_id).collect();
+        client_ids.extend(Self::staged_client_ids(summary_dir)?);
+        client_ids.sort_unstable();
+        client_ids.dedup();
+        Ok(client_ids)
+    }
 
-        if result.is_err() {
+    // the clients of the accounts staged by a run
+    pub fn staged_client_ids(summary_dir: &str) -> Result<Vec<ClientId>, AppError> {
+        let mut client_ids = Vec::new();
+        for files in AccountPath::paths(summary_dir)? {
+            client_ids.extend(files.iter().filter_map(Self::listed_client_id));
+        }
+        Ok(client_ids)
+    }
+
+    fn load_from_file(client_id: ClientId, account_dir: &str) -> Option<Account> {
+        let file_path = &[account_dir, "/", &client_id.to_string(), ".csv"].join("");
+        if !Path::new(file_path).exists() {
             return None;
         }
+        Self::read_from_file(client_id, account_dir).ok()
+    }
 
-        Some(result.unwrap())
+    // an account file that can't be read is an error
+    fn read_from_file(client_id: ClientId, account_dir: &str) -> Result<Account, AppError> {
+        let file_path = &[account_dir, "/", &client_id.to_string(), ".csv"].join("");
+        let mut reader = TxReader::new_reader(file_path)?;
+        let mut byte_record = ByteRecord::new();
+        reader.read_byte_record(&mut byte_record).map_err(|e| {
+            AppError::parse(PATH, "read_from_file", &["00", file_path].join(" | "), e)
+        })?;
+
+        if byte_record.is_empty() {
+            return Err(AppError::parse(
+                PATH,
+                "read_from_file",
+                "01",
+                ["empty account file", file_path].join(" | "),
+            ));
+        }
+
+        // files from before account states could only be locked by a chargeback
+        let mut account = byte_record.deserialize::<Account>(None).map_err(|e| {
+            AppError::parse(PATH, "read_from_file", &["02", file_path].join(" | "), e)
+        })?;
+        if account.locked && account.state == AccountState::ACTIVE {
+            account.state = AccountState::LOCKED;
+        }
+        Ok(account)
+    }
+
+    pub fn set_state(&mut self, state: AccountState) {
+        self.state = state;
+        self.locked = state != AccountState::ACTIVE;
     }
 
     pub fn handle_tx(
         &mut self,
         tx_type: &TxRecordType,
-        tx_id: &u32,
+        tx_id: &TxId,
         amount: &Decimal,
         tx_history: &mut TxHistory,
-    ) {
-        match *tx_type {
-            TxRecordType::DEPOSIT => {
-                if self.locked {
-                    return;
-                }
+    ) -> Result<TxOutcome, AppError> {
+        if tx_type.admin_type() {
+            return Ok(self.handle_admin(tx_type));
+        }
+
+        if let Some(reason) = self.state.refuses(tx_type) {
+            return Ok(TxOutcome::REJECTED(reason));
+        }
+
+        let outcome = match *tx_type {
+            TxRecordType::DEPOSIT | TxRecordType::INTEREST => {
                 self.available += *amount;
                 self.total += *amount;
+                TxOutcome::APPLIED
             }
-            TxRecordType::WITHDRAW => {
-                if self.locked {
-                    return;
-                }
-                if self.available >= *amount {
-                    self.available -= *amount;
-                    self.total -= *amount;
+            TxRecordType::WITHDRAW | TxRecordType::FEE => {
+                if self.available < *amount {
+                    return Ok(TxOutcome::REJECTED(TxRejectReason::INSUFFICIENT_FUNDS));
                 }
+                self.available -= *amount;
+                self.total -= *amount;
+                TxOutcome::APPLIED
             }
+            // the amount of a dispute, resolve or chargeback is the part of the tx it is about, zero for all of it
             TxRecordType::DISPUTE => {
-                if self.locked {
-                    return;
-                }
-
-                let key = TxConflict::key(tx_id);
-                if tx_history.contains_conflict_key(&key) {
-                    return;
-                }
-
-                if let Some(tx) = tx_history.get_tx(tx_id) {
-                    if tx.type_id == TxRecordType::DEPOSIT {
-                        self.held += tx.amount;
-                        self.available -= tx.amount;
+                let tx = match tx_history.get_tx(tx_id)? {
+                    Some(tx) => tx,
+                    None => return Ok(TxOutcome::REJECTED(TxRejectReason::UNKNOWN_TX)),
+                };
+
+                match tx_history.dispute(&tx, amount)? {
+                    Ok(amount) => {
+                        if tx.type_id.credit_type() {
+                            self.held += amount;
+                            self.available -= amount;
+                        }
+                        // if the dispute is on a withdrawal we do nothing.
+                        // withdrawal chargebacks are handled like debit card/atm chargebacks.
+                        // debit card/atm chargebacks only revert transaction on the chargeback.
+                        TxOutcome::APPLIED
                     }
-                    // if the dispute is on a withdrawal we do nothing.
-                    // withdrawal chargebacks are handled like debit card/atm chargebacks.
-                    // debit card/atm chargebacks only revert transaction on the chargeback.
-
-                    tx_history.set_conflict(
-                        &tx.tx_id,
-                        &tx.type_id,
-                        &TxRecordType::DISPUTE,
-                        &tx.amount,
-                    );
+                    Err(reason) => TxOutcome::REJECTED(reason),
                 }
             }
-            TxRecordType::RESOLVE => {
-                if self.locked {
-                    return;
-                }
-
-                if let Some(conflict) = &mut tx_history.get_conflict(tx_id) {
-                    if conflict.state_id != TxRecordType::DISPUTE {
-                        return;
-                    }
-
-                    if conflict.type_id == TxRecordType::DEPOSIT {
-                        self.held -= conflict.amount;
-                        self.available += conflict.amount;
+            TxRecordType::RESOLVE => match tx_history.settle(tx_id, tx_type, amount)? {
+                Ok((type_id, amount)) => {
+                    if type_id.credit_type() {
+                        self.held -= amount;
+                        self.available += amount;
                     }
                     // if the resolve is on a withdrawal we do nothing.
                     // withdrawal chargebacks are handled like debit card/atm chargebacks.
                     // debit card/atm chargebacks only revert transaction on the chargeback.
-
-                    conflict.state_id = TxRecordType::RESOLVE;
-                    tx_history.set_conflict(
-                        &conflict.tx_id,
-                        &conflict.type_id,
-                        &conflict.state_id,
-                        &conflict.amount,
-                    );
+                    TxOutcome::APPLIED
                 }
-            }
-            TxRecordType::CHARGEBACK => {
-                if self.locked {
-                    return;
-                }
-
-                if let Some(conflict) = &mut tx_history.get_conflict(tx_id) {
-                    if conflict.state_id != TxRecordType::DISPUTE {
-                        return;
+                Err(reason) => TxOutcome::REJECTED(reason),
+            },
+            TxRecordType::CHARGEBACK => match tx_history.settle(tx_id, tx_type, amount)? {
+                Ok((type_id, amount)) => {
+                    if type_id.credit_type() {
+                        self.held -= amount;
+                        self.total -= amount;
+                    } else if type_id.debit_type() {
+                        // if the chargeback is on a withdrawal reimburse the client the amount charged back.
+                        self.available += amount;
+                        self.total += amount;
                     }
+                    self.set_state(AccountState::LOCKED);
+                    TxOutcome::APPLIED
+                }
+                Err(reason) => TxOutcome::REJECTED(reason),
+            },
+            _ => TxOutcome::REJECTED(TxRejectReason::UNKNOWN_TYPE),
+        };
+        Ok(outcome)
+    }
 
-                    if conflict.type_id == TxRecordType::DEPOSIT {
-                        self.held -= conflict.amount;
-                        self.total -= conflict.amount;
-                    } else if conflict.type_id == TxRecordType::WITHDRAW {
-                        // if the chargeback is on a withdrawal reimburse the client the amount of the withdrawal.
-                        self.available += conflict.amount;
-                        self.total += conflict.amount;
-                    }
+    // an account is only closed once it holds no funds
+    fn handle_admin(&mut self, tx_type: &TxRecordType) -> TxOutcome {
+        let next = match self.state.next(tx_type) {
+            Some(next) => next,
+            None => return TxOutcome::REJECTED(TxRejectReason::INVALID_TRANSITION),
+        };
 
-                    conflict.state_id = TxRecordType::CHARGEBACK;
-                    tx_history.set_conflict(
-                        &conflict.tx_id,
-                        &conflict.type_id,
-                        &conflict.state_id,
-                        &conflict.amount,
-                    );
-                    self.locked = true;
-                }
-            }
-            _ => {}
+        let zero = Decimal::new(0, 0);
+        if next == AccountState::CLOSED && (self.total != zero || self.held != zero) {
+            return TxOutcome::REJECTED(TxRejectReason::NON_ZERO_BALANCE);
         }
+
+        self.set_state(next);
+        TxOutcome::APPLIED
     }
 
-    pub fn write_to_csv(&self, summary_dir: &str) -> Result<(), AppError> {
+    // "client,available,held,total,locked,state", with the amounts at 4 decimal places
+    pub fn to_byte_record(&self) -> ByteRecord {
         let available_str = format!("{:.4}", self.available);
         let held_str = format!("{:.4}", self.held);
         let total_str = format!("{:.4}", self.total);
 
-        let byte_record = ByteRecord::from(
+        ByteRecord::from(
             &[
                 &self.client_id.to_string(),
                 &available_str,
                 &held_str,
                 &total_str,
                 &self.locked.to_string(),
+                &self.state.to_string(),
             ][..],
-        );
+        )
+    }
 
+    pub fn write_to_csv(&self, summary_dir: &str) -> Result<(), AppError> {
         let mut tx_writer = TxWriter::new(summary_dir, &self.client_id.to_string())?;
-        tx_writer.write_records(&vec![byte_record])?;
+        tx_writer.write_records(&vec![self.to_byte_record()])?;
+        Ok(())
+    }
+
+    // move the staged account + transaction history of a client into the official data.
+    // the rollback marker is written before any official data is touched.
+    pub fn publish(summary_dir: &str, file_name: &str, config: &Config) -> Result<(), AppError> {
+        let client_id = Self::client_id(file_name)?;
+        let staged_file = [summary_dir, file_name].join("/");
+        let account_file = [&config.account_dir(), file_name].join("/");
+
+        // the account + history backups of a publish share its time
+        let millis = Utc::now().timestamp_millis();
+        let mut backup_file = String::new();
+        if Path::new(&account_file).exists() {
+            backup_file = Self::backup_path(&client_id, millis, config);
+            fs::copy(&account_file, &backup_file)
+                .map_err(|e| AppError::io(PATH, "publish", "00", e))?;
+        }
+
+        let rollback_dir = [summary_dir, SUMMARY_ROLLBACK_DIR].join("/");
+        fs::create_dir_all(&rollback_dir).map_err(|e| AppError::io(PATH, "publish", "01", e))?;
+        fs::write([&rollback_dir, file_name].join("/"), &backup_file)
+            .map_err(|e| AppError::io(PATH, "publish", "02", e))?;
+
+        let history_backup_file = TxHistory::backup_path(&client_id, millis, config);
+        TxHistory::publish(&client_id, summary_dir, &history_backup_file, config)?;
+
+        fs::rename(&staged_file, &account_file)
+            .map_err(|e| AppError::io(PATH, "publish", "03", e))?;
+        Ok(())
+    }
+
+    // undo a publish by restoring the backup recorded in the rollback marker.
+    // if there was no account before the run, the account file is removed.
+    pub fn rollback(summary_dir: &str, file_name: &str, config: &Config) -> Result<(), AppError> {
+        let client_id = Self::client_id(file_name)?;
+        let marker_file = [summary_dir, SUMMARY_ROLLBACK_DIR, file_name].join("/");
+        let account_file = [&config.account_dir(), file_name].join("/");
+
+        let backup_file = fs::read_to_string(&marker_file)
+            .map_err(|e| AppError::io(PATH, "rollback", "00", e))?;
+
+        TxHistory::rollback(&client_id, summary_dir, config)?;
+
+        if backup_file.is_empty() {
+            if Path::new(&account_file).exists() {
+                fs::remove_file(&account_file)
+                    .map_err(|e| AppError::io(PATH, "rollback", "01", e))?;
+            }
+        } else {
+            fs::copy(&backup_file, &account_file)
+                .map_err(|e| AppError::io(PATH, "rollback", "02", e))?;
+        }
+
+        fs::remove_file(&marker_file).map_err(|e| AppError::io(PATH, "rollback", "03", e))?;
         Ok(())
     }
+
+    // "<client>_<millis>.csv" in the backup dir
+    pub fn backup_path(client_id: &ClientId, millis: i64, config: &Config) -> String {
+        [
+            &config.account_backup_dir(),
+            "/",
+            &client_id.to_string(),
+            "_",
+            &millis.to_string(),
+            ".csv",
+        ]
+        .join("")
+    }
+
+    // put the account file of a client back to a backup, or remove it if the client had no account then.
+    // the current account file is backed up first, so the restore can be undone in turn.
+    pub fn restore(
+        client_id: &ClientId,
+        backup_file: Option<&str>,
+        millis: i64,
+        config: &Config,
+    ) -> Result<(), AppError> {
+        let account_file = [&config.account_dir(), "/", &client_id.to_string(), ".csv"].join("");
+
+        if Path::new(&account_file).exists() {
+            fs::copy(&account_file, Self::backup_path(client_id, millis, config))
+                .map_err(|e| AppError::io(PATH, "restore", "00", e))?;
+        }
+
+        match backup_file {
+            Some(backup_file) => {
+                fs::create_dir_all(config.account_dir())
+                    .map_err(|e| AppError::io(PATH, "restore", "01", e))?;
+                fs::copy(backup_file, &account_file)
+                    .map_err(|e| AppError::io(PATH, "restore", "02", e))?;
+            }
+            None => {
+                if Path::new(&account_file).exists() {
+                    fs::remove_file(&account_file)
+                        .map_err(|e| AppError::io(PATH, "restore", "03", e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // roll back every client with a rollback marker in the summary dir.
+    pub fn rollback_all(summary_dir: &str, config: &Config) -> Result<(), AppError> {
+        let rollback_dir = [summary_dir, SUMMARY_ROLLBACK_DIR].join("/");
+        if !Path::new(&rollback_dir).exists() {
+            return Ok(());
+        }
+
+        for files in AccountPath::paths(&rollback_dir)? {
+            for entry in files {
+                Self::rollback(summary_dir, &entry.file_name, config)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn client_id(file_name: &str) -> Result<ClientId, AppError> {
+        file_name
+            .replace(".csv", "")
+            .parse::<ClientId>()
+            .map_err(|e| AppError::parse(PATH, "client_id", &["00", file_name].join(" | "), e))
+    }
+
+    // a file in an account dir that isn't named after a client is skipped, see unlisted
+    fn listed_client_id(entry: &AccountPath) -> Option<ClientId> {
+        Self::client_id(&entry.file_name).ok()
+    }
 }
 
 #[derive(Debug)]
 pub struct AccountPath {
-    pub update_file: bool,
-    pub file_path: String,
+    pub dir: String,
     pub file_name: String,
 }
 
 impl AccountPath {
-    pub fn paths(update_file: bool, dir: &str) -> Result<Vec<Vec<AccountPath>>, AppError> {
+    pub fn paths(dir: &str) -> Result<Vec<Vec<AccountPath>>, AppError> {
         let mut row = 0;
         let mut paths: Vec<Vec<AccountPath>> = Vec::new();
 
-        let p =
-            fs::read_dir(dir).map_err(|e| AppError::new(PATH, "paths", "00", &e.to_string()))?;
+        let p = fs::read_dir(dir).map_err(|e| AppError::io(PATH, "paths", "00", e))?;
 
         let mut v = Vec::new();
         for e in p {
@@ -228,7 +505,6 @@ impl AccountPath {
                 continue;
             }
 
-            let file_path = path.path().display().to_string();
             let file_name = path
                 .path()
                 .file_name()
@@ -238,8 +514,7 @@ impl AccountPath {
                 .to_string();
 
             v.push(AccountPath {
-                update_file,
-                file_path,
+                dir: dir.to_string(),
                 file_name,
             });
 
@@ -251,7 +526,7 @@ impl AccountPath {
             }
         }
 
-        if v.len() > 0 {
+        if !v.is_empty() {
             paths.push(v);
         }
 
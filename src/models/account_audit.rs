@@ -0,0 +1,181 @@
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::account::AccountState;
+use super::tx_record::{ClientId, TxId, TxRow};
+use crate::common::config::Config;
+use crate::common::constants::{AUDIT_FILE, SUMMARY_AUDIT_DIR};
+use crate::common::error::AppError;
+use crate::common::file;
+
+const PATH: &str = "model/account_audit";
+const STAGED_FILE: &str = "staged.csv";
+
+const HEADER: [&str; 9] = [
+    "time", "actor", "file", "line", "client", "tx", "action", "from", "to",
+];
+
+// a change of account state: who made it, when, from which input row + what it changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountAudit {
+    pub time: String,
+    pub actor: String,
+    pub file: String,
+    pub line: u64,
+    pub client: ClientId,
+    pub tx: TxId,
+    // the transaction type that changed the state
+    pub action: String,
+    pub from: String,
+    pub to: String,
+}
+
+// the state changes of a run are staged by the workers that make them, stamped with the actor + input file
+// once the run is read, then appended to the audit trail in the data root once its accounts are published.
+pub struct AccountAuditLog;
+
+impl AccountAuditLog {
+    // staged rows are "millis,source,line,from,to,type,client,tx,amount", source being the position of the input file
+    pub fn stage(
+        summary_dir: &str,
+        name: &str,
+        rows: &[(i64, TxRow, AccountState, AccountState)],
+    ) -> Result<(), AppError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let dir = [summary_dir, SUMMARY_AUDIT_DIR].join("/");
+        fs::create_dir_all(&dir).map_err(|e| AppError::io(PATH, "stage", "00", e))?;
+
+        let data: String = rows
+            .iter()
+            .map(|(millis, row, from, to)| {
+                let string =
+                    TxRow::to_string(&row.type_id, &row.client_id, &row.tx_id, &row.amount);
+                format!(
+                    "{},{},{},{},{},{}\n",
+                    millis, row.source_id, row.line, from, to, string
+                )
+            })
+            .collect();
+        fs::write([&dir, "/", name, ".csv"].join(""), data)
+            .map_err(|e| AppError::io(PATH, "stage", "01", e))
+    }
+
+    // merge the rows staged by the workers in input order, with the actor of the run + the input file names
+    pub fn seal(
+        summary_dir: &str,
+        source_csv_paths: &[String],
+        actor: &str,
+    ) -> Result<(), AppError> {
+        let dir = [summary_dir, SUMMARY_AUDIT_DIR].join("/");
+        if !Path::new(&dir).exists() {
+            return Ok(());
+        }
+
+        let p = fs::read_dir(&dir).map_err(|e| AppError::io(PATH, "seal", "00", e))?;
+
+        let mut keyed = Vec::new();
+        for e in p.flatten() {
+            if e.file_name() == STAGED_FILE {
+                continue;
+            }
+
+            let string =
+                fs::read_to_string(e.path()).map_err(|e| AppError::io(PATH, "seal", "01", e))?;
+            for line in string.lines() {
+                let v: Vec<&str> = line.splitn(6, ',').collect();
+                if v.len() != 6 {
+                    continue;
+                }
+
+                let millis = v[0].parse::<i64>().unwrap_or(0);
+                let source_id = v[1].parse::<usize>().unwrap_or(0);
+                let row = TxRow::new_from_string(v[5])?;
+                let audit = AccountAudit {
+                    time: Utc.timestamp_millis(millis).to_rfc3339(),
+                    actor: actor.to_string(),
+                    file: source_csv_paths.get(source_id).cloned().unwrap_or_default(),
+                    line: v[2].parse::<u64>().unwrap_or(0),
+                    client: row.client_id,
+                    tx: row.tx_id,
+                    action: row.type_id.to_string(),
+                    from: v[3].to_string(),
+                    to: v[4].to_string(),
+                };
+                keyed.push((source_id, audit));
+            }
+        }
+
+        keyed.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.line.cmp(&b.1.line)));
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path([&dir, "/", STAGED_FILE].join(""))
+            .map_err(|e| AppError::io(PATH, "seal", "02", e))?;
+        for (_, audit) in keyed {
+            writer
+                .serialize(audit)
+                .map_err(|e| AppError::io(PATH, "seal", "03", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| AppError::io(PATH, "seal", "04", e))
+    }
+
+    // append the sealed rows of a run, once its accounts are all published.
+    // the rows are appended once, so recovery can run it again.
+    pub fn commit(summary_dir: &str, config: &Config) -> Result<(), AppError> {
+        let staged_path = [summary_dir, SUMMARY_AUDIT_DIR, STAGED_FILE].join("/");
+        if !Path::new(&staged_path).exists() {
+            return Ok(());
+        }
+
+        let data = fs::read(&staged_path).map_err(|e| AppError::io(PATH, "commit", "00", e))?;
+        if !data.is_empty() {
+            fs::create_dir_all(config.audit_dir())
+                .map_err(|e| AppError::io(PATH, "commit", "01", e))?;
+
+            file::append_once(
+                &Self::path(config),
+                &[&staged_path, ".len"].join(""),
+                Some(&HEADER.join(",")),
+                &String::from_utf8_lossy(&data),
+            )
+            .map_err(|e| AppError::io(PATH, "commit", "02", e))?;
+        }
+
+        fs::remove_file(&staged_path).map_err(|e| AppError::io(PATH, "commit", "03", e))?;
+        let _ = fs::remove_file([&staged_path, ".len"].join(""));
+        Ok(())
+    }
+
+    // the audit trail, oldest first. one client, or every client.
+    pub fn read(
+        client_id: Option<ClientId>,
+        config: &Config,
+    ) -> Result<Vec<AccountAudit>, AppError> {
+        let audit_path = Self::path(config);
+        if !Path::new(&audit_path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut reader =
+            csv::Reader::from_path(&audit_path).map_err(|e| AppError::io(PATH, "read", "00", e))?;
+        let mut audits = Vec::new();
+        for result in reader.deserialize::<AccountAudit>() {
+            let audit = result.map_err(|e| AppError::parse(PATH, "read", "01", e))?;
+            if client_id.is_none() || client_id == Some(audit.client) {
+                audits.push(audit);
+            }
+        }
+        Ok(audits)
+    }
+
+    pub fn path(config: &Config) -> String {
+        [&config.audit_dir(), "/", AUDIT_FILE].join("")
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::{TimeZone, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use super::account::Account;
+use super::account_audit::{AccountAudit, AccountAuditLog};
+use super::account_output::{AccountRow, HEADER as ACCOUNT_HEADER};
+use super::output::{Output, OutputFormat};
+use super::store::Store;
+use super::tx_history::TxHistory;
+use super::tx_record::{ClientId, TxConflict, TxId};
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/account_detail";
+
+const DISPUTE_HEADER: [&str; 3] = ["tx", "type", "amount"];
+const BACKUP_HEADER: [&str; 2] = ["file", "time"];
+const AUDIT_HEADER: [&str; 9] = [
+    "time", "actor", "file", "line", "client", "tx", "action", "from", "to",
+];
+
+// a transaction under dispute, with the part of its amount not yet resolved or charged back
+#[derive(Debug, Serialize)]
+pub struct DisputeRow {
+    pub tx: TxId,
+    #[serde(rename = "type")]
+    pub type_id: String,
+    pub amount: String,
+}
+
+// a copy of the account file taken before a run published over it
+#[derive(Debug, Serialize)]
+pub struct BackupRow {
+    pub file: String,
+    pub time: String,
+}
+
+// one published account, with its open disputes, backups + state changes when asked for.
+// the lists are left out of the output when they were not asked for.
+#[derive(Debug, Serialize)]
+pub struct AccountDetail {
+    pub account: AccountRow,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disputes: Option<Vec<DisputeRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backups: Option<Vec<BackupRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit: Option<Vec<AccountAudit>>,
+}
+
+impl AccountDetail {
+    pub fn load(
+        client_id: ClientId,
+        config: &Config,
+        disputes: bool,
+        backups: bool,
+        audit: bool,
+    ) -> Result<Self, AppError> {
+        Store::check(config)?;
+
+        let account = Account::find(client_id, config).ok_or_else(|| {
+            AppError::rule(
+                PATH,
+                "load",
+                "00",
+                ["unknown client", &client_id.to_string()].join(" | "),
+            )
+        })?;
+
+        Ok(Self {
+            account: AccountRow::new(&account),
+            disputes: if disputes {
+                Some(Self::open_disputes(client_id, config)?)
+            } else {
+                None
+            },
+            backups: if backups {
+                Some(Self::backups(client_id, config)?)
+            } else {
+                None
+            },
+            audit: if audit {
+                Some(AccountAuditLog::read(Some(client_id), config)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    // stdout when there is no output path
+    pub fn write(&self, output: Option<&str>, format: OutputFormat) -> Result<(), AppError> {
+        let mut out = Output::target(output)?;
+        match format {
+            // the account, then each list with its own header after a blank line
+            OutputFormat::CSV => {
+                Output::write_rows(&ACCOUNT_HEADER, &[&self.account], &mut out, format)?;
+                if let Some(disputes) = &self.disputes {
+                    Self::blank_line(&mut out)?;
+                    Output::write_rows(&DISPUTE_HEADER, disputes, &mut out, format)?;
+                }
+                if let Some(backups) = &self.backups {
+                    Self::blank_line(&mut out)?;
+                    Output::write_rows(&BACKUP_HEADER, backups, &mut out, format)?;
+                }
+                if let Some(audit) = &self.audit {
+                    Self::blank_line(&mut out)?;
+                    Output::write_rows(&AUDIT_HEADER, audit, &mut out, format)?;
+                }
+                Ok(())
+            }
+            OutputFormat::JSON | OutputFormat::NDJSON => Output::write_value(self, out, format),
+        }
+    }
+
+    // by tx id
+    fn open_disputes(client_id: ClientId, config: &Config) -> Result<Vec<DisputeRow>, AppError> {
+        if !Path::new(&TxHistory::committed_path(&client_id, config)).exists() {
+            return Ok(Vec::new());
+        }
+
+        let db = TxHistory::open_committed(&client_id, config)?;
+        let mut disputes = Vec::new();
+        for entry in db.scan_prefix(b"c_") {
+            let (_, value) =
+                entry.map_err(|e| AppError::storage(PATH, "open_disputes", "00", e))?;
+            let parts = TxConflict::list_from_string(&String::from_utf8_lossy(&value))?;
+            let disputed = TxConflict::disputed(&parts);
+            if let Some(conflict) = parts.first() {
+                if !disputed.is_zero() {
+                    disputes.push(DisputeRow {
+                        tx: conflict.tx_id,
+                        type_id: conflict.type_id.to_string(),
+                        amount: format!("{:.4}", disputed),
+                    });
+                }
+            }
+        }
+        disputes.sort_by_key(|d| d.tx);
+        Ok(disputes)
+    }
+
+    // backups are named "<client>_<millis>.csv", oldest first
+    fn backups(client_id: ClientId, config: &Config) -> Result<Vec<BackupRow>, AppError> {
+        let backup_dir = config.account_backup_dir();
+        if !Path::new(&backup_dir).exists() {
+            return Ok(Vec::new());
+        }
+
+        let p = fs::read_dir(&backup_dir).map_err(|e| AppError::io(PATH, "backups", "00", e))?;
+
+        let prefix = [&client_id.to_string(), "_"].join("");
+        let mut backups = Vec::new();
+        for e in p.flatten() {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let millis = file_name
+                .strip_prefix(&prefix)
+                .and_then(|s| s.strip_suffix(".csv"))
+                .and_then(|s| s.parse::<i64>().ok());
+            if let Some(millis) = millis {
+                backups.push((millis, e.path().display().to_string()));
+            }
+        }
+        backups.sort();
+
+        Ok(backups
+            .into_iter()
+            .map(|(millis, file)| BackupRow {
+                file,
+                time: Utc.timestamp_millis(millis).to_rfc3339(),
+            })
+            .collect())
+    }
+
+    fn blank_line(out: &mut impl Write) -> Result<(), AppError> {
+        writeln!(out).map_err(|e| AppError::io(PATH, "blank_line", "00", e))
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Serialize;
+use std::io::Write;
+
+use super::account::Account;
+use super::output::{Output, OutputFormat};
+use super::tx_record::ClientId;
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+pub const HEADER: [&str; 6] = ["client", "available", "held", "total", "locked", "state"];
+
+// an account as written out, the amounts are strings at 4 decimal places.
+// the state is last, so the columns before it are as they were before account states.
+#[derive(Debug, Serialize)]
+pub struct AccountRow {
+    pub client: ClientId,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+    pub state: String,
+}
+
+impl AccountRow {
+    pub fn new(account: &Account) -> Self {
+        Self {
+            client: account.client_id,
+            available: format!("{:.4}", account.available),
+            held: format!("{:.4}", account.held),
+            total: format!("{:.4}", account.total),
+            locked: account.locked,
+            state: account.state.to_string(),
+        }
+    }
+}
+
+// the output stage: every published account, sorted by client id.
+// the accounts are written by a single writer, so the output is the same from run to run.
+pub struct AccountOutput;
+
+impl AccountOutput {
+    // stdout when there is no output path
+    pub fn write(
+        config: &Config,
+        output: Option<&str>,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        let accounts = Account::all(config)?;
+        Self::write_accounts(&accounts, Output::target(output)?, format)
+    }
+
+    pub fn write_accounts(
+        accounts: &[Account],
+        out: impl Write,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        let rows: Vec<AccountRow> = accounts.iter().map(AccountRow::new).collect();
+        Output::write_rows(&HEADER, &rows, out, format)
+    }
+}
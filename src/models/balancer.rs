@@ -1,13 +1,18 @@
+use chrono::Utc;
 use crossbeam_channel::{bounded, select, unbounded, Receiver, Sender};
 use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
-use super::account::Account;
+use super::account::{Account, AccountState};
+use super::account_audit::AccountAuditLog;
 use super::tx_cluster::TxCluster;
 use super::tx_history::TxHistory;
-use super::tx_record::TxRow;
-use crate::lib::error::AppError;
+use super::tx_record::{ClientId, TxRow};
+use super::tx_rejection::{TxOutcome, TxRejectReason, TxRejectionLog};
+use super::tx_transfer::TxTransfer;
+use crate::common::config::Config;
+use crate::common::error::AppError;
 
 const MAX_NUM_RECORDS: usize = 10;
 const THREAD_SLEEP_DURATION: u64 = 250;
@@ -15,20 +20,34 @@ const NUM_WORKERS: u16 = 32;
 
 const PATH: &str = "model/balancer";
 
+// the rows of a client a worker applies
+type ClientRows = (ClientId, Vec<TxRow>);
+
+// what the manager + workers are sent. a sync is answered once everything sent before it is applied.
+// a transfer, or a dispute, resolve or chargeback on one, goes with its sender + recipient.
+enum Packet<T> {
+    Data(T),
+    Transfer(TxRow, ClientId, ClientId),
+    Sync,
+    Stop,
+}
+
 pub struct Balancer {
     started: bool,
     summary_dir: String,
-    tx: Sender<Option<TxCluster>>,
+    config: Config,
+    tx: Sender<Packet<TxCluster>>,
     rx: Receiver<Result<(), AppError>>,
 }
 
 impl Balancer {
-    pub fn new(summary_dir: &str) -> Self {
+    pub fn new(summary_dir: &str, config: &Config) -> Self {
         let (tx, _) = bounded(0);
         let (_, rx) = bounded(0);
         Self {
             started: false,
             summary_dir: summary_dir.to_string(),
+            config: config.clone(),
             tx,
             rx,
         }
@@ -43,9 +62,19 @@ impl Balancer {
     }
 
     pub fn add(&self, tx_cluster: TxCluster) -> Result<(), AppError> {
+        self.send(Packet::Data(tx_cluster), "add")
+    }
+
+    // a row touching two clients. it is applied after every row added before it,
+    // without holding up the rows of the other clients.
+    pub fn transfer(&self, tx_row: TxRow, from: ClientId, to: ClientId) -> Result<(), AppError> {
+        self.send(Packet::Transfer(tx_row, from, to), "transfer")
+    }
+
+    fn send(&self, packet: Packet<TxCluster>, method: &str) -> Result<(), AppError> {
         self.tx
-            .send(Some(tx_cluster))
-            .map_err(|e| AppError::new(PATH, "add", "00", &e.to_string()))?;
+            .send(packet)
+            .map_err(|e| AppError::concurrency(PATH, method, "00", e.to_string()))?;
         loop {
             if self.tx.len() >= MAX_NUM_RECORDS {
                 thread::sleep(Duration::from_millis(THREAD_SLEEP_DURATION));
@@ -63,7 +92,7 @@ impl Balancer {
         }
 
         self.started = false;
-        self.tx.send(None).unwrap();
+        self.tx.send(Packet::Stop).unwrap();
         if let Ok(result) = self.rx.recv() {
             if result.is_err() {
                 return Err(result.err().unwrap());
@@ -80,7 +109,7 @@ impl Balancer {
         self.tx = parent_tx;
         self.rx = parent_rx;
 
-        let mut manager = LoadManager::new(&self.summary_dir, child_tx, child_rx);
+        let mut manager = LoadManager::new(&self.summary_dir, &self.config, child_tx, child_rx);
         thread::spawn(move || {
             for _ in 0..NUM_WORKERS - 1 {
                 manager.spawn_worker();
@@ -94,25 +123,28 @@ impl Balancer {
 
 struct LoadManager {
     tx: Sender<Result<(), AppError>>,
-    rx: Receiver<Option<TxCluster>>,
+    rx: Receiver<Packet<TxCluster>>,
     summary_dir: String,
+    config: Config,
     num_workers: u16,
     worker_id_ptr: u16,
-    worker_map: HashMap<u16, u16>,
-    worker_tx_channels: Vec<Sender<Option<(u16, Vec<TxRow>)>>>,
+    worker_map: HashMap<ClientId, u16>,
+    worker_tx_channels: Vec<Sender<Packet<ClientRows>>>,
     worker_rx_channels: Vec<Receiver<Result<u16, AppError>>>,
 }
 
 impl LoadManager {
     fn new(
         summary_dir: &str,
+        config: &Config,
         tx: Sender<Result<(), AppError>>,
-        rx: Receiver<Option<TxCluster>>,
+        rx: Receiver<Packet<TxCluster>>,
     ) -> Self {
         Self {
             tx,
             rx,
             summary_dir: summary_dir.to_string(),
+            config: config.clone(),
             num_workers: 0,
             worker_id_ptr: 0,
             worker_map: HashMap::new(),
@@ -126,25 +158,14 @@ impl LoadManager {
             select! {
                 recv(self.rx) -> packet => {
                     if let Ok(block) = packet {
-                        if let Some(tx_cluster) = block {
+                        if let Packet::Data(tx_cluster) = block {
                             for (client_id, tx_rows) in tx_cluster.tx_row_map {
-                                if self.worker_map.contains_key(&client_id) {
-                                    let worker_id = self.worker_map.get(&client_id).unwrap().clone();
-                                    let tx = self.worker_tx_channels.get(worker_id as usize).unwrap();
-                                    tx.send(Some((client_id, tx_rows))).unwrap();
-                                } else {
-                                    let worker_id_ptr = self.worker_id_ptr.clone();
-                                    let tx = self.worker_tx_channels.get(worker_id_ptr as usize).unwrap();
-                                    tx.send(Some((client_id, tx_rows))).unwrap();
-
-                                    self.worker_map.insert(client_id.clone(),worker_id_ptr.clone());
-                                    if (self.worker_id_ptr as usize) == self.worker_tx_channels.len() - 1 {
-                                        self.worker_id_ptr = 0;
-                                    } else {
-                                        self.worker_id_ptr += 1;
-                                    }
-                                }
+                                let worker_id = self.worker_id(client_id);
+                                let tx = self.worker_tx_channels.get(worker_id as usize).unwrap();
+                                tx.send(Packet::Data((client_id, tx_rows))).unwrap();
                             }
+                        } else if let Packet::Transfer(tx_row, from, to) = block {
+                            self.transfer(tx_row, from, to);
                         } else {
                             self.shutdown();
                             return;
@@ -158,14 +179,58 @@ impl LoadManager {
         }
     }
 
+    // the worker of a client, a new client goes to the next worker in turn
+    fn worker_id(&mut self, client_id: ClientId) -> u16 {
+        if let Some(worker_id) = self.worker_map.get(&client_id) {
+            return *worker_id;
+        }
+
+        let worker_id = self.worker_id_ptr;
+        self.worker_map.insert(client_id, worker_id);
+        if (self.worker_id_ptr as usize) == self.worker_tx_channels.len() - 1 {
+            self.worker_id_ptr = 0;
+        } else {
+            self.worker_id_ptr += 1;
+        }
+        worker_id
+    }
+
+    // the recipient moves to the worker of the sender, so the row comes after the rows of both sent before it.
+    // the worker it leaves is drained first. the accounts + histories are on disk, so nothing else moves.
+    fn transfer(&mut self, tx_row: TxRow, from: ClientId, to: ClientId) {
+        let worker_id = self.worker_id(from);
+        if let Some(to_worker_id) = self.worker_map.get(&to).copied() {
+            if to_worker_id != worker_id {
+                self.sync(to_worker_id);
+            }
+        }
+        self.worker_map.insert(to, worker_id);
+
+        let tx = self.worker_tx_channels.get(worker_id as usize).unwrap();
+        tx.send(Packet::Transfer(tx_row, from, to)).unwrap();
+    }
+
+    // a worker answers a sync once its queue is drained
+    fn sync(&self, worker_id: u16) {
+        self.worker_tx_channels[worker_id as usize]
+            .send(Packet::Sync)
+            .unwrap();
+        let _ = self.worker_rx_channels[worker_id as usize].recv();
+    }
+
     fn shutdown(&mut self) {
         for t in &self.worker_tx_channels {
-            t.send(None).unwrap();
+            t.send(Packet::Stop).unwrap();
         }
+        let mut result = Ok(());
         for r in &self.worker_rx_channels {
-            let _ = r.recv().unwrap();
+            if let Ok(Err(err)) = r.recv() {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
         }
-        let _ = self.tx.send(Ok(()));
+        let _ = self.tx.send(result);
     }
 
     fn spawn_worker(&mut self) {
@@ -176,7 +241,7 @@ impl LoadManager {
         let wid = self.num_workers;
         let tx = worker_tx.clone();
         let rx = worker_rx.clone();
-        let mut worker = Worker::new(wid, &self.summary_dir, tx, rx);
+        let mut worker = Worker::new(wid, &self.summary_dir, &self.config, tx, rx);
         thread::spawn(move || worker.listen());
         self.num_workers += 1;
     }
@@ -185,24 +250,40 @@ impl LoadManager {
 struct Worker {
     id: u16,
     summary_dir: String,
-    account_map: HashMap<u16, Account>,
+    config: Config,
+    error: Option<AppError>,
+    account_map: HashMap<ClientId, Account>,
+    rejected: Vec<(TxRow, TxRejectReason)>,
+    // time, row, from + to of every change of account state
+    state_changes: Vec<(i64, TxRow, AccountState, AccountState)>,
+    tx_transfer: TxTransfer,
     tx: Sender<Result<u16, AppError>>,
-    rx: Receiver<Option<(u16, Vec<TxRow>)>>,
+    rx: Receiver<Packet<ClientRows>>,
 }
 
 impl Worker {
     fn new(
         id: u16,
         summary_dir: &str,
+        config: &Config,
         tx: Sender<Result<u16, AppError>>,
-        rx: Receiver<Option<(u16, Vec<TxRow>)>>,
+        rx: Receiver<Packet<ClientRows>>,
     ) -> Self {
         Self {
             id,
             summary_dir: summary_dir.to_string(),
+            config: config.clone(),
+            error: None,
             tx,
             rx,
             account_map: HashMap::new(),
+            rejected: Vec::new(),
+            state_changes: Vec::new(),
+            tx_transfer: TxTransfer::new(
+                summary_dir,
+                &["transfer_", &id.to_string()].join(""),
+                config,
+            ),
         }
     }
 
@@ -211,29 +292,68 @@ impl Worker {
             select! {
                 recv(self.rx) -> packet => {
                     if let Ok(block) = packet {
-                        if let Some(tuple) = block {
+                        if let Packet::Data(tuple) = block {
+                            // keep draining the queue after a failure so the manager never blocks
+                            if self.error.is_some() {
+                                continue;
+                            }
+
                             let client_id = tuple.0;
                             let tx_rows = tuple.1;
                             let mut account: Account;
                             if self.account_map.contains_key(&client_id) {
                                 account = self.account_map.get(&client_id).unwrap().clone();
                             } else {
-                                account = Account::new(client_id, &self.summary_dir);
+                                account = Account::new(client_id, &self.summary_dir, &self.config);
                             }
-                            let mut tx_history = TxHistory::new(&client_id);
+                            // the error goes up the channel on shutdown, like a failed write
+                            let mut tx_history = match TxHistory::new(&client_id, &self.summary_dir, &self.config) {
+                                Ok(tx_history) => tx_history,
+                                Err(err) => {
+                                    self.error = Some(err);
+                                    continue;
+                                }
+                            };
                             for row in &tx_rows {
-                                account.handle_tx(&row.type_id, &row.tx_id, &row.amount, &mut tx_history);
+                                let state = account.state;
+                                // a history that can't be read fails the run, nothing of the batch is written
+                                let outcome = match account.handle_tx(&row.type_id, &row.tx_id, &row.amount, &mut tx_history) {
+                                    Ok(outcome) => outcome,
+                                    Err(err) => {
+                                        self.error = Some(err);
+                                        break;
+                                    }
+                                };
+                                // only applied transactions are kept, so a rejected one can't be disputed later on
+                                if let TxOutcome::REJECTED(reason) = outcome {
+                                    self.rejected.push((*row, reason));
+                                    continue;
+                                }
+                                if account.state != state {
+                                    self.state_changes.push((Utc::now().timestamp_millis(), *row, state, account.state));
+                                }
                                 tx_history.set_tx(&row.type_id, &row.client_id, &row.tx_id, &row.amount);
                             }
+                            if self.error.is_some() {
+                                continue;
+                            }
                             let result = account.write_to_csv(&self.summary_dir);
                             tx_history.clear_cache();
                             tx_history.commit();
 
                             if result.is_err() {
-                                self.tx.send(Err(result.err().unwrap())).unwrap();
-                                self.shutdown();
-                                break;
+                                self.error = result.err();
+                            }
+                        } else if let Packet::Transfer(tx_row, from, to) = block {
+                            if self.error.is_some() {
+                                continue;
+                            }
+                            if let Err(err) = self.tx_transfer.apply(&tx_row, from, to) {
+                                self.error = Some(err);
                             }
+                        } else if let Packet::Sync = block {
+                            // a failure is only reported on shutdown
+                            let _ = self.tx.send(Ok(self.id));
                         } else {
                             self.shutdown();
                             break;
@@ -248,6 +368,20 @@ impl Worker {
     }
 
     fn shutdown(&mut self) {
+        if self.error.is_none() {
+            let name = ["worker_", &self.id.to_string()].join("");
+            let result = TxRejectionLog::stage(&self.summary_dir, &name, &self.rejected)
+                .and_then(|_| AccountAuditLog::stage(&self.summary_dir, &name, &self.state_changes))
+                .and_then(|_| self.tx_transfer.stage());
+            if let Err(err) = result {
+                self.error = Some(err);
+            }
+        }
+
+        if let Some(err) = self.error.take() {
+            let _ = self.tx.send(Err(err));
+            return;
+        }
         let _ = self.tx.send(Ok(self.id));
     }
 }
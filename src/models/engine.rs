@@ -0,0 +1,144 @@
+use chrono::Utc;
+use std::fs;
+
+use super::account::Account;
+use super::processor::Processor;
+use super::store::Store;
+use super::tx_columns::TxColumnMap;
+use super::tx_dispute::DisputePolicy;
+use super::tx_input::TxInputReport;
+use super::tx_reader::{ParseMode, PrecisionPolicy};
+use super::tx_record::{ClientId, TxRow};
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/engine";
+
+// entry point for embedding the engine: rows are fed in memory, accounts are queried, and a commit applies the fed rows as one run.
+// a commit writes the fed rows to an input file under the data root and processes it like any other input,
+// with the same settings as the cli, so it goes through the same tx index, journal + rollback.
+// the file is left out of the registry, the same rows can be committed again, e.g. a freeze after an unlock.
+// the file is removed once the run is over.
+pub struct Engine {
+    config: Config,
+    rows: Vec<TxRow>,
+    parse_mode: ParseMode,
+    column_map: TxColumnMap,
+    precision: PrecisionPolicy,
+    actor: Option<String>,
+}
+
+impl Engine {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            rows: Vec::new(),
+            parse_mode: ParseMode::STRICT,
+            column_map: TxColumnMap::new(),
+            precision: PrecisionPolicy::ROUND,
+            actor: None,
+        }
+    }
+
+    pub fn set_parse_mode(&mut self, parse_mode: ParseMode) {
+        self.parse_mode = parse_mode;
+    }
+
+    pub fn set_column_map(&mut self, column_map: TxColumnMap) {
+        self.column_map = column_map;
+    }
+
+    pub fn set_precision(&mut self, precision: PrecisionPolicy) {
+        self.precision = precision;
+    }
+
+    // how disputes on resolved transactions are taken
+    pub fn set_dispute_policy(&mut self, dispute_policy: DisputePolicy) {
+        self.config.set_dispute_policy(dispute_policy);
+    }
+
+    // who is recorded in the audit trail for the state changes of a commit
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = Some(actor.to_string());
+    }
+
+    pub fn feed(&mut self, row: TxRow) {
+        self.rows.push(row);
+    }
+
+    // rows fed since the last commit
+    pub fn pending(&self) -> &[TxRow] {
+        &self.rows
+    }
+
+    // accounts only reflect committed rows
+    pub fn account(&self, client_id: ClientId) -> Option<Account> {
+        Account::find(client_id, &self.config)
+    }
+
+    pub fn accounts(&self) -> Result<Vec<Account>, AppError> {
+        Store::check(&self.config)?;
+        Account::all(&self.config)
+    }
+
+    // none if nothing was fed. the fed rows are kept if the commit fails, so it can be retried.
+    pub fn commit(&mut self) -> Result<Option<TxInputReport>, AppError> {
+        if self.rows.is_empty() {
+            return Ok(None);
+        }
+
+        let feed_path = self.write_feed()?;
+        let result = Processor::new(&feed_path, &self.config).and_then(|mut p| {
+            p.set_register(false);
+            p.set_parse_mode(self.parse_mode);
+            p.set_column_map(self.column_map.clone());
+            p.set_precision(self.precision);
+            if let Some(actor) = &self.actor {
+                p.set_actor(actor);
+            }
+            p.process_data(true)
+        });
+        // the run is published or undone by now, its name is all that is left of it in the outputs
+        let _ = fs::remove_file(&feed_path);
+
+        let mut reports = result?;
+        self.rows.clear();
+        Ok(reports.pop())
+    }
+
+    fn write_feed(&self) -> Result<String, AppError> {
+        let feed_dir = self.config.feed_dir();
+        fs::create_dir_all(&feed_dir).map_err(|e| AppError::io(PATH, "write_feed", "00", e))?;
+
+        let mut data = String::from("type,client,tx,amount,to\n");
+        // amounts at full precision, the precision policy is applied when the feed is read
+        for row in &self.rows {
+            let to = row.to_client_id.map(|c| c.to_string()).unwrap_or_default();
+            // a dispute, resolve or chargeback without an amount is about the whole tx, admin rows carry none.
+            // a funds row keeps its amount, so a zero one is rejected like in any input
+            let amount = if row.amount.is_zero() && !row.type_id.funds_type() {
+                String::new()
+            } else {
+                row.amount.to_string()
+            };
+            data.push_str(&format!(
+                "{},{},{},{},{}\n",
+                row.type_id.to_string(),
+                row.client_id,
+                row.tx_id,
+                amount,
+                to
+            ));
+        }
+
+        let feed_path = [
+            &feed_dir,
+            "/feed_",
+            &Utc::now().timestamp_nanos().to_string(),
+            ".csv",
+        ]
+        .join("");
+        fs::write(&feed_path, data).map_err(|e| AppError::io(PATH, "write_feed", "01", e))?;
+        Ok(feed_path)
+    }
+}
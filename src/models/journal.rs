@@ -0,0 +1,198 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::account::{Account, AccountPath};
+use super::account_audit::AccountAuditLog;
+use super::tx_index::TxIndex;
+use super::tx_registry::TxRegistry;
+use crate::common::config::Config;
+use crate::common::constants::FN_NEW;
+use crate::common::error::AppError;
+use crate::common::lock::Lock;
+
+const PATH: &str = "model/journal";
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum JournalState {
+    CLUSTER,
+    UPDATE,
+    NONE,
+}
+
+impl JournalState {
+    pub fn from_string(string: &str) -> Self {
+        match string {
+            "cluster" => Self::CLUSTER,
+            "update" => Self::UPDATE,
+            _ => Self::NONE,
+        }
+    }
+}
+
+impl fmt::Display for JournalState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            Self::CLUSTER => "cluster",
+            Self::UPDATE => "update",
+            _ => "none",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+// write-ahead record of the stage a run is in.
+// the journal is written before a stage starts and removed once the run is settled,
+// so a journal found on startup belongs to a run that was interrupted.
+// the run in flight holds a lock file next to its journal from before its summary dir is set up,
+// so no other process, nor another run of the same one, wipes or recovers it.
+pub struct Journal {
+    config: Config,
+    file_path: String,
+    lock: Lock,
+    summary_dir: String,
+}
+
+impl Journal {
+    pub fn new(summary_dir: &str, config: &Config) -> Result<Self, AppError> {
+        let name = Path::new(summary_dir)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| AppError::parse(PATH, FN_NEW, "00", "invalid summary dir"))?;
+
+        Ok(Self {
+            config: config.clone(),
+            file_path: [&config.journal_dir(), "/", name, ".csv"].join(""),
+            lock: Lock::new(&[&config.journal_dir(), "/", name, ".lock"].join("")),
+            summary_dir: summary_dir.to_string(),
+        })
+    }
+
+    // take the lock of the run, an error if a live run holds it
+    pub fn lock(&self) -> Result<(), AppError> {
+        if !self.acquire()? {
+            return Err(AppError::concurrency(
+                PATH,
+                "lock",
+                "00",
+                ["run in progress", &self.summary_dir].join(" | "),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, state: JournalState) -> Result<(), AppError> {
+        self.lock()?;
+
+        // write + rename so a crash never leaves a half written journal
+        let tmp_path = self.file_path.replace(".csv", ".tmp");
+        let data = [state.to_string(), self.summary_dir.clone()].join(",");
+        fs::write(&tmp_path, data).map_err(|e| AppError::io(PATH, "write", "00", e))?;
+        fs::rename(&tmp_path, &self.file_path).map_err(|e| AppError::io(PATH, "write", "01", e))?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), AppError> {
+        self.remove()?;
+        self.release();
+        Ok(())
+    }
+
+    // hand the journal over to the next recovery without removing it.
+    pub fn release(&self) {
+        self.lock.release();
+    }
+
+    pub fn state(&self) -> JournalState {
+        if let Ok(string) = fs::read_to_string(&self.file_path) {
+            let v: Vec<&str> = string.split(',').collect();
+            return JournalState::from_string(v[0]);
+        }
+        JournalState::NONE
+    }
+
+    // settle every run that was interrupted before its journal was cleared.
+    pub fn recover_all(config: &Config) -> Result<(), AppError> {
+        let p = fs::read_dir(config.journal_dir())
+            .map_err(|e| AppError::io(PATH, "recover_all", "00", e))?;
+
+        for e in p.flatten() {
+            let file_path = e.path().display().to_string();
+            if !file_path.ends_with(".csv") {
+                continue;
+            }
+
+            // the journal may have been cleared in the meantime
+            let string = match fs::read_to_string(&file_path) {
+                Ok(string) => string,
+                Err(_) => continue,
+            };
+            let v: Vec<&str> = string.splitn(2, ',').collect();
+            if v.len() != 2 {
+                continue;
+            }
+
+            // the run is in flight. the lock is taken before the journal is settled,
+            // so two processes never recover it both
+            let journal = Self::new(v[1], config)?;
+            if !journal.acquire()? {
+                continue;
+            }
+            journal.settle()?;
+        }
+        Ok(())
+    }
+
+    // settle the interrupted run of the journal, its lock held.
+    // stage 2 publishes to the tx index, so the journal is left for later while a live run holds the index.
+    pub fn settle(&self) -> Result<bool, AppError> {
+        // the state is read under the lock, the run may have moved on since
+        let state = self.state();
+        if state == JournalState::NONE {
+            return Ok(true);
+        }
+
+        let index_lock = TxIndex::lock(&self.config);
+        if !index_lock
+            .acquire()
+            .map_err(|e| AppError::io(PATH, "settle", "00", e))?
+        {
+            return Ok(false);
+        }
+        self.recover(state)?;
+        Ok(true)
+    }
+
+    // an interrupted stage 1 never touched the official data, so the staged data is dropped.
+    // an interrupted stage 2 is finished if every staged account was published, which registers its tx ids + inputs,
+    // otherwise it is undone with the rollback markers.
+    fn recover(&self, state: JournalState) -> Result<(), AppError> {
+        if state == JournalState::UPDATE && Path::new(&self.summary_dir).exists() {
+            let pending = AccountPath::paths(&self.summary_dir)?;
+            if !pending.is_empty() {
+                Account::rollback_all(&self.summary_dir, &self.config)?;
+            } else {
+                TxIndex::publish(&self.summary_dir, &self.config)?;
+                TxRegistry::commit(&self.summary_dir, &self.config)?;
+                AccountAuditLog::commit(&self.summary_dir, &self.config)?;
+            }
+        }
+
+        let _ = fs::remove_dir_all(&self.summary_dir);
+        self.remove()
+    }
+
+    fn remove(&self) -> Result<(), AppError> {
+        if Path::new(&self.file_path).exists() {
+            fs::remove_file(&self.file_path).map_err(|e| AppError::io(PATH, "remove", "00", e))?;
+        }
+        Ok(())
+    }
+
+    fn acquire(&self) -> Result<bool, AppError> {
+        self.lock
+            .acquire()
+            .map_err(|e| AppError::io(PATH, "acquire", "00", e))
+    }
+}
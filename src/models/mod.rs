@@ -1,9 +1,26 @@
 pub mod account;
+pub mod account_audit;
+pub mod account_detail;
+pub mod account_output;
 pub mod balancer;
+pub mod engine;
+pub mod journal;
+pub mod output;
 pub mod processor;
+pub mod restore;
+pub mod statement;
+pub mod store;
 pub mod tx_cluster;
+pub mod tx_columns;
+pub mod tx_dispute;
 pub mod tx_history;
+pub mod tx_index;
+pub mod tx_input;
 pub mod tx_reader;
 pub mod tx_record;
+pub mod tx_registry;
+pub mod tx_rejection;
+pub mod tx_schedule;
+pub mod tx_transfer;
 pub mod tx_writer;
 pub mod updater;
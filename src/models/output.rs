@@ -0,0 +1,113 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+
+use crate::common::error::AppError;
+
+const PATH: &str = "model/output";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OutputFormat {
+    CSV,
+    // a json array of rows
+    JSON,
+    // one json row per line
+    NDJSON,
+}
+
+impl OutputFormat {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "csv" => Some(Self::CSV),
+            "json" => Some(Self::JSON),
+            "ndjson" => Some(Self::NDJSON),
+            _ => None,
+        }
+    }
+}
+
+// writes rows of any report in one of the output formats
+pub struct Output;
+
+impl Output {
+    // csv always gets the header, even without rows
+    pub fn write_rows<T: Serialize>(
+        header: &[&str],
+        rows: &[T],
+        out: impl Write,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        match format {
+            OutputFormat::CSV => Self::write_csv(header, rows, out),
+            OutputFormat::JSON => Self::write_json(rows, out),
+            OutputFormat::NDJSON => Self::write_ndjson(rows, out),
+        }
+    }
+
+    fn write_csv<T: Serialize>(
+        header: &[&str],
+        rows: &[T],
+        out: impl Write,
+    ) -> Result<(), AppError> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(out);
+        writer
+            .write_record(header)
+            .map_err(|e| AppError::io(PATH, "write_csv", "00", e))?;
+        for row in rows {
+            writer
+                .serialize(row)
+                .map_err(|e| AppError::io(PATH, "write_csv", "01", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| AppError::io(PATH, "write_csv", "02", e))
+    }
+
+    fn write_json<T: Serialize>(rows: &[T], mut out: impl Write) -> Result<(), AppError> {
+        serde_json::to_writer_pretty(&mut out, rows)
+            .map_err(|e| AppError::io(PATH, "write_json", "00", e))?;
+        writeln!(out).map_err(|e| AppError::io(PATH, "write_json", "01", e))?;
+        out.flush()
+            .map_err(|e| AppError::io(PATH, "write_json", "02", e))
+    }
+
+    fn write_ndjson<T: Serialize>(rows: &[T], mut out: impl Write) -> Result<(), AppError> {
+        for row in rows {
+            serde_json::to_writer(&mut out, row)
+                .map_err(|e| AppError::io(PATH, "write_ndjson", "00", e))?;
+            writeln!(out).map_err(|e| AppError::io(PATH, "write_ndjson", "01", e))?;
+        }
+        out.flush()
+            .map_err(|e| AppError::io(PATH, "write_ndjson", "02", e))
+    }
+
+    // a single value, json is pretty printed + ndjson is one line. csv has no nesting, so it is left to the caller.
+    pub fn write_value<T: Serialize>(
+        value: &T,
+        mut out: impl Write,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        let result = match format {
+            OutputFormat::NDJSON => serde_json::to_writer(&mut out, value),
+            _ => serde_json::to_writer_pretty(&mut out, value),
+        };
+        result.map_err(|e| AppError::io(PATH, "write_value", "00", e))?;
+        writeln!(out).map_err(|e| AppError::io(PATH, "write_value", "01", e))?;
+        out.flush()
+            .map_err(|e| AppError::io(PATH, "write_value", "02", e))
+    }
+
+    // stdout when there is no output path
+    pub fn target(output: Option<&str>) -> Result<Box<dyn Write>, AppError> {
+        match output {
+            Some(output) => {
+                let file =
+                    File::create(output).map_err(|e| AppError::io(PATH, "target", "00", e))?;
+                Ok(Box::new(file))
+            }
+            None => Ok(Box::new(io::stdout().lock())),
+        }
+    }
+}
@@ -1,125 +1,476 @@
+use std::env;
 use std::fs;
+use std::io;
+use std::mem;
 
-use super::account::AccountPath;
+use super::account::{Account, AccountPath};
+use super::account_audit::AccountAuditLog;
+use super::account_output::AccountOutput;
 use super::balancer::Balancer;
+use super::journal::{Journal, JournalState};
+use super::output::OutputFormat;
+use super::store::Store;
 use super::tx_cluster::TxCluster;
-use super::tx_reader::TxReader;
-use super::tx_record::TxRow;
+use super::tx_columns::TxColumnMap;
+use super::tx_dispute::DisputePolicy;
+use super::tx_index::TxIndex;
+use super::tx_input::{TxInput, TxInputReport, TxSpool};
+use super::tx_reader::{InputFormat, ParseMode, PrecisionPolicy, TxQuarantine, TxReader};
+use super::tx_record::{ClientId, TxId, TxRecordType, TxRow};
+use super::tx_registry::{TxRegistry, TxRegistryEntry};
+use super::tx_rejection::{TxRejectReason, TxRejectionLog};
+use super::tx_schedule::TxSchedule;
 use super::updater::Updater;
-use crate::lib::constants::{
-    ACCOUNT_BACKUP_DIR, ACCOUNT_DIR, FN_NEW, SUMMARY_DIR, TRANSACTION_DIR,
-};
-use crate::lib::error::AppError;
+use crate::common::config::Config;
+use crate::common::constants::{FN_NEW, STDIN_PATH};
+use crate::common::error::AppError;
 
 const PATH: &str = "model/processor";
 const BLOCK_SIZE: usize = 1_000_000;
 
-pub struct Processor<'a> {
-    source_csv_path: &'a str,
+pub struct Processor {
+    source_csv_paths: Vec<String>,
     csv_summary_dir: String,
+    config: Config,
+    force: bool,
+    register: bool,
+    parse_mode: ParseMode,
+    input_format: Option<InputFormat>,
+    column_map: TxColumnMap,
+    precision: PrecisionPolicy,
+    actor: String,
+    schedule: Option<TxSchedule>,
+    // the journal of the run, locked from before the summary dir is wiped until the processor is dropped
+    journal: Journal,
+    // stdin copied to the data root, removed with the processor
+    _spools: Vec<TxSpool>,
 }
 
-impl<'a> Processor<'a> {
-    pub fn new(source_csv_path: &'a str) -> Result<Self, AppError> {
-        let csv_summary_dir = Self::csv_base_dir(source_csv_path, SUMMARY_DIR)?;
+impl Processor {
+    pub fn new(source_csv_path: &str, config: &Config) -> Result<Self, AppError> {
+        Self::new_batch(vec![source_csv_path.to_string()], config)
+    }
+
+    // the files are read in the given order through a single cluster + update cycle.
+    // "-" reads stdin, which is spooled to the data root first.
+    pub fn new_batch(source_csv_paths: Vec<String>, config: &Config) -> Result<Self, AppError> {
+        let mut source_csv_paths = source_csv_paths;
+        let mut spools = Vec::new();
+        for source_csv_path in source_csv_paths.iter_mut() {
+            if source_csv_path == STDIN_PATH {
+                let spool = TxInput::spool(io::stdin().lock(), &config.stdin_dir())?;
+                *source_csv_path = spool.path().to_string();
+                spools.push(spool);
+            }
+        }
+
+        let csv_summary_dir = Self::batch_base_dir(&source_csv_paths, &config.summary_dir())?;
+
+        fs::create_dir_all(config.journal_dir()).map_err(|e| AppError::io(PATH, FN_NEW, "4", e))?;
+
+        Store::migrate(config)?;
+
+        // settle interrupted runs before their staged data can be wiped
+        Journal::recover_all(config)?;
+
+        // the summary dir may be staged by a live run of the same input
+        let journal = Self::lock_journal(&csv_summary_dir, config)?;
 
         let _ = fs::remove_dir_all(&csv_summary_dir);
 
         fs::create_dir_all(csv_summary_dir.clone())
-            .map_err(|e| AppError::new(PATH, FN_NEW, "1", &e.to_string()))?;
+            .map_err(|e| AppError::io(PATH, FN_NEW, "1", e))?;
 
-        fs::create_dir_all(ACCOUNT_DIR.clone())
-            .map_err(|e| AppError::new(PATH, FN_NEW, "2", &e.to_string()))?;
+        fs::create_dir_all(config.account_dir()).map_err(|e| AppError::io(PATH, FN_NEW, "2", e))?;
 
-        fs::create_dir_all(ACCOUNT_BACKUP_DIR.clone())
-            .map_err(|e| AppError::new(PATH, FN_NEW, "2", &e.to_string()))?;
+        fs::create_dir_all(config.account_backup_dir())
+            .map_err(|e| AppError::io(PATH, FN_NEW, "2", e))?;
 
-        fs::create_dir_all(TRANSACTION_DIR.clone())
-            .map_err(|e| AppError::new(PATH, FN_NEW, "3", &e.to_string()))?;
+        fs::create_dir_all(config.transaction_dir())
+            .map_err(|e| AppError::io(PATH, FN_NEW, "3", e))?;
 
         Ok(Self {
-            source_csv_path,
+            source_csv_paths,
             csv_summary_dir,
+            config: config.clone(),
+            force: false,
+            register: true,
+            parse_mode: ParseMode::STRICT,
+            input_format: None,
+            column_map: TxColumnMap::new(),
+            precision: PrecisionPolicy::ROUND,
+            actor: env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
+            schedule: None,
+            journal,
+            _spools: spools,
         })
     }
 
-    #[allow(dead_code)]
-    pub fn set_source_path(&mut self, source_csv_path: &'a str) -> Result<(), AppError> {
-        let csv_summary_dir = Self::csv_base_dir(source_csv_path, SUMMARY_DIR)?;
+    pub fn csv_summary_dir(&self) -> &str {
+        &self.csv_summary_dir
+    }
+
+    // the journal of the run, held by the processor
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn set_source_path(&mut self, source_csv_path: &str) -> Result<(), AppError> {
+        let csv_summary_dir = Self::csv_base_dir(source_csv_path, &self.config.summary_dir())?;
+        let journal = Self::lock_journal(&csv_summary_dir, &self.config)?;
+        self.journal.release();
+        self.journal = journal;
         let _ = fs::remove_dir_all(&csv_summary_dir);
         fs::create_dir_all(csv_summary_dir.clone())
-            .map_err(|e| AppError::new(PATH, FN_NEW, "1", &e.to_string()))?;
-        self.source_csv_path = source_csv_path;
+            .map_err(|e| AppError::io(PATH, FN_NEW, "1", e))?;
+        self.source_csv_paths = vec![source_csv_path.to_string()];
         self.csv_summary_dir = csv_summary_dir;
         Ok(())
     }
 
-    pub fn process_data(&self, enable_cleanup: bool) -> Result<(), AppError> {
-        let result = self.cluster_transactions();
+    // apply inputs that are already in the registry again
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    // keep the inputs in the registry, so the same content is only applied once. true by default.
+    // inputs that aren't kept, like the feeds of the engine, are always applied.
+    pub fn set_register(&mut self, register: bool) {
+        self.register = register;
+    }
+
+    pub fn set_parse_mode(&mut self, parse_mode: ParseMode) {
+        self.parse_mode = parse_mode;
+    }
+
+    pub fn set_precision(&mut self, precision: PrecisionPolicy) {
+        self.precision = precision;
+    }
+
+    // the names the columns of the inputs can go by
+    pub fn set_column_map(&mut self, column_map: TxColumnMap) {
+        self.column_map = column_map;
+    }
+
+    // who is recorded in the audit trail for the state changes of the run, $USER by default
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_string();
+    }
+
+    // the fees + interest applied to every account once the inputs of the run are applied
+    pub fn set_schedule(&mut self, schedule: Option<TxSchedule>) {
+        self.schedule = schedule;
+    }
+
+    // how disputes on resolved transactions are taken
+    pub fn set_dispute_policy(&mut self, dispute_policy: DisputePolicy) {
+        self.config.set_dispute_policy(dispute_policy);
+    }
+
+    // read every input in this format, instead of going by its extension
+    pub fn set_input_format(&mut self, input_format: Option<InputFormat>) {
+        self.input_format = input_format;
+    }
+
+    // the tx index is locked for the whole run, the claims of the run hold until its ids are published
+    pub fn process_data(&self, enable_cleanup: bool) -> Result<Vec<TxInputReport>, AppError> {
+        let _index_lock = TxIndex::acquire(&self.config)?;
+        self.run(enable_cleanup)
+    }
+
+    fn run(&self, enable_cleanup: bool) -> Result<Vec<TxInputReport>, AppError> {
+        let (source_csv_paths, mut reports) = self.registry_inputs()?;
+        if source_csv_paths.is_empty() {
+            let _ = fs::remove_dir_all(&self.csv_summary_dir);
+            return Ok(reports);
+        }
+
+        let journal = &self.journal;
+
+        journal.write(JournalState::CLUSTER)?;
+        let result = self.cluster_inputs(&source_csv_paths);
         if result.is_err() {
             self.cleanup(enable_cleanup);
+            journal.clear()?;
             return result;
         }
+        reports.append(&mut result.unwrap());
 
+        journal.write(JournalState::UPDATE)?;
         let result = self.update_accounts();
-        if result.is_err() {
+        if let Err(err) = result {
+            // restore every account + transaction history published by this run
+            if let Err(rollback_err) = Account::rollback_all(&self.csv_summary_dir, &self.config) {
+                // leave the journal for the next run to recover
+                journal.release();
+                rollback_err.show();
+                return Err(err);
+            }
             self.cleanup(enable_cleanup);
-            return result;
+            journal.clear()?;
+            return Err(err);
+        }
+
+        // the accounts are published, the tx ids + inputs can't be applied again
+        let result = TxIndex::publish(&self.csv_summary_dir, &self.config)
+            .and_then(|_| TxRegistry::commit(&self.csv_summary_dir, &self.config))
+            .and_then(|_| AccountAuditLog::commit(&self.csv_summary_dir, &self.config))
+            .and_then(|_| {
+                TxRejectionLog::collect(&self.csv_summary_dir, &self.sources(&source_csv_paths))
+            })
+            .and_then(|rejections| TxRejectionLog::write(&self.csv_summary_dir, &rejections))
+            .and_then(|_| self.write_quarantine(&reports));
+        if let Err(err) = result {
+            // leave the journal for the next run to recover
+            journal.release();
+            return Err(err);
         }
 
-        let result = self.show_accounts();
-        result
+        // the staged data has been published, nothing in the summary dir is needed anymore
+        let _ = fs::remove_dir_all(&self.csv_summary_dir);
+        journal.clear()?;
+
+        // report in input order
+        reports.sort_by_key(|r| self.source_csv_paths.iter().position(|p| *p == r.path));
+        Ok(reports)
     }
 
-    pub fn cluster_transactions(&self) -> Result<(), AppError> {
+    pub fn cluster_transactions(&self) -> Result<Vec<TxInputReport>, AppError> {
+        self.cluster_inputs(&self.source_csv_paths)
+    }
+
+    // inputs already applied are skipped, unless forced.
+    // reusing the name of an applied input for other content is refused, since names are unique.
+    fn registry_inputs(&self) -> Result<(Vec<String>, Vec<TxInputReport>), AppError> {
+        if !self.register {
+            return Ok((self.source_csv_paths.clone(), Vec::new()));
+        }
+
+        let registry = TxRegistry::new(&self.config)?;
+        let mut source_csv_paths = Vec::new();
+        let mut entries: Vec<TxRegistryEntry> = Vec::new();
+        let mut reports = Vec::new();
+
+        for source_csv_path in &self.source_csv_paths {
+            let entry = TxRegistry::entry(source_csv_path)?;
+            if !self.force {
+                let applied = registry.find_hash(&entry.hash).is_some()
+                    || entries.iter().any(|e| e.hash == entry.hash);
+                if applied {
+                    reports.push(TxInputReport {
+                        path: source_csv_path.to_string(),
+                        rows: 0,
+                        skipped: true,
+                        duplicates: Vec::new(),
+                        quarantined: Vec::new(),
+                    });
+                    continue;
+                }
+
+                let reused = registry.find_name(&entry.name).is_some()
+                    || entries.iter().any(|e| e.name == entry.name);
+                if reused {
+                    return Err(AppError::rule(
+                        PATH,
+                        "registry_inputs",
+                        "00",
+                        ["input name already applied", source_csv_path].join(" | "),
+                    ));
+                }
+            }
+            entries.push(entry);
+            source_csv_paths.push(source_csv_path.to_string());
+        }
+
+        TxRegistry::stage(&self.csv_summary_dir, &entries)?;
+        Ok((source_csv_paths, reports))
+    }
+
+    fn cluster_inputs(&self, source_csv_paths: &[String]) -> Result<Vec<TxInputReport>, AppError> {
         let mut tx_cluster = TxCluster::new();
-        let mut tx_reader = TxReader::new(&self.source_csv_path)?;
-        let mut balancer = Balancer::new(&self.csv_summary_dir);
+        let mut balancer = Balancer::new(&self.csv_summary_dir, &self.config);
+        let tx_index = TxIndex::new(&self.csv_summary_dir, &self.config)?;
+        let mut reports = Vec::new();
+
+        let mut rejected = Vec::new();
 
         balancer.start()?;
         let mut rows: usize = 0;
 
-        while tx_reader.next_record() {
-            let tx_id = tx_reader.tx_record_type().clone();
-            let tx_row = TxRow::new(
-                tx_id,
-                tx_reader.tx_record_client().clone(),
-                tx_reader.tx_record_tx().clone(),
-                tx_reader.tx_record_amount().clone(),
-            );
-            tx_cluster.add(tx_row);
-
-            rows += 1;
-            if rows == BLOCK_SIZE {
-                rows = 0;
-                balancer.add(tx_cluster)?;
-                tx_cluster = TxCluster::new();
+        for (source_id, source_csv_path) in source_csv_paths.iter().enumerate() {
+            let result = match self.input_format {
+                Some(input_format) => TxReader::new_with_format(source_csv_path, input_format),
+                None => TxReader::new(source_csv_path),
+            };
+            if result.is_err() {
+                let _ = balancer.stop();
+                return Err(result.err().unwrap());
             }
-        }
 
-        // handle rollback
-        if let Some(error) = tx_reader.error() {
-            let _ = balancer.stop();
-            return Err(AppError::new(
-                PATH,
-                "cluster_transactions_by_client",
-                "00",
-                error,
-            ));
+            let mut tx_reader = result.unwrap();
+            tx_reader.set_mode(self.parse_mode);
+            tx_reader.set_column_map(&self.column_map);
+            tx_reader.set_precision(self.precision);
+            let mut file_rows: usize = 0;
+            let mut duplicates = Vec::new();
+
+            while tx_reader.next_record() {
+                let tx_id = *tx_reader.tx_record_type();
+                let mut tx_row = TxRow::new(
+                    tx_id,
+                    *tx_reader.tx_record_client(),
+                    *tx_reader.tx_record_tx(),
+                    *tx_reader.tx_record_amount(),
+                );
+                tx_row.to_client_id = *tx_reader.tx_record_to();
+                tx_row.source_id = source_id;
+                tx_row.line = tx_reader.line();
+                file_rows += 1;
+
+                if let Some(reason) = *tx_reader.tx_record_rejected() {
+                    rejected.push((tx_row, reason));
+                    continue;
+                }
+
+                // a tx id is only applied once, whichever client or run it came from
+                if tx_row.type_id.funds_type() {
+                    let result = match tx_row.to_client_id {
+                        Some(to) => tx_index.claim_transfer(&tx_row.tx_id, &tx_row.client_id, &to),
+                        None => tx_index.claim(&tx_row.tx_id, &tx_row.client_id),
+                    };
+                    if result.is_err() {
+                        let _ = balancer.stop();
+                        return Err(result.err().unwrap());
+                    }
+                    if !result.unwrap() {
+                        duplicates.push(tx_row);
+                        continue;
+                    }
+                }
+
+                // transfers + the disputes on them touch two clients, which may be on different workers.
+                // the rows read so far are sent first, so the row is applied after them.
+                let result = Self::transfer_parties(&tx_index, &tx_row);
+                if result.is_err() {
+                    let _ = balancer.stop();
+                    return Err(result.err().unwrap());
+                }
+                if let Some((from, to)) = result.unwrap() {
+                    let cluster = mem::take(&mut tx_cluster);
+                    rows = 0;
+                    let result = if cluster.tx_row_map.is_empty() {
+                        Ok(())
+                    } else {
+                        balancer.add(cluster)
+                    };
+                    let result = result.and_then(|_| balancer.transfer(tx_row, from, to));
+                    if result.is_err() {
+                        let _ = balancer.stop();
+                        return Err(result.err().unwrap());
+                    }
+                    continue;
+                }
+                tx_cluster.add(tx_row);
+
+                rows += 1;
+                if rows == BLOCK_SIZE {
+                    rows = 0;
+                    balancer.add(tx_cluster)?;
+                    tx_cluster = TxCluster::new();
+                }
+            }
+
+            // handle rollback
+            if let Some(err) = tx_reader.take_error() {
+                let _ = balancer.stop();
+                return Err(err);
+            }
+
+            reports.push(TxInputReport {
+                path: source_csv_path.to_string(),
+                rows: file_rows,
+                skipped: false,
+                duplicates,
+                quarantined: tx_reader.quarantined().clone(),
+            });
         }
 
         // send remaining data to write queue
-        if tx_cluster.tx_row_map.len() > 0 {
+        if !tx_cluster.tx_row_map.is_empty() {
             balancer.add(tx_cluster)?;
         }
 
         balancer.stop()?;
-        Ok(())
+
+        // a rejected transfer moved nothing, its tx id is free to be sent again
+        let transfers: Vec<TxId> = TxRejectionLog::read(&self.csv_summary_dir)?
+            .into_iter()
+            .filter(|(row, _)| row.type_id == TxRecordType::TRANSFER)
+            .map(|(row, _)| row.tx_id)
+            .collect();
+        tx_index.release(&transfers)?;
+
+        // the schedule file comes after the inputs in the sources of the rejections output
+        match &self.schedule {
+            Some(schedule) => schedule.apply(
+                source_csv_paths.len(),
+                &self.csv_summary_dir,
+                &tx_index,
+                &self.config,
+            )?,
+            None => TxSchedule::defer(&self.csv_summary_dir, &tx_index)?,
+        }
+        tx_index.commit()?;
+
+        let duplicates: Vec<(TxRow, TxRejectReason)> = reports
+            .iter()
+            .flat_map(|r| r.duplicates.iter())
+            .map(|row| (*row, TxRejectReason::DUPLICATE_TX))
+            .collect();
+        TxRejectionLog::stage(&self.csv_summary_dir, "tx_index", &duplicates)?;
+        TxRejectionLog::stage(&self.csv_summary_dir, "tx_reader", &rejected)?;
+        AccountAuditLog::seal(&self.csv_summary_dir, source_csv_paths, &self.actor)?;
+        Ok(reports)
+    }
+
+    // the files rows of the run can come from, by source id
+    fn sources(&self, source_csv_paths: &[String]) -> Vec<String> {
+        let mut sources = source_csv_paths.to_vec();
+        if let Some(schedule) = &self.schedule {
+            sources.push(schedule.path().to_string());
+        }
+        sources
+    }
+
+    // the sender + recipient of a transfer, or of the transfer a dispute, resolve or chargeback is on
+    fn transfer_parties(
+        tx_index: &TxIndex,
+        tx_row: &TxRow,
+    ) -> Result<Option<(ClientId, ClientId)>, AppError> {
+        if let Some(to) = tx_row.to_client_id {
+            return Ok(Some((tx_row.client_id, to)));
+        }
+        if tx_row.type_id.conflict_type() {
+            return tx_index.transfer(&tx_row.tx_id);
+        }
+        Ok(None)
+    }
+
+    fn write_quarantine(&self, reports: &[TxInputReport]) -> Result<(), AppError> {
+        if self.parse_mode != ParseMode::LENIENT {
+            return Ok(());
+        }
+
+        let rows: Vec<TxQuarantine> = reports
+            .iter()
+            .flat_map(|r| r.quarantined.iter().cloned())
+            .collect();
+        TxReader::write_quarantine(&self.csv_summary_dir, &rows)
     }
 
     fn update_accounts(&self) -> Result<(), AppError> {
-        let mut updater = Updater::new();
-        let batches = AccountPath::paths(true, &self.csv_summary_dir)?;
+        let mut updater = Updater::new(&self.config);
+        let batches = AccountPath::paths(&self.csv_summary_dir)?;
 
         updater.start()?;
         for files in batches {
@@ -129,28 +480,65 @@ impl<'a> Processor<'a> {
         Ok(())
     }
 
-    fn show_accounts(&self) -> Result<(), AppError> {
-        let mut updater = Updater::new();
-        let batches = AccountPath::paths(false, ACCOUNT_DIR)?;
-        updater.start()?;
-        for files in batches {
-            updater.add(files)?;
+    // write every account in the system to the output path, or stdout
+    pub fn write_accounts(
+        &self,
+        output: Option<&str>,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        AccountOutput::write(&self.config, output, format)
+    }
+
+    // lock the journal of the summary dir, settling the run it was left by.
+    // an error if a live run holds it.
+    fn lock_journal(csv_summary_dir: &str, config: &Config) -> Result<Journal, AppError> {
+        let journal = Journal::new(csv_summary_dir, config)?;
+        journal.lock()?;
+        if !journal.settle()? {
+            journal.release();
+            return Err(AppError::concurrency(
+                PATH,
+                "lock_journal",
+                "00",
+                ["tx index in use by another run", csv_summary_dir].join(" | "),
+            ));
         }
-        updater.stop()?;
+        Ok(journal)
+    }
 
-        Ok(())
+    // a batch is named after its first + last file
+    fn batch_base_dir(source_csv_paths: &[String], base: &str) -> Result<String, AppError> {
+        if source_csv_paths.is_empty() {
+            return Err(AppError::rule(
+                PATH,
+                "batch_base_dir",
+                "00",
+                "no input files",
+            ));
+        }
+
+        let mut name = Self::csv_base_name(&source_csv_paths[0])?;
+        if source_csv_paths.len() > 1 {
+            let last = Self::csv_base_name(&source_csv_paths[source_csv_paths.len() - 1])?;
+            name = [name, last].join("-");
+        }
+        Ok([base, &name].join("/"))
     }
 
     fn csv_base_dir(source_csv_path: &str, base: &str) -> Result<String, AppError> {
+        Ok([base, &Self::csv_base_name(source_csv_path)?].join("/"))
+    }
+
+    fn csv_base_name(source_csv_path: &str) -> Result<String, AppError> {
         let v: Vec<&str> = source_csv_path.split("/").collect();
-        if v.len() > 0 {
+        if !v.is_empty() {
             let file_name = v[v.len() - 1];
-            if file_name.len() > 0 {
+            if !file_name.is_empty() {
                 let v: Vec<&str> = file_name.split(".").collect();
-                return Ok([base, v[0]].join("/"));
+                return Ok(v[0].to_string());
             }
         }
-        Err(AppError::new(PATH, "file_dir", "01", "invalid file path"))
+        Err(AppError::parse(PATH, "file_dir", "01", "invalid file path"))
     }
 
     fn cleanup(&self, enable_cleanup: bool) {
@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use super::account::Account;
+use super::journal::Journal;
+use super::store::Store;
+use super::tx_history::TxHistory;
+use super::tx_index::TxIndex;
+use super::tx_record::ClientId;
+use super::tx_registry::TxRegistry;
+use crate::common::config::Config;
+use crate::common::constants::HISTORY_BACKUP_SUFFIX;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/restore";
+
+// the backups a publish left for a client: the account file it replaced, if there was one, + the history values it replaced
+#[derive(Debug, Default, Clone, Copy)]
+struct RestorePoint {
+    account: bool,
+    history: bool,
+}
+
+// puts clients back as they were at a point in time, from the backups every publish leaves in the backup dir.
+// a backup holds the data from before its publish, so the data at a time is undone from the newest publish after it
+// down to the oldest, which has the account as it was then.
+pub struct Restore;
+
+impl Restore {
+    // rfc 3339, e.g. "2022-03-01T12:00:00Z", or epoch millis
+    pub fn parse_time(string: &str) -> Result<i64, AppError> {
+        if let Ok(millis) = string.parse::<i64>() {
+            return Ok(millis);
+        }
+
+        DateTime::parse_from_rfc3339(string)
+            .map(|t| t.timestamp_millis())
+            .map_err(|e| AppError::parse(PATH, "parse_time", &["00", string].join(" | "), e))
+    }
+
+    // restore one client, or every client published after the time. the restored clients are returned.
+    // the other party of a transfer that is undone or put back is restored with the client.
+    // a full restore makes the registry forget the runs committed after the time, so their files can be applied again.
+    // a client restore keeps them: disputes, resolves, chargebacks + admin rows are not in the tx index,
+    // so applying the files again would apply them to the clients that were not restored as well.
+    pub fn run(
+        millis: i64,
+        client_id: Option<ClientId>,
+        config: &Config,
+    ) -> Result<Vec<ClientId>, AppError> {
+        Store::migrate(config)?;
+
+        // settle interrupted runs, their backups may be needed
+        Journal::recover_all(config)?;
+
+        // no run may publish while the stores are rewritten
+        let _index_lock = TxIndex::acquire(config)?;
+
+        let mut points = Self::points(config)?;
+        let mut pending: BTreeSet<ClientId> = match client_id {
+            Some(id) => BTreeSet::from([id]),
+            None => points.keys().copied().collect(),
+        };
+
+        let mut restored = Vec::new();
+        while let Some(id) = pending.pop_first() {
+            let client_points: Vec<(i64, RestorePoint)> = points
+                .remove(&id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(m, _)| *m > millis)
+                .collect();
+            if client_points.is_empty() {
+                continue;
+            }
+
+            let parties = Self::restore_client(id, &client_points, config)?;
+            restored.push(id);
+            pending.extend(parties.into_iter().filter(|p| points.contains_key(p)));
+        }
+
+        if client_id.is_none() && !restored.is_empty() {
+            TxRegistry::remove_after(millis, config)?;
+        }
+        restored.sort_unstable();
+        Ok(restored)
+    }
+
+    // the points are oldest first. the other parties of the transfers it changed are returned.
+    fn restore_client(
+        client_id: ClientId,
+        points: &[(i64, RestorePoint)],
+        config: &Config,
+    ) -> Result<Vec<ClientId>, AppError> {
+        // account backups from before history backups were kept can't be restored with their history
+        if let Some((m, _)) = points.iter().find(|(_, p)| !p.history) {
+            return Err(AppError::rule(
+                PATH,
+                "restore_client",
+                "00",
+                [
+                    "backup without history",
+                    &Account::backup_path(&client_id, *m, config),
+                ]
+                .join(" | "),
+            ));
+        }
+
+        // the restore is a point of its own, after every point it undoes
+        let last = points.last().map(|(m, _)| *m).unwrap_or_default();
+        let now = Utc::now().timestamp_millis().max(last + 1);
+
+        let history_paths: Vec<String> = points
+            .iter()
+            .rev()
+            .map(|(m, _)| TxHistory::backup_path(&client_id, *m, config))
+            .collect();
+        let (removed, added, changed) = TxHistory::restore(
+            &client_id,
+            &history_paths,
+            &TxHistory::backup_path(&client_id, now, config),
+            config,
+        )?;
+
+        let (first, point) = points[0];
+        let account_backup = if point.account {
+            Some(Account::backup_path(&client_id, first, config))
+        } else {
+            None
+        };
+        Account::restore(&client_id, account_backup.as_deref(), now, config)?;
+
+        TxIndex::restore(&client_id, &removed, &added, &changed, config)
+    }
+
+    // every restore point in the backup dir, by client + time
+    fn points(
+        config: &Config,
+    ) -> Result<BTreeMap<ClientId, BTreeMap<i64, RestorePoint>>, AppError> {
+        let mut points: BTreeMap<ClientId, BTreeMap<i64, RestorePoint>> = BTreeMap::new();
+
+        let backup_dir = config.account_backup_dir();
+        if !Path::new(&backup_dir).exists() {
+            return Ok(points);
+        }
+
+        let p = fs::read_dir(&backup_dir).map_err(|e| AppError::io(PATH, "points", "00", e))?;
+        for e in p.flatten() {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let (stem, history) = match file_name.strip_suffix(HISTORY_BACKUP_SUFFIX) {
+                Some(stem) => (stem, true),
+                None => match file_name.strip_suffix(".csv") {
+                    Some(stem) => (stem, false),
+                    None => continue,
+                },
+            };
+
+            let (client_id, millis) = match stem.split_once('_') {
+                Some((c, m)) => match (c.parse::<ClientId>(), m.parse::<i64>()) {
+                    (Ok(c), Ok(m)) => (c, m),
+                    _ => continue,
+                },
+                None => continue,
+            };
+
+            let point = points
+                .entry(client_id)
+                .or_default()
+                .entry(millis)
+                .or_default();
+            if history {
+                point.history = true;
+            } else {
+                point.account = true;
+            }
+        }
+        Ok(points)
+    }
+}
@@ -0,0 +1,138 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::account::Account;
+use super::output::{Output, OutputFormat};
+use super::store::Store;
+use super::tx_history::TxHistory;
+use super::tx_record::{ClientId, TxConflict, TxId, TxRecordType, TxRow};
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/statement";
+
+const HEADER: [&str; 7] = [
+    "tx",
+    "type",
+    "amount",
+    "state",
+    "available",
+    "held",
+    "total",
+];
+
+// a transaction of a client with its dispute state + the balances once it is applied.
+// the amounts are strings at 4 decimal places.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementLine {
+    pub tx: TxId,
+    #[serde(rename = "type")]
+    pub type_id: String,
+    pub amount: String,
+    // none, or the state of the latest dispute, resolve or chargeback on part or all of the tx
+    pub state: String,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+}
+
+// the published transaction history of a client, in tx order, with running balances.
+// disputes are shown on the transaction they are about, with the effect of the parts under each state,
+// so the last line has the balances of the account.
+pub struct Statement;
+
+impl Statement {
+    pub fn lines(client_id: ClientId, config: &Config) -> Result<Vec<StatementLine>, AppError> {
+        Store::check(config)?;
+
+        let history_exists = Path::new(&TxHistory::committed_path(&client_id, config)).exists();
+        if !history_exists && Account::find(client_id, config).is_none() {
+            return Err(AppError::rule(
+                PATH,
+                "lines",
+                "00",
+                ["unknown client", &client_id.to_string()].join(" | "),
+            ));
+        }
+
+        let mut rows = Vec::new();
+        let mut conflicts = HashMap::new();
+        if history_exists {
+            let db = TxHistory::open_committed(&client_id, config)?;
+            for entry in db.iter() {
+                let (key, value) = entry.map_err(|e| AppError::storage(PATH, "lines", "01", e))?;
+                let value = String::from_utf8_lossy(&value).to_string();
+                if key.starts_with(b"c_") {
+                    let parts = TxConflict::list_from_string(&value)?;
+                    if let Some(conflict) = parts.first() {
+                        conflicts.insert(conflict.tx_id, parts);
+                    }
+                } else if !key.starts_with(b"e_") && !key.starts_with(b"s_") {
+                    // the dispute events are in the dispute log, the schedule periods are not rows
+                    rows.push(TxRow::new_from_string(&value)?);
+                }
+            }
+        }
+        rows.sort_by_key(|row| row.tx_id);
+
+        let mut available = Decimal::new(0, 0);
+        let mut held = Decimal::new(0, 0);
+        let mut total = Decimal::new(0, 0);
+        let mut lines = Vec::new();
+        for row in rows {
+            let parts = conflicts.remove(&row.tx_id).unwrap_or_default();
+            let state = parts
+                .last()
+                .map(|c| c.state_id)
+                .unwrap_or(TxRecordType::NONE);
+            let part = |state_id: TxRecordType| -> Decimal {
+                parts
+                    .iter()
+                    .filter(|c| c.state_id == state_id)
+                    .map(|c| c.amount)
+                    .sum()
+            };
+            let disputed = part(TxRecordType::DISPUTE);
+            let charged_back = part(TxRecordType::CHARGEBACK);
+
+            match row.type_id {
+                // the recipient of a transfer is credited like for a deposit
+                TxRecordType::DEPOSIT | TxRecordType::INTEREST | TxRecordType::TRANSFER_IN => {
+                    available += row.amount - disputed - charged_back;
+                    held += disputed;
+                    total += row.amount - charged_back;
+                }
+                TxRecordType::WITHDRAW | TxRecordType::FEE | TxRecordType::TRANSFER => {
+                    // a withdrawal, fee or transfer chargeback reimburses the client
+                    available -= row.amount - charged_back;
+                    total -= row.amount - charged_back;
+                }
+                _ => {}
+            }
+
+            lines.push(StatementLine {
+                tx: row.tx_id,
+                type_id: row.type_id.to_string(),
+                amount: format!("{:.4}", row.amount),
+                state: state.to_string(),
+                available: format!("{:.4}", available),
+                held: format!("{:.4}", held),
+                total: format!("{:.4}", total),
+            });
+        }
+        Ok(lines)
+    }
+
+    // stdout when there is no output path
+    pub fn write(
+        client_id: ClientId,
+        config: &Config,
+        output: Option<&str>,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        let lines = Self::lines(client_id, config)?;
+        Output::write_rows(&HEADER, &lines, Output::target(output)?, format)
+    }
+}
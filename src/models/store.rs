@@ -0,0 +1,119 @@
+use std::fs;
+use std::path::Path;
+
+use super::account::Account;
+use super::tx_record::TxId;
+use crate::common::config::Config;
+use crate::common::constants::STORE_VERSION_FILE;
+use crate::common::db;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/store";
+
+// version of the data under a data root, kept in its version file.
+// 1: u16 client + u32 tx ids, from before the version file.
+// 2: u32 client + u64 tx ids.
+pub const STORE_VERSION: u32 = 2;
+
+pub struct Store;
+
+impl Store {
+    // none for a data root without a version file
+    pub fn version(config: &Config) -> Result<Option<u32>, AppError> {
+        let path = Self::version_path(config);
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+
+        let string =
+            fs::read_to_string(&path).map_err(|e| AppError::io(PATH, "version", "00", e))?;
+        let version = string
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| AppError::parse(PATH, "version", "01", e))?;
+        Ok(Some(version))
+    }
+
+    // the version of the data root, refused if it is unknown or newer than this build.
+    // every entry point checks it, the ones that write migrate the data root as well.
+    pub fn check(config: &Config) -> Result<u32, AppError> {
+        let version = match Self::version(config)? {
+            Some(version) => version,
+            None if Self::is_empty(config) => STORE_VERSION,
+            None => 1,
+        };
+
+        if version == 0 || version > STORE_VERSION {
+            return Err(AppError::rule(
+                PATH,
+                "check",
+                "00",
+                format!(
+                    "store version {} is unknown, the latest is {}",
+                    version, STORE_VERSION
+                ),
+            ));
+        }
+        Ok(version)
+    }
+
+    // bring the data root up to the current version, one step at a time.
+    // a data root without any data yet starts at the current version.
+    pub fn migrate(config: &Config) -> Result<(), AppError> {
+        let mut version = Self::check(config)?;
+
+        while version < STORE_VERSION {
+            if version == 1 {
+                Self::migrate_v1(config)?;
+            }
+            version += 1;
+            Self::write_version(config, version)?;
+        }
+
+        if Self::version(config)?.is_none() {
+            Self::write_version(config, version)?;
+        }
+        Ok(())
+    }
+
+    // v1 ids are decimal text in the account file names + contents, the tx history keys and the tx index keys,
+    // which the wider ids read as they are. the step only checks every account + tx index id can be read,
+    // so a data root that can't be is never marked as v2.
+    fn migrate_v1(config: &Config) -> Result<(), AppError> {
+        Account::validate_all(config)?;
+
+        if Path::new(&config.tx_index_dir()).exists() {
+            let tx_index = db::open(&config.tx_index_dir())
+                .map_err(|e| AppError::storage(PATH, "migrate_v1", "00", e))?;
+            for entry in tx_index.iter() {
+                let (key, _) = entry.map_err(|e| AppError::storage(PATH, "migrate_v1", "01", e))?;
+                String::from_utf8_lossy(&key)
+                    .parse::<TxId>()
+                    .map_err(|e| AppError::parse(PATH, "migrate_v1", "02", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_empty(config: &Config) -> bool {
+        !Path::new(&config.account_dir()).exists()
+            && !Path::new(&config.transaction_dir()).exists()
+            && !Path::new(&config.tx_index_dir()).exists()
+    }
+
+    // written next to the final file then renamed, so the version file is never half written
+    fn write_version(config: &Config, version: u32) -> Result<(), AppError> {
+        fs::create_dir_all(config.data_dir())
+            .map_err(|e| AppError::io(PATH, "write_version", "00", e))?;
+
+        let path = Self::version_path(config);
+        let tmp_path = [&path, ".tmp"].join("");
+        fs::write(&tmp_path, version.to_string())
+            .map_err(|e| AppError::io(PATH, "write_version", "01", e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| AppError::io(PATH, "write_version", "02", e))
+    }
+
+    fn version_path(config: &Config) -> String {
+        [config.data_dir(), STORE_VERSION_FILE].join("/")
+    }
+}
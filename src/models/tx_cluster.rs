@@ -1,8 +1,14 @@
-use super::tx_record::TxRow;
+use super::tx_record::{ClientId, TxRow};
 use std::collections::HashMap;
 
 pub struct TxCluster {
-    pub tx_row_map: HashMap<u16, Vec<TxRow>>,
+    pub tx_row_map: HashMap<ClientId, Vec<TxRow>>,
+}
+
+impl Default for TxCluster {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TxCluster {
@@ -14,16 +20,12 @@ impl TxCluster {
 
     pub fn add(&mut self, tx_row: TxRow) {
         if self.tx_row_map.contains_key(&tx_row.client_id) {
-            self.tx_row_map
-                .entry(tx_row.client_id.clone())
-                .and_modify(|e| {
-                    e.push(tx_row);
-                });
+            self.tx_row_map.entry(tx_row.client_id).and_modify(|e| {
+                e.push(tx_row);
+            });
         } else {
-            let client_id = tx_row.client_id.clone();
-            let mut v = Vec::new();
-            v.push(tx_row);
-            self.tx_row_map.insert(client_id, v);
+            let client_id = tx_row.client_id;
+            self.tx_row_map.insert(client_id, vec![tx_row]);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,165 @@
+use csv::ByteRecord;
+use std::collections::HashMap;
+use std::fs;
+use std::str;
+
+use super::tx_record::TxRecordType;
+use crate::common::constants::{AMOUNT_POS, CLIENT_POS, MAX_CSV_ROW_LEN, TO_POS, TX_POS, TYPE_POS};
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_columns";
+
+const FIELDS: [&str; MAX_CSV_ROW_LEN] = ["type", "client", "tx", "amount", "to"];
+
+// the names a column can go by. every field is known by its own name, more are added as aliases.
+#[derive(Debug, Clone)]
+pub struct TxColumnMap {
+    aliases: HashMap<String, usize>,
+    ignore_unknown: bool,
+}
+
+impl Default for TxColumnMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxColumnMap {
+    pub fn new() -> Self {
+        let mut aliases = HashMap::new();
+        for (pos, field) in FIELDS.iter().enumerate() {
+            aliases.insert(field.to_string(), pos);
+        }
+        Self {
+            aliases,
+            ignore_unknown: false,
+        }
+    }
+
+    // each line is a field followed by its aliases, e.g. "client,customer,account".
+    // blank lines + # comments are skipped.
+    pub fn from_file(path: &str) -> Result<Self, AppError> {
+        let string =
+            fs::read_to_string(path).map_err(|e| AppError::io(PATH, "from_file", "00", e))?;
+
+        let mut column_map = Self::new();
+        for line in string.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let v: Vec<&str> = line.split(',').collect();
+            for alias in &v[1..] {
+                column_map.add_alias(alias, v[0]).map_err(|e| {
+                    AppError::parse(PATH, "from_file", &["01", path].join(" | "), e)
+                })?;
+            }
+        }
+        Ok(column_map)
+    }
+
+    pub fn add_alias(&mut self, alias: &str, field: &str) -> Result<(), String> {
+        let pos = FIELDS
+            .iter()
+            .position(|f| *f == Self::normalize(field))
+            .ok_or_else(|| ["unknown field", field].join(" | "))?;
+        self.aliases.insert(Self::normalize(alias), pos);
+        Ok(())
+    }
+
+    // columns with no known name are skipped instead of refused
+    pub fn set_ignore_unknown(&mut self, ignore_unknown: bool) {
+        self.ignore_unknown = ignore_unknown;
+    }
+
+    // position of the field a column name stands for
+    pub fn field(&self, name: &str) -> Option<usize> {
+        self.aliases.get(&Self::normalize(name)).copied()
+    }
+
+    // a header row names at least one field + has no transaction type in its type column
+    pub fn is_header(&self, record: &ByteRecord, columns: &TxColumns) -> bool {
+        if let Some(Some(pos)) = columns.positions.get(TYPE_POS) {
+            if let Some(field) = record.get(*pos) {
+                if TxRecordType::from_binary(field) != TxRecordType::NONE {
+                    return false;
+                }
+            }
+        }
+
+        record
+            .iter()
+            .any(|f| str::from_utf8(f).ok().and_then(|s| self.field(s)).is_some())
+    }
+
+    // the columns of a header row. the amount + to may be left out, the type, client + tx may not.
+    pub fn resolve(&self, record: &ByteRecord) -> Result<TxColumns, String> {
+        let mut positions = [None; MAX_CSV_ROW_LEN];
+        for (i, f) in record.iter().enumerate() {
+            let name = String::from_utf8_lossy(f);
+            match self.field(&name) {
+                Some(pos) => {
+                    if positions[pos].is_some() {
+                        return Err(["duplicate column", &name].join(" | "));
+                    }
+                    positions[pos] = Some(i);
+                }
+                None => {
+                    if !self.ignore_unknown {
+                        return Err(["unknown column", &name].join(" | "));
+                    }
+                }
+            }
+        }
+
+        for pos in [TYPE_POS, CLIENT_POS, TX_POS] {
+            if positions[pos].is_none() {
+                return Err(["missing column", FIELDS[pos]].join(" | "));
+            }
+        }
+        Ok(TxColumns { positions })
+    }
+
+    fn normalize(name: &str) -> String {
+        name.to_lowercase().replace(' ', "")
+    }
+}
+
+// where each field is in the rows of a file. files without a header are in "type,client,tx,amount,to" order.
+#[derive(Debug, Clone)]
+pub struct TxColumns {
+    positions: [Option<usize>; MAX_CSV_ROW_LEN],
+}
+
+impl Default for TxColumns {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxColumns {
+    pub fn new() -> Self {
+        Self {
+            positions: [
+                Some(TYPE_POS),
+                Some(CLIENT_POS),
+                Some(TX_POS),
+                Some(AMOUNT_POS),
+                Some(TO_POS),
+            ],
+        }
+    }
+
+    // copy the fields of a row in "type,client,tx,amount,to" order, up to the first missing one
+    pub fn remap(&self, record: &ByteRecord, mapped: &mut ByteRecord) {
+        mapped.clear();
+        for pos in self.positions.iter() {
+            match pos.and_then(|i| record.get(i)) {
+                Some(field) => mapped.push_field(field),
+                None => break,
+            }
+        }
+        mapped.set_position(record.position().cloned());
+    }
+}
@@ -0,0 +1,188 @@
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use super::account::Account;
+use super::output::{Output, OutputFormat};
+use super::store::Store;
+use super::tx_history::TxHistory;
+use super::tx_record::{ClientId, TxId, TxRecordType};
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_dispute";
+
+const HEADER: [&str; 5] = ["tx", "seq", "state", "amount", "round"];
+
+// how many times a tx can be disputed again once part of it was resolved, and in which states.
+// the state is that of the latest dispute, resolve or chargeback on the tx.
+// with no re-disputes allowed, a resolved part is settled for good.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisputePolicy {
+    limit: u32,
+    after: Vec<TxRecordType>,
+}
+
+impl Default for DisputePolicy {
+    fn default() -> Self {
+        Self {
+            limit: 0,
+            after: vec![TxRecordType::RESOLVE],
+        }
+    }
+}
+
+impl DisputePolicy {
+    pub fn new(limit: u32, after: Vec<TxRecordType>) -> Self {
+        Self { limit, after }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    // a tx that was disputed again round times can be once more, from this state
+    pub fn allows(&self, round: u32, state_id: &TxRecordType) -> bool {
+        round < self.limit && self.after.contains(state_id)
+    }
+}
+
+// one dispute, resolve or chargeback applied to a tx. the events of a tx are only ever appended to,
+// so the whole lifecycle is kept while the conflict parts only hold where the amount stands.
+// the round is how many times the tx was disputed again after a resolve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TxEvent {
+    pub tx_id: TxId,
+    pub state_id: TxRecordType,
+    pub amount: Decimal,
+    pub round: u32,
+}
+
+impl TxEvent {
+    pub fn key(tx_id: &TxId) -> String {
+        ["e_", &tx_id.to_string()].join("")
+    }
+
+    // a value that can't be read is reported, a corrupt history is not applied to
+    pub fn new_from_string(string: &str) -> Result<Self, AppError> {
+        let invalid = || {
+            AppError::storage(
+                PATH,
+                "new_from_string",
+                "00",
+                ["invalid event", string].join(" | "),
+            )
+        };
+
+        let a: Vec<&str> = string.split(',').collect();
+        if a.len() != 4 {
+            return Err(invalid());
+        }
+        let tx_id = a[0]
+            .replace("e_", "")
+            .parse::<TxId>()
+            .map_err(|_| invalid())?;
+        let state_id = TxRecordType::from_binary(a[1].as_bytes());
+        let amount = Decimal::from_str(a[2]).map_err(|_| invalid())?;
+        let round = a[3].parse::<u32>().map_err(|_| invalid())?;
+        Ok(Self {
+            tx_id,
+            state_id,
+            amount,
+            round,
+        })
+    }
+
+    // the events of a tx are separated by ";", oldest first
+    pub fn list_from_string(string: &str) -> Result<Vec<Self>, AppError> {
+        string
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(Self::new_from_string)
+            .collect()
+    }
+
+    pub fn list_to_string(events: &[Self]) -> String {
+        events
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+}
+
+impl fmt::Display for TxEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "e_{},{},{:.4},{}",
+            self.tx_id,
+            self.state_id.to_string(),
+            self.amount,
+            self.round
+        )
+    }
+}
+
+// an event of the dispute log, seq is its position in the events of the tx
+#[derive(Debug, Clone, Serialize)]
+pub struct DisputeLine {
+    pub tx: TxId,
+    pub seq: usize,
+    pub state: String,
+    pub amount: String,
+    pub round: u32,
+}
+
+// the published dispute events of a client, by tx then in the order they were applied
+pub struct DisputeLog;
+
+impl DisputeLog {
+    pub fn lines(client_id: ClientId, config: &Config) -> Result<Vec<DisputeLine>, AppError> {
+        Store::check(config)?;
+
+        let history_exists = Path::new(&TxHistory::committed_path(&client_id, config)).exists();
+        if !history_exists && Account::find(client_id, config).is_none() {
+            return Err(AppError::rule(
+                PATH,
+                "lines",
+                "00",
+                ["unknown client", &client_id.to_string()].join(" | "),
+            ));
+        }
+
+        let mut events = Vec::new();
+        if history_exists {
+            let db = TxHistory::open_committed(&client_id, config)?;
+            for entry in db.scan_prefix(b"e_") {
+                let (_, value) = entry.map_err(|e| AppError::storage(PATH, "lines", "01", e))?;
+                events.push(TxEvent::list_from_string(&String::from_utf8_lossy(&value))?);
+            }
+        }
+        events.sort_by_key(|e| e.first().map(|e| e.tx_id));
+
+        Ok(events
+            .into_iter()
+            .flat_map(|e| e.into_iter().enumerate())
+            .map(|(seq, e)| DisputeLine {
+                tx: e.tx_id,
+                seq: seq + 1,
+                state: e.state_id.to_string(),
+                amount: format!("{:.4}", e.amount),
+                round: e.round,
+            })
+            .collect())
+    }
+
+    // stdout when there is no output path
+    pub fn write(
+        client_id: ClientId,
+        config: &Config,
+        output: Option<&str>,
+        format: OutputFormat,
+    ) -> Result<(), AppError> {
+        let lines = Self::lines(client_id, config)?;
+        Output::write_rows(&HEADER, &lines, Output::target(output)?, format)
+    }
+}
@@ -1,55 +1,107 @@
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
 
-use crate::lib::constants::TRANSACTION_DIR;
+use crate::common::config::Config;
+use crate::common::constants::{FN_NEW, HISTORY_BACKUP_SUFFIX, SUMMARY_TRANSACTION_DIR};
+use crate::common::db;
+use crate::common::error::AppError;
 
-use super::tx_record::{TxConflict, TxRecordType, TxRow};
+use super::tx_dispute::{DisputePolicy, TxEvent};
+use super::tx_record::{ClientId, TxConflict, TxId, TxRecordType, TxRow};
+use super::tx_rejection::TxRejectReason;
 
+const PATH: &str = "model/tx_history";
+const UNDO_TREE: &str = "undo";
+
+// a key + its value before a publish, empty when it did not exist
+type BackupEntry = (Vec<u8>, Vec<u8>);
+
+// the tx ids a restore removed, put back + changed
+type RestoredTxIds = (Vec<TxId>, Vec<TxId>, Vec<TxId>);
+
+// what a dispute, resolve or chargeback came to, or why it was rejected
+pub type Settled<T> = Result<T, TxRejectReason>;
+
+// writes go to a staged db inside the summary dir of the current run.
+// reads fall through to the committed db in the transaction dir.
+// the staged db is only merged into the committed db by publish during update_accounts.
 pub struct TxHistory {
-    client_id: u16,
+    client_id: ClientId,
     db: sled::Db,
-    cache: HashMap<u32, TxRow>,
-    conflict_cache: HashMap<String, TxConflict>,
+    committed_db: Option<sled::Db>,
+    cache: HashMap<TxId, TxRow>,
+    conflict_cache: HashMap<String, Vec<TxConflict>>,
+    event_cache: HashMap<String, Vec<TxEvent>>,
+    dispute_policy: DisputePolicy,
 }
 
 impl TxHistory {
-    pub fn new(client_id: &u16) -> Self {
-        let path = [TRANSACTION_DIR, "/", &client_id.to_string(), "_db"].join("");
-        let db = sled::open(path).unwrap();
-        Self {
-            client_id: client_id.clone(),
+    pub fn new(client_id: &ClientId, summary_dir: &str, config: &Config) -> Result<Self, AppError> {
+        let db = Self::open(&Self::staged_path(client_id, summary_dir), FN_NEW, "00")?;
+
+        // don't create the committed db during stage 1
+        let committed_path = Self::committed_path(client_id, config);
+        let mut committed_db = None;
+        if Path::new(&committed_path).exists() {
+            committed_db = Some(Self::open(&committed_path, FN_NEW, "01")?);
+        }
+
+        Ok(Self {
+            client_id: *client_id,
             db,
+            committed_db,
             cache: HashMap::new(),
             conflict_cache: HashMap::new(),
-        }
+            event_cache: HashMap::new(),
+            dispute_policy: config.dispute_policy().clone(),
+        })
+    }
+
+    pub fn staged_path(client_id: &ClientId, summary_dir: &str) -> String {
+        [
+            summary_dir,
+            "/",
+            SUMMARY_TRANSACTION_DIR,
+            "/",
+            &client_id.to_string(),
+            "_db",
+        ]
+        .join("")
     }
 
-    pub fn get_tx(&mut self, tx_id: &u32) -> Option<TxRow> {
+    pub fn committed_path(client_id: &ClientId, config: &Config) -> String {
+        [
+            &config.transaction_dir(),
+            "/",
+            &client_id.to_string(),
+            "_db",
+        ]
+        .join("")
+    }
+
+    pub fn get_tx(&mut self, tx_id: &TxId) -> Result<Option<TxRow>, AppError> {
         // check cache
         if let Some(row) = self.cache.get(tx_id) {
-            return Some(row.clone());
+            return Ok(Some(*row));
         }
 
-        if let Ok(row) = self.db.get(tx_id.to_string().as_bytes()) {
-            if let Some(data) = row {
-                if let Ok(string) = String::from_utf8(data.to_vec()) {
-                    let row = TxRow::new_from_string(&string);
-                    self.cache.insert(tx_id.clone(), row.clone());
-                    return Some(row);
-                }
-            }
+        if let Some(string) = self.get(tx_id.to_string().as_bytes())? {
+            let row = TxRow::new_from_string(&string)?;
+            self.cache.insert(*tx_id, row);
+            return Ok(Some(row));
         }
-        return None;
+        Ok(None)
     }
 
     pub fn set_tx(
         &mut self,
         tx_type: &TxRecordType,
-        client_id: &u16,
-        tx_id: &u32,
+        client_id: &ClientId,
+        tx_id: &TxId,
         amount: &Decimal,
     ) -> bool {
-        if tx_type.conflict_type() {
+        if !tx_type.funds_type() {
             return false;
         }
 
@@ -61,57 +113,209 @@ impl TxHistory {
             return false;
         }
 
-        self.cache.remove(&tx_id.clone());
+        self.cache.remove(tx_id);
         let key = tx_id.to_string();
         let data = TxRow::to_string(tx_type, client_id, tx_id, amount);
         let result = self.db.insert(key.as_bytes(), data.as_bytes());
         result.is_ok()
     }
 
-    pub fn contains_conflict_key(&self, key: &str) -> bool {
-        if self.conflict_cache.contains_key(key) {
-            return true;
+    // the parts of a tx under a conflict, oldest first
+    pub fn get_conflicts(&mut self, tx_id: &TxId) -> Result<Vec<TxConflict>, AppError> {
+        let key = TxConflict::key(tx_id);
+        if let Some(conflicts) = self.conflict_cache.get(&key) {
+            return Ok(conflicts.clone());
         }
 
-        let result = self.db.contains_key(key.as_bytes());
-        if result.is_ok() {
-            return result.unwrap();
+        let conflicts = match self.get(key.as_bytes())? {
+            Some(string) => TxConflict::list_from_string(&string)?,
+            None => Vec::new(),
+        };
+        self.conflict_cache.insert(key, conflicts.clone());
+        Ok(conflicts)
+    }
+
+    pub fn set_conflicts(&mut self, tx_id: &TxId, conflicts: &[TxConflict]) -> bool {
+        let key = TxConflict::key(tx_id);
+        self.conflict_cache.remove(&key);
+        let data = TxConflict::list_to_string(conflicts);
+        let result = self.db.insert(key.as_bytes(), data.as_bytes());
+        self.commit();
+        result.is_ok()
+    }
+
+    // the disputes, resolves + chargebacks applied to a tx, oldest first
+    pub fn get_events(&mut self, tx_id: &TxId) -> Result<Vec<TxEvent>, AppError> {
+        let key = TxEvent::key(tx_id);
+        if let Some(events) = self.event_cache.get(&key) {
+            return Ok(events.clone());
         }
 
-        return false;
+        let events = match self.get(key.as_bytes())? {
+            Some(string) => TxEvent::list_from_string(&string)?,
+            None => Vec::new(),
+        };
+        self.event_cache.insert(key, events.clone());
+        Ok(events)
     }
 
-    pub fn get_conflict(&mut self, tx_id: &u32) -> Option<TxConflict> {
-        let key = TxConflict::key(tx_id);
-        if let Some(row) = self.conflict_cache.get(&key) {
-            return Some(row.clone());
+    pub fn set_events(&mut self, tx_id: &TxId, events: &[TxEvent]) -> bool {
+        let key = TxEvent::key(tx_id);
+        self.event_cache.remove(&key);
+        let data = TxEvent::list_to_string(events);
+        let result = self.db.insert(key.as_bytes(), data.as_bytes());
+        self.commit();
+        result.is_ok()
+    }
+
+    // the last period a schedule rule was applied for, by the key of the rule
+    pub fn get_period(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.get(key.as_bytes())
+    }
+
+    pub fn set_period(&mut self, key: &str, period: &str) -> bool {
+        let result = self.db.insert(key.as_bytes(), period.as_bytes());
+        result.is_ok()
+    }
+
+    fn push_event(
+        &mut self,
+        tx_id: &TxId,
+        state_id: TxRecordType,
+        amount: Decimal,
+        redispute: bool,
+    ) -> Result<(), AppError> {
+        let mut events = self.get_events(tx_id)?;
+        let round = events.last().map(|e| e.round).unwrap_or(0) + redispute as u32;
+        events.push(TxEvent {
+            tx_id: *tx_id,
+            state_id,
+            amount,
+            round,
+        });
+        self.set_events(tx_id, &events);
+        Ok(())
+    }
+
+    // dispute part of a tx, or all of what can be disputed when the amount is zero.
+    // the undisputed remainder is taken first. resolved parts can only be disputed again as the dispute policy
+    // allows, charged back parts never. the disputed amount is returned, an error if the history can't be read.
+    pub fn dispute(&mut self, tx: &TxRow, amount: &Decimal) -> Result<Settled<Decimal>, AppError> {
+        let mut conflicts = self.get_conflicts(&tx.tx_id)?;
+        let events = self.get_events(&tx.tx_id)?;
+        let zero = Decimal::new(0, 0);
+        let remainder = tx.amount - conflicts.iter().map(|c| c.amount).sum::<Decimal>();
+        let resolved: Decimal = conflicts
+            .iter()
+            .filter(|c| c.state_id == TxRecordType::RESOLVE)
+            .map(|c| c.amount)
+            .sum();
+
+        let redispute = match events.last() {
+            Some(last) => resolved > zero && self.dispute_policy.allows(last.round, &last.state_id),
+            None => false,
+        };
+        // only a policy that takes re-disputes refuses them, otherwise the tx is disputed as far as it goes
+        let refused = resolved > zero && !redispute && self.dispute_policy.limit() > 0;
+        let disputable = if redispute {
+            remainder + resolved
+        } else {
+            remainder
+        };
+        if disputable <= zero {
+            return Ok(Err(if refused {
+                TxRejectReason::REDISPUTE_REFUSED
+            } else {
+                TxRejectReason::ALREADY_DISPUTED
+            }));
         }
 
-        if let Ok(row) = self.db.get(key.as_bytes()) {
-            if let Some(data) = row {
-                if let Ok(string) = String::from_utf8(data.to_vec()) {
-                    let row = TxConflict::new_from_string(&string);
-                    self.conflict_cache.insert(key, row.clone());
-                    return Some(row);
-                }
+        let amount = if amount.is_zero() {
+            disputable
+        } else {
+            *amount
+        };
+        if amount > disputable {
+            return Ok(Err(if refused && amount <= remainder + resolved {
+                TxRejectReason::REDISPUTE_REFUSED
+            } else {
+                TxRejectReason::EXCEEDS_UNDISPUTED
+            }));
+        }
+
+        // what the remainder doesn't cover is taken from the oldest resolved parts
+        let mut left = (amount - remainder).max(zero);
+        let redisputed = left > zero;
+        for conflict in conflicts.iter_mut() {
+            if left.is_zero() {
+                break;
+            }
+            if conflict.state_id != TxRecordType::RESOLVE {
+                continue;
             }
+
+            let part = conflict.amount.min(left);
+            conflict.amount -= part;
+            left -= part;
         }
-        return None;
+        conflicts.retain(|c| !c.amount.is_zero());
+
+        conflicts.push(TxConflict {
+            tx_id: tx.tx_id,
+            type_id: tx.type_id,
+            state_id: TxRecordType::DISPUTE,
+            amount,
+        });
+        self.set_conflicts(&tx.tx_id, &conflicts);
+        self.push_event(&tx.tx_id, TxRecordType::DISPUTE, amount, redisputed)?;
+        Ok(Ok(amount))
     }
 
-    pub fn set_conflict(
+    // resolve or charge back part of what is under dispute on a tx, or all of it when the amount is zero.
+    // the oldest disputes are settled first. the type of the tx + the settled amount are returned, an error if the history can't be read.
+    pub fn settle(
         &mut self,
-        tx_id: &u32,
-        type_id: &TxRecordType,
+        tx_id: &TxId,
         state_id: &TxRecordType,
         amount: &Decimal,
-    ) -> bool {
-        let key = TxConflict::key(tx_id);
-        self.conflict_cache.remove(&key.clone());
-        let data = TxConflict::to_string(tx_id, type_id, state_id, amount);
-        let result = self.db.insert(key.as_bytes(), data.as_bytes());
-        self.commit();
-        result.is_ok()
+    ) -> Result<Settled<(TxRecordType, Decimal)>, AppError> {
+        let mut conflicts = self.get_conflicts(tx_id)?;
+        let disputed = TxConflict::disputed(&conflicts);
+        if disputed <= Decimal::new(0, 0) {
+            return Ok(Err(TxRejectReason::NOT_DISPUTED));
+        }
+
+        let amount = if amount.is_zero() { disputed } else { *amount };
+        if amount > disputed {
+            return Ok(Err(TxRejectReason::EXCEEDS_DISPUTED));
+        }
+
+        let mut left = amount;
+        let mut settled = Vec::new();
+        for conflict in conflicts.iter_mut() {
+            if left.is_zero() {
+                break;
+            }
+            if conflict.state_id != TxRecordType::DISPUTE {
+                continue;
+            }
+
+            let part = conflict.amount.min(left);
+            conflict.amount -= part;
+            left -= part;
+            settled.push(TxConflict {
+                state_id: *state_id,
+                amount: part,
+                ..*conflict
+            });
+        }
+        conflicts.retain(|c| !c.amount.is_zero());
+        conflicts.append(&mut settled);
+
+        let type_id = conflicts[0].type_id;
+        self.set_conflicts(tx_id, &conflicts);
+        self.push_event(tx_id, *state_id, amount, false)?;
+        Ok(Ok((type_id, amount)))
     }
 
     pub fn commit(&mut self) {
@@ -121,5 +325,249 @@ impl TxHistory {
     pub fn clear_cache(&mut self) {
         self.cache.clear();
         self.conflict_cache.clear();
+        self.event_cache.clear();
+    }
+
+    // the history backup taken by a publish, next to the account backup of the same time
+    pub fn backup_path(client_id: &ClientId, millis: i64, config: &Config) -> String {
+        [
+            &config.account_backup_dir(),
+            "/",
+            &client_id.to_string(),
+            "_",
+            &millis.to_string(),
+            HISTORY_BACKUP_SUFFIX,
+        ]
+        .join("")
+    }
+
+    // merge the staged db of a client into the committed db.
+    // the previous committed values are saved in the undo tree of the staged db first,
+    // so rollback can restore the committed db if the run fails later on.
+    // they are also written to the backup file, so a restore can undo the publish for good.
+    pub fn publish(
+        client_id: &ClientId,
+        summary_dir: &str,
+        backup_path: &str,
+        config: &Config,
+    ) -> Result<(), AppError> {
+        let staged_path = Self::staged_path(client_id, summary_dir);
+        if !Path::new(&staged_path).exists() {
+            return Self::write_backup(backup_path, &[]);
+        }
+
+        let staged_db = Self::open(&staged_path, "publish", "00")?;
+        let committed_db = Self::open(&Self::committed_path(client_id, config), "publish", "01")?;
+        let undo = staged_db
+            .open_tree(UNDO_TREE)
+            .map_err(|e| AppError::storage(PATH, "publish", "02", e))?;
+
+        let mut undo_batch = sled::Batch::default();
+        let mut batch = sled::Batch::default();
+        let mut backup = Vec::new();
+        for entry in staged_db.iter() {
+            let (key, value) = entry.map_err(|e| AppError::storage(PATH, "publish", "03", e))?;
+            let previous = committed_db
+                .get(&key)
+                .map_err(|e| AppError::storage(PATH, "publish", "04", e))?;
+
+            // an empty value marks a key that did not exist in the committed db
+            let previous = previous.map(|v| v.to_vec()).unwrap_or_default();
+            undo_batch.insert(key.clone(), previous.clone());
+            backup.push((key.to_vec(), previous));
+            batch.insert(key, value);
+        }
+
+        undo.apply_batch(undo_batch)
+            .map_err(|e| AppError::storage(PATH, "publish", "05", e))?;
+        undo.flush()
+            .map_err(|e| AppError::storage(PATH, "publish", "06", e))?;
+        Self::write_backup(backup_path, &backup)?;
+
+        committed_db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "publish", "07", e))?;
+        committed_db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "publish", "08", e))?;
+        Ok(())
+    }
+
+    // restore the committed db of a client from the undo tree written by publish.
+    pub fn rollback(
+        client_id: &ClientId,
+        summary_dir: &str,
+        config: &Config,
+    ) -> Result<(), AppError> {
+        let staged_path = Self::staged_path(client_id, summary_dir);
+        if !Path::new(&staged_path).exists() {
+            return Ok(());
+        }
+
+        let staged_db = Self::open(&staged_path, "rollback", "00")?;
+        let undo = staged_db
+            .open_tree(UNDO_TREE)
+            .map_err(|e| AppError::storage(PATH, "rollback", "01", e))?;
+        if undo.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = sled::Batch::default();
+        for entry in undo.iter() {
+            let (key, value) = entry.map_err(|e| AppError::storage(PATH, "rollback", "02", e))?;
+            if value.is_empty() {
+                batch.remove(key);
+            } else {
+                batch.insert(key, value);
+            }
+        }
+
+        let committed_db = Self::open(&Self::committed_path(client_id, config), "rollback", "03")?;
+        committed_db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "rollback", "04", e))?;
+        committed_db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "rollback", "05", e))?;
+        Ok(())
+    }
+
+    // put the committed db of a client back as it was before the publishes of the backups, given newest first.
+    // the values it replaces are written to a new backup first, so the restore can be undone in turn.
+    // the tx ids whose rows are removed, those whose rows are put back, then every tx id with a row,
+    // conflict or event changed are returned.
+    pub fn restore(
+        client_id: &ClientId,
+        backup_paths: &[String],
+        restore_backup_path: &str,
+        config: &Config,
+    ) -> Result<RestoredTxIds, AppError> {
+        // the oldest backup of a key wins
+        let mut values = BTreeMap::new();
+        for backup_path in backup_paths {
+            for (key, value) in Self::read_backup(backup_path)? {
+                values.insert(key, value);
+            }
+        }
+
+        let committed_db = Self::open(&Self::committed_path(client_id, config), "restore", "00")?;
+        let mut backup = Vec::new();
+        let mut batch = sled::Batch::default();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let mut changed = BTreeSet::new();
+        for (key, value) in values {
+            let current = committed_db
+                .get(&key)
+                .map_err(|e| AppError::storage(PATH, "restore", "01", e))?;
+
+            // conflict + event keys are not tx ids
+            let string = String::from_utf8_lossy(&key).to_string();
+            if let Ok(tx_id) = string.parse::<TxId>() {
+                if value.is_empty() && current.is_some() {
+                    removed.push(tx_id);
+                } else if !value.is_empty() && current.is_none() {
+                    added.push(tx_id);
+                }
+            }
+            if let Some(tx_id) = Self::key_tx_id(&string) {
+                if current.as_deref().unwrap_or_default() != value.as_slice() {
+                    changed.insert(tx_id);
+                }
+            }
+            backup.push((key.clone(), current.map(|v| v.to_vec()).unwrap_or_default()));
+
+            if value.is_empty() {
+                batch.remove(key);
+            } else {
+                batch.insert(key, value);
+            }
+        }
+        Self::write_backup(restore_backup_path, &backup)?;
+
+        committed_db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "restore", "02", e))?;
+        committed_db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "restore", "03", e))?;
+        Ok((removed, added, changed.into_iter().collect()))
+    }
+
+    // the tx id of a row key, or of a conflict (c_) or event (e_) key. schedule period keys (s_) are about no tx.
+    fn key_tx_id(key: &str) -> Option<TxId> {
+        let id = match key.split_once('_') {
+            Some(("c", id)) | Some(("e", id)) => id,
+            Some(_) => return None,
+            None => key,
+        };
+        id.parse::<TxId>().ok()
+    }
+
+    // "key,value" rows, an empty value marks a key that did not exist
+    fn write_backup(path: &str, entries: &[BackupEntry]) -> Result<(), AppError> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .map_err(|e| AppError::io(PATH, "write_backup", "00", e))?;
+        for (key, value) in entries {
+            writer
+                .write_record([key, value])
+                .map_err(|e| AppError::io(PATH, "write_backup", "01", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| AppError::io(PATH, "write_backup", "02", e))
+    }
+
+    fn read_backup(path: &str) -> Result<Vec<BackupEntry>, AppError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .map_err(|e| AppError::io(PATH, "read_backup", "00", e))?;
+
+        let mut entries = Vec::new();
+        for record in reader.byte_records() {
+            let record = record.map_err(|e| AppError::parse(PATH, "read_backup", "01", e))?;
+            let key = record.get(0).unwrap_or_default().to_vec();
+            let value = record.get(1).unwrap_or_default().to_vec();
+            entries.push((key, value));
+        }
+        Ok(entries)
+    }
+
+    // the staged value of a key, or the committed one. a store that can't be read is reported.
+    fn get(&self, key: &[u8]) -> Result<Option<String>, AppError> {
+        let mut data = self
+            .db
+            .get(key)
+            .map_err(|e| AppError::storage(PATH, "get", "00", e))?;
+        if data.is_none() {
+            if let Some(committed_db) = &self.committed_db {
+                data = committed_db
+                    .get(key)
+                    .map_err(|e| AppError::storage(PATH, "get", "01", e))?;
+            }
+        }
+
+        match data {
+            Some(data) => String::from_utf8(data.to_vec())
+                .map(Some)
+                .map_err(|e| AppError::storage(PATH, "get", "02", e)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn open_committed(client_id: &ClientId, config: &Config) -> Result<sled::Db, AppError> {
+        Self::open(
+            &Self::committed_path(client_id, config),
+            "open_committed",
+            "00",
+        )
+    }
+
+    fn open(path: &str, method: &str, tag: &str) -> Result<sled::Db, AppError> {
+        db::open(path).map_err(|e| AppError::storage(PATH, method, tag, e))
     }
 }
@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::tx_record::{ClientId, TxId};
+use crate::common::config::Config;
+use crate::common::constants::{FN_NEW, SUMMARY_TX_INDEX_DIR};
+use crate::common::db;
+use crate::common::error::AppError;
+use crate::common::lock::Lock;
+
+const PATH: &str = "model/tx_index";
+
+// the next id for rows the run generates itself, like fees + interest.
+// they are taken from the base up, out of the way of the ids of input rows.
+const GENERATED_KEY: &str = "g_next";
+const GENERATED_TX_BASE: TxId = 1 << 62;
+
+// the values of the tx ids a restore freed
+const FREED_TREE: &str = "freed";
+
+// "d_<client>" is "1" while the schedule has to go back to a client in a period it was applied in already
+const DUE_PREFIX: &str = "d_";
+
+// index of every deposit, withdrawal + transfer tx id, across clients and runs.
+// the value is the client id, or "<from>,<to>" for a transfer.
+// the ids claimed by a run are staged in its summary dir, lookups fall through to the committed index.
+// the staged index is merged into the committed index once the accounts of the run are published.
+pub struct TxIndex {
+    db: sled::Db,
+    committed_db: Option<sled::Db>,
+}
+
+impl TxIndex {
+    pub fn new(summary_dir: &str, config: &Config) -> Result<Self, AppError> {
+        let db = Self::open(&Self::staged_path(summary_dir), FN_NEW, "00")?;
+
+        // don't create the committed index during stage 1
+        let mut committed_db = None;
+        if Path::new(&config.tx_index_dir()).exists() {
+            committed_db = Some(Self::open(&config.tx_index_dir(), FN_NEW, "01")?);
+        }
+
+        Ok(Self { db, committed_db })
+    }
+
+    // the lock of the committed index. a run holds it from its first claim until its ids are published,
+    // so runs on other inputs never claim the same id.
+    pub fn lock(config: &Config) -> Lock {
+        Lock::new(&[&config.journal_dir(), "/tx_index.lock"].join(""))
+    }
+
+    // take the lock of the committed index, an error if a live run holds it
+    pub fn acquire(config: &Config) -> Result<Lock, AppError> {
+        let lock = Self::lock(config);
+        if !lock
+            .acquire()
+            .map_err(|e| AppError::io(PATH, "acquire", "00", e))?
+        {
+            return Err(AppError::concurrency(
+                PATH,
+                "acquire",
+                "01",
+                "tx index in use by another run",
+            ));
+        }
+        Ok(lock)
+    }
+
+    pub fn staged_path(summary_dir: &str) -> String {
+        [summary_dir, SUMMARY_TX_INDEX_DIR].join("/")
+    }
+
+    // claim a tx id for a client. false if the id is already taken.
+    pub fn claim(&self, tx_id: &TxId, client_id: &ClientId) -> Result<bool, AppError> {
+        self.claim_value(tx_id, &client_id.to_string())
+    }
+
+    // claim a tx id for a transfer between two clients
+    pub fn claim_transfer(
+        &self,
+        tx_id: &TxId,
+        from: &ClientId,
+        to: &ClientId,
+    ) -> Result<bool, AppError> {
+        self.claim_value(tx_id, &[from.to_string(), to.to_string()].join(","))
+    }
+
+    // the sender + recipient of a tx id, if it was claimed by a transfer
+    pub fn transfer(&self, tx_id: &TxId) -> Result<Option<(ClientId, ClientId)>, AppError> {
+        let key = tx_id.to_string();
+        let mut value = self
+            .db
+            .get(key.as_bytes())
+            .map_err(|e| AppError::storage(PATH, "transfer", "00", e))?;
+        if value.is_none() {
+            if let Some(committed_db) = &self.committed_db {
+                value = committed_db
+                    .get(key.as_bytes())
+                    .map_err(|e| AppError::storage(PATH, "transfer", "01", e))?;
+            }
+        }
+
+        Ok(value.and_then(|v| {
+            let string = String::from_utf8_lossy(&v).to_string();
+            let (from, to) = string.split_once(',')?;
+            Some((from.parse().ok()?, to.parse().ok()?))
+        }))
+    }
+
+    // claim the next free generated tx id for a client
+    pub fn generate(&self, client_id: &ClientId) -> Result<TxId, AppError> {
+        let mut value = self
+            .db
+            .get(GENERATED_KEY.as_bytes())
+            .map_err(|e| AppError::storage(PATH, "generate", "00", e))?;
+        if value.is_none() {
+            if let Some(committed_db) = &self.committed_db {
+                value = committed_db
+                    .get(GENERATED_KEY.as_bytes())
+                    .map_err(|e| AppError::storage(PATH, "generate", "01", e))?;
+            }
+        }
+
+        let mut tx_id = value
+            .and_then(|v| String::from_utf8_lossy(&v).parse::<TxId>().ok())
+            .unwrap_or(GENERATED_TX_BASE);
+        while !self.claim(&tx_id, client_id)? {
+            tx_id += 1;
+        }
+
+        self.db
+            .insert(GENERATED_KEY.as_bytes(), (tx_id + 1).to_string().as_bytes())
+            .map_err(|e| AppError::storage(PATH, "generate", "02", e))?;
+        Ok(tx_id)
+    }
+
+    // the period a schedule rule was last applied to every account in, by the key of the rule
+    pub fn schedule_period(&self, key: &str) -> Result<Option<String>, AppError> {
+        let mut value = self
+            .db
+            .get(key.as_bytes())
+            .map_err(|e| AppError::storage(PATH, "schedule_period", "00", e))?;
+        if value.is_none() {
+            if let Some(committed_db) = &self.committed_db {
+                value = committed_db
+                    .get(key.as_bytes())
+                    .map_err(|e| AppError::storage(PATH, "schedule_period", "01", e))?;
+            }
+        }
+        Ok(value.map(|v| String::from_utf8_lossy(&v).to_string()))
+    }
+
+    pub fn set_schedule_period(&self, key: &str, period: &str) -> Result<(), AppError> {
+        self.db
+            .insert(key.as_bytes(), period.as_bytes())
+            .map_err(|e| AppError::storage(PATH, "set_schedule_period", "00", e))?;
+        Ok(())
+    }
+
+    // the clients the schedule has to go back to: those with a rejected row, or restored since
+    pub fn schedule_due(&self) -> Result<Vec<ClientId>, AppError> {
+        let mut due = BTreeMap::new();
+        let dbs = self.committed_db.iter().chain([&self.db]);
+        for db in dbs {
+            for entry in db.scan_prefix(DUE_PREFIX.as_bytes()) {
+                let (key, value) =
+                    entry.map_err(|e| AppError::storage(PATH, "schedule_due", "00", e))?;
+                let key = String::from_utf8_lossy(&key).to_string();
+                if let Ok(client_id) = key.trim_start_matches(DUE_PREFIX).parse::<ClientId>() {
+                    due.insert(client_id, value.as_ref() == b"1");
+                }
+            }
+        }
+        Ok(due
+            .into_iter()
+            .filter(|(_, due)| *due)
+            .map(|(client_id, _)| client_id)
+            .collect())
+    }
+
+    pub fn set_schedule_due(&self, client_id: &ClientId, due: bool) -> Result<(), AppError> {
+        let key = [DUE_PREFIX, &client_id.to_string()].join("");
+        let value = if due { "1" } else { "0" };
+        self.db
+            .insert(key.as_bytes(), value.as_bytes())
+            .map_err(|e| AppError::storage(PATH, "set_schedule_due", "00", e))?;
+        Ok(())
+    }
+
+    fn claim_value(&self, tx_id: &TxId, value: &str) -> Result<bool, AppError> {
+        let key = tx_id.to_string();
+        if let Some(committed_db) = &self.committed_db {
+            let found = committed_db
+                .contains_key(key.as_bytes())
+                .map_err(|e| AppError::storage(PATH, "claim", "00", e))?;
+            if found {
+                return Ok(false);
+            }
+        }
+
+        let previous = self
+            .db
+            .compare_and_swap(
+                key.as_bytes(),
+                None as Option<&[u8]>,
+                Some(value.as_bytes()),
+            )
+            .map_err(|e| AppError::storage(PATH, "claim", "01", e))?;
+        Ok(previous.is_ok())
+    }
+
+    // free tx ids claimed by the run, for rows that were rejected
+    pub fn release(&self, tx_ids: &[TxId]) -> Result<(), AppError> {
+        let mut batch = sled::Batch::default();
+        for tx_id in tx_ids {
+            batch.remove(tx_id.to_string().as_bytes());
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "release", "00", e))
+    }
+
+    pub fn commit(&self) -> Result<(), AppError> {
+        self.db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "commit", "00", e))?;
+        Ok(())
+    }
+
+    // merge the staged index of a run into the committed index.
+    // the ids of a run are new to the committed index, so publishing twice is harmless.
+    pub fn publish(summary_dir: &str, config: &Config) -> Result<(), AppError> {
+        let staged_path = Self::staged_path(summary_dir);
+        if !Path::new(&staged_path).exists() {
+            return Ok(());
+        }
+
+        let staged_db = Self::open(&staged_path, "publish", "00")?;
+        let committed_db = Self::open(&config.tx_index_dir(), "publish", "01")?;
+
+        let mut batch = sled::Batch::default();
+        for entry in staged_db.iter() {
+            let (key, value) = entry.map_err(|e| AppError::storage(PATH, "publish", "02", e))?;
+            batch.insert(key, value);
+        }
+
+        committed_db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "publish", "03", e))?;
+        committed_db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "publish", "04", e))?;
+        Ok(())
+    }
+
+    // a restore frees the tx ids of the rows it removes, so they can be applied again,
+    // + claims back those of the rows it puts back. the value of a freed id is kept aside,
+    // so a transfer is claimed back for both its parties. the schedule goes back to the client on its next run.
+    // the other party of every transfer among the changed tx ids is returned, it has to be restored with the client.
+    pub fn restore(
+        client_id: &ClientId,
+        removed: &[TxId],
+        added: &[TxId],
+        changed: &[TxId],
+        config: &Config,
+    ) -> Result<Vec<ClientId>, AppError> {
+        if removed.is_empty() && added.is_empty() && changed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let committed_db = Self::open(&config.tx_index_dir(), "restore", "00")?;
+        let freed = committed_db
+            .open_tree(FREED_TREE)
+            .map_err(|e| AppError::storage(PATH, "restore", "01", e))?;
+
+        let mut parties = Vec::new();
+        for tx_id in changed {
+            let key = tx_id.to_string();
+            let mut value = committed_db
+                .get(key.as_bytes())
+                .map_err(|e| AppError::storage(PATH, "restore", "02", e))?;
+            if value.is_none() {
+                value = freed
+                    .get(key.as_bytes())
+                    .map_err(|e| AppError::storage(PATH, "restore", "03", e))?;
+            }
+
+            let string = value
+                .map(|v| String::from_utf8_lossy(&v).to_string())
+                .unwrap_or_default();
+            if let Some((from, to)) = string.split_once(',') {
+                for party in [from, to] {
+                    if let Ok(party) = party.parse::<ClientId>() {
+                        if party != *client_id && !parties.contains(&party) {
+                            parties.push(party);
+                        }
+                    }
+                }
+            }
+        }
+
+        // the other party of a transfer may have freed or claimed back the id already
+        let mut batch = sled::Batch::default();
+        for tx_id in removed {
+            let key = tx_id.to_string();
+            let value = committed_db
+                .get(key.as_bytes())
+                .map_err(|e| AppError::storage(PATH, "restore", "04", e))?;
+            if let Some(value) = value {
+                freed
+                    .insert(key.as_bytes(), value)
+                    .map_err(|e| AppError::storage(PATH, "restore", "05", e))?;
+                batch.remove(key.as_bytes());
+            }
+        }
+        for tx_id in added {
+            let key = tx_id.to_string();
+            let found = committed_db
+                .contains_key(key.as_bytes())
+                .map_err(|e| AppError::storage(PATH, "restore", "06", e))?;
+            if found {
+                continue;
+            }
+
+            let value = freed
+                .remove(key.as_bytes())
+                .map_err(|e| AppError::storage(PATH, "restore", "07", e))?;
+            match value {
+                Some(value) => batch.insert(key.as_bytes(), value),
+                None => batch.insert(key.as_bytes(), client_id.to_string().as_bytes()),
+            }
+        }
+
+        let key = [DUE_PREFIX, &client_id.to_string()].join("");
+        batch.insert(key.as_bytes(), "1".as_bytes());
+
+        committed_db
+            .apply_batch(batch)
+            .map_err(|e| AppError::storage(PATH, "restore", "08", e))?;
+        committed_db
+            .flush()
+            .map_err(|e| AppError::storage(PATH, "restore", "09", e))?;
+        Ok(parties)
+    }
+
+    fn open(path: &str, method: &str, tag: &str) -> Result<sled::Db, AppError> {
+        db::open(path).map_err(|e| AppError::storage(PATH, method, tag, e))
+    }
+}
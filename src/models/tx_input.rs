@@ -0,0 +1,327 @@
+use chrono::Utc;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::tx_reader::{Compression, InputFormat, TxQuarantine, TxReader};
+use super::tx_record::TxRow;
+use crate::common::constants::STDIN_PATH;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_input";
+const INPUT_EXTENSIONS: [&str; 3] = ["csv", "ndjson", "jsonl"];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum InputOrder {
+    NAME,
+    MTIME,
+}
+
+impl InputOrder {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "name" => Some(Self::NAME),
+            "mtime" => Some(Self::MTIME),
+            _ => None,
+        }
+    }
+}
+
+// a spooled stream, the file is removed once the spool is dropped
+#[derive(Debug)]
+pub struct TxSpool {
+    path: String,
+}
+
+impl TxSpool {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn rename(&mut self, path: &str) -> Result<(), AppError> {
+        fs::rename(&self.path, path).map_err(|e| AppError::io(PATH, "rename", "00", e))?;
+        self.path = path.to_string();
+        Ok(())
+    }
+}
+
+impl Drop for TxSpool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TxInputReport {
+    pub path: String,
+    pub rows: usize,
+    pub skipped: bool,
+    // rows rejected because their tx id was already used
+    pub duplicates: Vec<TxRow>,
+    // malformed rows set aside in lenient mode
+    pub quarantined: Vec<TxQuarantine>,
+}
+
+impl TxInputReport {
+    pub fn duplicates_to_string(&self) -> Vec<String> {
+        self.duplicates
+            .iter()
+            .map(|r| {
+                let row = TxRow::to_string(&r.type_id, &r.client_id, &r.tx_id, &r.amount);
+                [self.path.as_str(), "duplicate", &row].join(",")
+            })
+            .collect()
+    }
+}
+
+// "path,rows,duplicates,quarantined,status"
+impl fmt::Display for TxInputReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = if self.skipped { "skipped" } else { "applied" };
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.path,
+            self.rows,
+            self.duplicates.len(),
+            self.quarantined.len(),
+            status
+        )
+    }
+}
+
+// turns files, directories and glob patterns into the ordered list of input files of a run.
+pub struct TxInput;
+
+impl TxInput {
+    pub fn resolve(inputs: &[String], order: InputOrder) -> Result<Vec<String>, AppError> {
+        let mut paths = Vec::new();
+        let mut stdin = false;
+        for input in inputs {
+            if input == STDIN_PATH {
+                stdin = true;
+            } else if Self::is_glob(input) {
+                paths.append(&mut Self::expand_glob(input)?);
+            } else if Path::new(input).is_dir() {
+                paths.append(&mut Self::expand_dir(input)?);
+            } else if Path::new(input).is_file() {
+                paths.push(input.to_string());
+            } else {
+                return Err(AppError::io(
+                    PATH,
+                    "resolve",
+                    "00",
+                    ["input not found", input].join(" | "),
+                ));
+            }
+        }
+
+        match order {
+            InputOrder::NAME => {
+                paths.sort_by(|a, b| Self::file_name(a).cmp(Self::file_name(b)).then(a.cmp(b)))
+            }
+            InputOrder::MTIME => {
+                let mut keyed = Vec::new();
+                for path in paths {
+                    keyed.push((Self::modified(&path)?, path));
+                }
+                keyed.sort_by(|a, b| {
+                    a.0.cmp(&b.0)
+                        .then(Self::file_name(&a.1).cmp(Self::file_name(&b.1)))
+                });
+                paths = keyed.into_iter().map(|(_, path)| path).collect();
+            }
+        }
+
+        // stdin is read after the files
+        if stdin {
+            paths.push(STDIN_PATH.to_string());
+        }
+
+        Ok(Self::dedup(paths))
+    }
+
+    // a stream can only be read once, so it is copied to a file in the dir before the run.
+    // the file can then be hashed for the registry, named for the summary dir + reported on like any input.
+    // a stream has no name to tell its format, so the content is sniffed + the file given the extensions a file would have.
+    pub fn spool(mut reader: impl Read, dir: &str) -> Result<TxSpool, AppError> {
+        fs::create_dir_all(dir).map_err(|e| AppError::io(PATH, "spool", "00", e))?;
+
+        let name = [dir, "/stdin_", &Utc::now().timestamp_nanos().to_string()].join("");
+        let mut spool = TxSpool { path: name.clone() };
+        let mut file =
+            File::create(spool.path()).map_err(|e| AppError::io(PATH, "spool", "01", e))?;
+        io::copy(&mut reader, &mut file).map_err(|e| AppError::io(PATH, "spool", "02", e))?;
+
+        let file = File::open(spool.path()).map_err(|e| AppError::io(PATH, "spool", "03", e))?;
+        let compression =
+            Compression::sniff(file).map_err(|e| AppError::io(PATH, "spool", "04", e))?;
+        spool.rename(&[&name, compression.extension()].join(""))?;
+
+        let format = InputFormat::sniff(TxReader::open(spool.path())?)
+            .map_err(|e| AppError::io(PATH, "spool", "05", e))?;
+        spool.rename(&[&name, format.extension(), compression.extension()].join(""))?;
+        Ok(spool)
+    }
+
+    // the manifest lists one input file per line, in the order they are processed.
+    // relative paths are resolved from the manifest dir. blank lines + # comments are skipped.
+    pub fn from_manifest(manifest_path: &str) -> Result<Vec<String>, AppError> {
+        let string = fs::read_to_string(manifest_path)
+            .map_err(|e| AppError::io(PATH, "from_manifest", "00", e))?;
+        let base_dir = Path::new(manifest_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+
+        let mut paths = Vec::new();
+        for line in string.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let path = if Path::new(line).is_absolute() {
+                line.to_string()
+            } else {
+                base_dir.join(line).display().to_string()
+            };
+
+            if !Path::new(&path).is_file() {
+                return Err(AppError::io(
+                    PATH,
+                    "from_manifest",
+                    "01",
+                    ["input not found", &path].join(" | "),
+                ));
+            }
+            paths.push(path);
+        }
+
+        Ok(Self::dedup(paths))
+    }
+
+    fn expand_dir(dir: &str) -> Result<Vec<String>, AppError> {
+        let p = fs::read_dir(dir).map_err(|e| AppError::io(PATH, "expand_dir", "00", e))?;
+
+        let mut paths = Vec::new();
+        for e in p.flatten() {
+            let path = e.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let path_string = path.display().to_string();
+            let (_, stripped) = Compression::from_path(&path_string);
+            let extension = Path::new(stripped)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("");
+            if INPUT_EXTENSIONS.contains(&extension) {
+                paths.push(path.display().to_string());
+            }
+        }
+        Ok(paths)
+    }
+
+    // only the file name part of the pattern may hold wildcards
+    fn expand_glob(pattern: &str) -> Result<Vec<String>, AppError> {
+        let path = Path::new(pattern);
+        let file_pattern = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => Path::new(".").to_path_buf(),
+        };
+
+        if Self::is_glob(&dir.display().to_string()) {
+            return Err(AppError::rule(
+                PATH,
+                "expand_glob",
+                "00",
+                ["unsupported glob", pattern].join(" | "),
+            ));
+        }
+
+        let p = fs::read_dir(&dir).map_err(|e| AppError::io(PATH, "expand_glob", "01", e))?;
+
+        let mut paths = Vec::new();
+        for e in p.flatten() {
+            let path = e.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if Self::glob_match(file_pattern.as_bytes(), name.as_bytes()) {
+                    paths.push(path.display().to_string());
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    // * matches any run of bytes, ? any one byte. on a mismatch only the last * is tried again one byte further,
+    // so the match is linear in the name for each * instead of trying every split.
+    fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+        let (mut p, mut n) = (0, 0);
+        // pattern position after the last *, and the name position it was matched from
+        let mut star: Option<(usize, usize)> = None;
+
+        while n < name.len() {
+            match pattern.get(p) {
+                Some(b'*') => {
+                    p += 1;
+                    star = Some((p, n));
+                }
+                Some(&c) if c == b'?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match star {
+                    Some((star_p, star_n)) => {
+                        p = star_p;
+                        n = star_n + 1;
+                        star = Some((star_p, n));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == b'*')
+    }
+
+    fn is_glob(input: &str) -> bool {
+        input.contains('*') || input.contains('?')
+    }
+
+    fn file_name(path: &str) -> &str {
+        Path::new(path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(path)
+    }
+
+    fn modified(path: &str) -> Result<SystemTime, AppError> {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| AppError::io(PATH, "modified", "00", e))
+    }
+
+    // the same file is only read once, at its first position
+    fn dedup(paths: Vec<String>) -> Vec<String> {
+        let mut seen = Vec::new();
+        let mut v = Vec::new();
+        for path in paths {
+            let key = fs::canonicalize(&path)
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| path.clone());
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            v.push(path);
+        }
+        v
+    }
+}
@@ -1,69 +1,288 @@
-use std::fs::{self, File};
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::str;
 
-use csv::{ByteRecord, Reader, Trim};
-use rust_decimal::Decimal;
+use csv::{ByteRecord, Position, Reader, Trim};
+use rust_decimal::{Decimal, RoundingStrategy};
 
-use super::tx_record::{TxRecord, TxRecordSmall, TxRecordType};
-use crate::lib::{
-    constants::{MIN_CSV_ROW_LEN, TYPE_POS},
+use super::tx_columns::{TxColumnMap, TxColumns};
+use super::tx_record::{ClientId, TxId, TxRecord, TxRecordSmall, TxRecordType};
+use super::tx_rejection::TxRejectReason;
+use crate::common::{
+    constants::{AMOUNT_POS, AMOUNT_SCALE, MAX_CSV_ROW_LEN, MIN_CSV_ROW_LEN, TO_POS, TYPE_POS},
     error::AppError,
 };
 
 const PATH: &str = "model/tx_reader";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ParseMode {
+    STRICT,
+    LENIENT,
+}
+
+impl ParseMode {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "strict" => Some(Self::STRICT),
+            "lenient" => Some(Self::LENIENT),
+            _ => None,
+        }
+    }
+}
+
+// what is done with an amount that has more decimal places than are kept
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PrecisionPolicy {
+    REJECT,
+    // half to even
+    ROUND,
+    TRUNCATE,
+}
+
+impl PrecisionPolicy {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "reject" => Some(Self::REJECT),
+            "round" => Some(Self::ROUND),
+            "truncate" => Some(Self::TRUNCATE),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, amount: Decimal) -> Result<Decimal, TxRejectReason> {
+        if amount.scale() <= AMOUNT_SCALE {
+            return Ok(amount);
+        }
+
+        match self {
+            Self::REJECT => Err(TxRejectReason::TOO_PRECISE),
+            Self::ROUND => {
+                Ok(amount
+                    .round_dp_with_strategy(AMOUNT_SCALE, RoundingStrategy::MidpointNearestEven))
+            }
+            Self::TRUNCATE => {
+                Ok(amount.round_dp_with_strategy(AMOUNT_SCALE, RoundingStrategy::ToZero))
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum InputFormat {
+    CSV,
+    // one json object per line with the type, client, tx + amount fields
+    NDJSON,
+}
+
+impl InputFormat {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "csv" => Some(Self::CSV),
+            "ndjson" | "jsonl" => Some(Self::NDJSON),
+            _ => None,
+        }
+    }
+
+    // csv unless the extension, under any compression extension, says otherwise
+    pub fn from_path(path: &str) -> Self {
+        let (_, path) = Compression::from_path(path);
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        Self::from_string(extension).unwrap_or(Self::CSV)
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            Self::CSV => ".csv",
+            Self::NDJSON => ".ndjson",
+        }
+    }
+
+    // ndjson if the first byte that isn't blank opens a json object, csv otherwise
+    pub fn sniff(reader: impl Read) -> Result<Self, io::Error> {
+        for byte in BufReader::new(reader).bytes() {
+            let byte = byte?;
+            if !byte.is_ascii_whitespace() {
+                if byte == b'{' {
+                    return Ok(Self::NDJSON);
+                }
+                return Ok(Self::CSV);
+            }
+        }
+        Ok(Self::CSV)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Compression {
+    NONE,
+    GZIP,
+    ZSTD,
+}
+
+impl Compression {
+    // the compression of a file + its path without the compression extension
+    pub fn from_path(path: &str) -> (Self, &str) {
+        if let Some(stripped) = path.strip_suffix(".gz") {
+            return (Self::GZIP, stripped);
+        }
+        if let Some(stripped) = path
+            .strip_suffix(".zst")
+            .or_else(|| path.strip_suffix(".zstd"))
+        {
+            return (Self::ZSTD, stripped);
+        }
+        (Self::NONE, path)
+    }
+
+    // by the magic bytes the content starts with
+    pub fn sniff(mut reader: impl Read) -> Result<Self, io::Error> {
+        let mut magic = Vec::new();
+        reader.by_ref().take(4).read_to_end(&mut magic)?;
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            return Ok(Self::GZIP);
+        }
+        if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Ok(Self::ZSTD);
+        }
+        Ok(Self::NONE)
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            Self::NONE => "",
+            Self::GZIP => ".gz",
+            Self::ZSTD => ".zst",
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+enum TxSource {
+    CSV(Reader<Box<dyn Read>>),
+    // the position is the one of the next line
+    NDJSON(BufReader<Box<dyn Read>>, Position),
+}
+
+// a malformed row set aside in lenient mode
+#[derive(Debug, Clone, Serialize)]
+pub struct TxQuarantine {
+    pub file: String,
+    pub line: u64,
+    pub byte: u64,
+    pub record: String,
+    pub error: String,
+}
+
+// strict mode stops on the first malformed row, lenient mode quarantines it + reads on.
+// ndjson lines are turned into the same fields as a csv row, so both go through the same validation.
 pub struct TxReader {
-    reader: Reader<File>,
+    csv_path: String,
+    mode: ParseMode,
+    precision: PrecisionPolicy,
+    source: TxSource,
+    // the raw ndjson line of the current record + why it couldn't be decoded
+    line_string: String,
+    decode_error: Option<String>,
+    // the columns of a csv file come from its header, if it has one
+    column_map: TxColumnMap,
+    columns: TxColumns,
+    header_read: bool,
+    record: ByteRecord,
     tx_record_type: TxRecordType,
-    tx_record_client: u16,
-    tx_record_tx: u32,
+    tx_record_client: ClientId,
+    tx_record_tx: TxId,
     tx_record_amount: Decimal,
+    tx_record_to: Option<ClientId>,
+    // a well formed row that can't be applied, reported as a rejection
+    tx_record_rejected: Option<TxRejectReason>,
     byte_record: ByteRecord,
-    error: Option<String>,
+    error: Option<AppError>,
+    quarantined: Vec<TxQuarantine>,
 }
 
 impl TxReader {
+    // the format is taken from the extension
     pub fn new(csv_path: &str) -> Result<Self, AppError> {
-        let reader = Self::new_reader(csv_path)?;
-        Ok(Self {
-            reader,
+        Self::new_with_format(csv_path, InputFormat::from_path(csv_path))
+    }
+
+    pub fn new_with_format(csv_path: &str, format: InputFormat) -> Result<Self, AppError> {
+        Ok(Self::from_reader(csv_path, Self::open(csv_path)?, format))
+    }
+
+    // read from any source, the name stands for it in errors + quarantined rows
+    pub fn from_reader(name: &str, reader: Box<dyn Read>, format: InputFormat) -> Self {
+        let source = match format {
+            InputFormat::CSV => TxSource::CSV(Self::csv_reader(reader)),
+            InputFormat::NDJSON => TxSource::NDJSON(BufReader::new(reader), Position::new()),
+        };
+        Self {
+            csv_path: name.to_string(),
+            mode: ParseMode::STRICT,
+            precision: PrecisionPolicy::ROUND,
+            source,
+            line_string: String::new(),
+            decode_error: None,
+            column_map: TxColumnMap::new(),
+            columns: TxColumns::new(),
+            header_read: false,
+            record: ByteRecord::new(),
             tx_record_type: TxRecordType::NONE,
             tx_record_client: 0,
             tx_record_tx: 0,
             tx_record_amount: Decimal::new(0, 0),
+            tx_record_to: None,
+            tx_record_rejected: None,
             byte_record: ByteRecord::new(),
             error: None,
-        })
+            quarantined: Vec::new(),
+        }
+    }
+
+    pub fn new_reader(csv_path: &str) -> Result<Reader<Box<dyn Read>>, AppError> {
+        Ok(Self::csv_reader(Self::open(csv_path)?))
     }
 
-    pub fn new_reader(csv_path: &str) -> Result<Reader<File>, AppError> {
-        let f = fs::File::open(&csv_path).map_err(|e| {
-            AppError::new(
-                PATH,
-                "csv_reader",
-                &["00", csv_path].join("| "),
-                &e.to_string(),
-            )
-        })?;
-        // println!("size of file: {}", f.metadata().unwrap().len());
-        Ok(csv::ReaderBuilder::new()
+    // gzip + zstd files are decompressed on the fly, by extension
+    pub fn open(path: &str) -> Result<Box<dyn Read>, AppError> {
+        let f = fs::File::open(path)
+            .map_err(|e| AppError::io(PATH, "open", &["00", path].join(" | "), e))?;
+        let reader: Box<dyn Read> = match Compression::from_path(path).0 {
+            Compression::NONE => Box::new(f),
+            Compression::GZIP => Box::new(MultiGzDecoder::new(f)),
+            Compression::ZSTD => Box::new(
+                zstd::Decoder::new(f)
+                    .map_err(|e| AppError::io(PATH, "open", &["01", path].join(" | "), e))?,
+            ),
+        };
+        Ok(reader)
+    }
+
+    fn csv_reader(reader: Box<dyn Read>) -> Reader<Box<dyn Read>> {
+        csv::ReaderBuilder::new()
             .has_headers(false)
             .flexible(true)
             .trim(Trim::All)
-            .from_reader(f))
-    }
-
-    pub fn byte_record(&self) -> &ByteRecord {
-        &self.byte_record
+            .from_reader(reader)
     }
 
     pub fn tx_record_type(&self) -> &TxRecordType {
         &self.tx_record_type
     }
 
-    pub fn tx_record_client(&self) -> &u16 {
+    pub fn tx_record_client(&self) -> &ClientId {
         &self.tx_record_client
     }
 
-    pub fn tx_record_tx(&self) -> &u32 {
+    pub fn tx_record_tx(&self) -> &TxId {
         &self.tx_record_tx
     }
 
@@ -71,72 +290,343 @@ impl TxReader {
         &self.tx_record_amount
     }
 
-    pub fn error(&self) -> &Option<String> {
-        &self.error
+    // the recipient of a transfer, none for every other type
+    pub fn tx_record_to(&self) -> &Option<ClientId> {
+        &self.tx_record_to
+    }
+
+    // why the current record, well formed, is not applied. none if it is
+    pub fn tx_record_rejected(&self) -> &Option<TxRejectReason> {
+        &self.tx_record_rejected
+    }
+
+    // line of the current record in the file
+    pub fn line(&self) -> u64 {
+        self.byte_record.position().map(|p| p.line()).unwrap_or(0)
+    }
+
+    pub fn take_error(&mut self) -> Option<AppError> {
+        self.error.take()
+    }
+
+    pub fn quarantined(&self) -> &Vec<TxQuarantine> {
+        &self.quarantined
+    }
+
+    pub fn quarantine_path(summary_dir: &str) -> String {
+        [summary_dir, "_quarantine.csv"].join("")
+    }
+
+    pub fn write_quarantine(summary_dir: &str, rows: &[TxQuarantine]) -> Result<(), AppError> {
+        let mut writer = csv::Writer::from_path(Self::quarantine_path(summary_dir))
+            .map_err(|e| AppError::io(PATH, "write_quarantine", "00", e))?;
+        if rows.is_empty() {
+            writer
+                .write_record(["file", "line", "byte", "record", "error"])
+                .map_err(|e| AppError::io(PATH, "write_quarantine", "01", e))?;
+        }
+        for row in rows {
+            writer
+                .serialize(row)
+                .map_err(|e| AppError::io(PATH, "write_quarantine", "01", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| AppError::io(PATH, "write_quarantine", "02", e))
+    }
+
+    pub fn set_mode(&mut self, mode: ParseMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_precision(&mut self, precision: PrecisionPolicy) {
+        self.precision = precision;
+    }
+
+    pub fn set_column_map(&mut self, column_map: &TxColumnMap) {
+        self.column_map = column_map.clone();
     }
 
     pub fn next_record(&mut self) -> bool {
-        let result = self.reader.read_byte_record(&mut self.byte_record);
-        if result.is_err() {
-            let e = result.err().unwrap();
-            self.error = Some(e.to_string());
-            return false;
+        loop {
+            match self.read_record() {
+                Ok(true) => {}
+                Ok(false) => return false,
+                Err(err) => {
+                    // the reader can't go on after an io error
+                    self.error = Some(err);
+                    return false;
+                }
+            }
+
+            // a header that can't be resolved leaves no way to read the rows, whatever the mode
+            if !self.header_read {
+                self.header_read = true;
+                match self.read_header() {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => {
+                        let tag = ["02", &self.csv_path].join(" | ");
+                        let message = [self.position(), e].join(" | ");
+                        self.error = Some(AppError::parse(PATH, "next_record", &tag, message));
+                        return false;
+                    }
+                }
+            }
+
+            let result = match self.decode_error.take() {
+                Some(e) => Err(e),
+                None => self.parse_record(),
+            };
+            match result {
+                Ok(true) => return true,
+                Ok(false) => continue,
+                Err(e) => {
+                    if self.mode == ParseMode::STRICT {
+                        let tag = ["01", &self.csv_path].join(" | ");
+                        let message = [self.position(), e].join(" | ");
+                        self.error = Some(AppError::parse(PATH, "next_record", &tag, message));
+                        return false;
+                    }
+
+                    // set the row aside + carry on with the next one
+                    let position = self.byte_record.position();
+                    let record = match self.source {
+                        TxSource::CSV(_) => {
+                            let fields: Vec<String> = self
+                                .byte_record
+                                .iter()
+                                .map(|f| String::from_utf8_lossy(f).to_string())
+                                .collect();
+                            fields.join(",")
+                        }
+                        TxSource::NDJSON(..) => self.line_string.trim().to_string(),
+                    };
+                    self.quarantined.push(TxQuarantine {
+                        file: self.csv_path.clone(),
+                        line: position.map(|p| p.line()).unwrap_or(0),
+                        byte: position.map(|p| p.byte()).unwrap_or(0),
+                        record,
+                        error: e,
+                    });
+                }
+            }
         }
+    }
 
-        if self.byte_record.len() < MIN_CSV_ROW_LEN {
-            return false;
+    // read the next record into the byte record, false at the end of the input
+    fn read_record(&mut self) -> Result<bool, AppError> {
+        let result = match &mut self.source {
+            TxSource::CSV(reader) => reader
+                .read_byte_record(&mut self.byte_record)
+                .map_err(|e| e.to_string()),
+            TxSource::NDJSON(reader, position) => loop {
+                self.line_string.clear();
+                let result = reader.read_line(&mut self.line_string);
+                if let Err(e) = result {
+                    break Err(e.to_string());
+                }
+
+                let len = result.unwrap() as u64;
+                if len == 0 {
+                    break Ok(false);
+                }
+
+                let mut record_position = Position::new();
+                record_position
+                    .set_line(position.line())
+                    .set_byte(position.byte());
+                let (next_line, next_byte) = (position.line() + 1, position.byte() + len);
+                position.set_line(next_line).set_byte(next_byte);
+
+                // blank lines are skipped
+                let line = self.line_string.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                self.byte_record.clear();
+                self.decode_error = None;
+                match Self::decode_json(line, &self.column_map) {
+                    Ok(fields) => {
+                        for field in fields {
+                            self.byte_record.push_field(field.as_bytes());
+                        }
+                    }
+                    Err(e) => self.decode_error = Some(e),
+                }
+                self.byte_record.set_position(Some(record_position));
+                break Ok(true);
+            },
+        };
+
+        result.map_err(|e| {
+            let tag = ["00", &self.csv_path, &self.position()].join(" | ");
+            AppError::io(PATH, "read_record", &tag, e)
+        })
+    }
+
+    // the fields of a json object in csv order, the amount + to are optional like in csv.
+    // keys are looked up in the column map, other keys are ignored.
+    fn decode_json(line: &str, column_map: &TxColumnMap) -> Result<Vec<String>, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(line).map_err(|e| ["invalid json", &e.to_string()].join(" | "))?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| "invalid json | not an object".to_string())?;
+
+        let mut values = [None; MAX_CSV_ROW_LEN];
+        for (key, value) in object {
+            if let Some(pos) = column_map.field(key) {
+                values[pos] = Some(value);
+            }
+        }
+
+        let mut fields = Vec::new();
+        for value in values {
+            let field = match value {
+                Some(serde_json::Value::String(s)) => s.trim().to_string(),
+                Some(serde_json::Value::Null) | None => break,
+                Some(v) => v.to_string(),
+            };
+            fields.push(field);
+        }
+        Ok(fields)
+    }
+
+    // the first row of a csv file sets the columns if it is a header. true for a header row.
+    fn read_header(&mut self) -> Result<bool, String> {
+        if let TxSource::NDJSON(..) = self.source {
+            return Ok(false);
+        }
+
+        if !self.column_map.is_header(&self.byte_record, &self.columns) {
+            return Ok(false);
+        }
+        self.columns = self.column_map.resolve(&self.byte_record)?;
+        Ok(true)
+    }
+
+    // Ok(false) for a header row, Err with the reason for a malformed row
+    fn parse_record(&mut self) -> Result<bool, String> {
+        self.columns.remap(&self.byte_record, &mut self.record);
+        if self.record.len() < MIN_CSV_ROW_LEN {
+            return Err("missing transaction record fields".to_string());
         }
 
         // validate
-        let mut tx_record_type = TxRecordType::from_binary(&self.byte_record[TYPE_POS]);
+        let tx_record_type = TxRecordType::from_binary(&self.record[TYPE_POS]);
         if tx_record_type == TxRecordType::NONE {
-            if TxRecordType::header_type(&self.byte_record) {
-                // skip header + read the next row
-                let result = self.reader.read_byte_record(&mut self.byte_record);
-                if result.is_err() {
-                    let e = result.err().unwrap();
-                    self.error = Some(format!("{:?} | {}", self.byte_record, &e.to_string()));
-                    return false;
-                }
-                // reset tx_record_type value
-                tx_record_type = TxRecordType::from_binary(&self.byte_record[TYPE_POS]);
-            } else {
-                self.error = Some(format!(
-                    "{:?} --> invalid transaction record type",
-                    self.byte_record
-                ));
-                return false;
+            // headers repeated further down, e.g. in concatenated files
+            if self.column_map.is_header(&self.byte_record, &self.columns) {
+                return Ok(false);
             }
+            return Err("invalid transaction record type".to_string());
+        }
+
+        // the credit side of a transfer is written by the transfer itself
+        if tx_record_type == TxRecordType::TRANSFER_IN {
+            return Err("invalid transaction record type".to_string());
         }
 
-        if tx_record_type.conflict_type() {
-            let result = self.byte_record.deserialize::<TxRecordSmall>(None);
-            if result.is_err() {
-                self.error = Some("invalid transaction record".to_string());
-                return false;
+        self.tx_record_to = None;
+        self.tx_record_rejected = None;
+
+        // funds types carry an amount, disputes, resolves + chargebacks may carry the part of the tx they are about
+        if !tx_record_type.funds_type() {
+            let tx_record = self
+                .record
+                .deserialize::<TxRecordSmall>(None)
+                .map_err(|e| ["invalid transaction record", &e.to_string()].join(" | "))?;
+
+            let mut amount = Decimal::new(0, 0);
+            let field = self
+                .record
+                .get(AMOUNT_POS)
+                .and_then(|f| str::from_utf8(f).ok())
+                .filter(|f| !f.is_empty());
+            if let (true, Some(field)) = (tx_record_type.conflict_type(), field) {
+                let parsed = field
+                    .parse::<Decimal>()
+                    .map_err(|e| ["invalid transaction record", &e.to_string()].join(" | "))?;
+                amount = Self::amount(self.precision, parsed, &mut self.tx_record_rejected);
             }
-            let tx_record = result.unwrap();
+
             self.tx_record_type = tx_record_type;
-            self.tx_record_client = tx_record.client_id.clone();
-            self.tx_record_tx = tx_record.tx_id.clone();
-            self.tx_record_amount = Decimal::new(0, 0);
-            return true;
+            self.tx_record_client = tx_record.client_id;
+            self.tx_record_tx = tx_record.tx_id;
+            self.tx_record_amount = amount;
+            return Ok(true);
         }
 
-        let result = self.byte_record.deserialize::<TxRecord>(None);
-        if result.is_err() {
-            println!("{:?}, {:?}", self.byte_record(), tx_record_type);
-            let e = result.err().unwrap();
-            self.error = Some(e.to_string());
-            return false;
+        let tx_record = self
+            .record
+            .deserialize::<TxRecord>(None)
+            .map_err(|e| ["invalid transaction record", &e.to_string()].join(" | "))?;
+
+        let amount = Self::amount(
+            self.precision,
+            tx_record.amount,
+            &mut self.tx_record_rejected,
+        );
+        // fees + interest only come from the schedule of a run
+        if tx_record_type.scheduled_type() {
+            self.tx_record_rejected = Some(TxRejectReason::SCHEDULE_ONLY);
         }
 
-        let tx_record = result.unwrap();
-        self.tx_record_type = TxRecordType::from_binary(tx_record.type_id);
+        // a transfer goes to another client
+        if tx_record_type == TxRecordType::TRANSFER {
+            let to = self
+                .record
+                .get(TO_POS)
+                .and_then(|f| str::from_utf8(f).ok())
+                .filter(|f| !f.is_empty())
+                .ok_or_else(|| "missing transfer recipient".to_string())?;
+            let to = to
+                .parse::<ClientId>()
+                .map_err(|e| ["invalid transfer recipient", &e.to_string()].join(" | "))?;
+            if to == tx_record.client_id {
+                return Err("transfer to the same client".to_string());
+            }
+            self.tx_record_to = Some(to);
+        }
+
+        self.tx_record_type = tx_record_type;
         self.tx_record_client = tx_record.client_id;
         self.tx_record_tx = tx_record.tx_id;
-        self.tx_record_amount = tx_record.amount;
+        self.tx_record_amount = amount;
+        Ok(true)
+    }
+
+    // amounts are positive + kept at AMOUNT_SCALE decimal places. any other is rejected, not applied
+    fn amount(
+        precision: PrecisionPolicy,
+        amount: Decimal,
+        rejected: &mut Option<TxRejectReason>,
+    ) -> Decimal {
+        match precision.apply(amount) {
+            Ok(amount) => {
+                if amount <= Decimal::new(0, 0) {
+                    *rejected = Some(TxRejectReason::NON_POSITIVE_AMOUNT);
+                }
+                amount
+            }
+            Err(reason) => {
+                *rejected = Some(reason);
+                amount
+            }
+        }
+    }
 
-        true
+    fn position(&self) -> String {
+        let position = match &self.source {
+            TxSource::CSV(reader) => reader.position(),
+            TxSource::NDJSON(_, position) => position,
+        };
+        let (line, byte) = match self.byte_record.position() {
+            Some(p) => (p.line(), p.byte()),
+            None => (position.line(), position.byte()),
+        };
+        format!("line {} | byte {}", line, byte)
     }
 }
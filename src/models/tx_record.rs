@@ -1,16 +1,29 @@
-use csv::ByteRecord;
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::str;
 
-use crate::lib::constants::{AMOUNT_POS, CLIENT_POS, MAX_CSV_ROW_LEN, TX_POS, TYPE_POS};
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_record";
+
+// client + tx ids. both are written as decimal text on disk, so widening them keeps existing stores readable.
+pub type ClientId = u32;
+pub type TxId = u64;
 
 const B_DEPOSIT: &[u8] = b"deposit";
 const B_WITHDRAW: &[u8] = b"withdrawal";
 const B_DISPUTE: &[u8] = b"dispute";
 const B_RESOLVE: &[u8] = b"resolve";
 const B_CHARGEBACK: &[u8] = b"chargeback";
+const B_UNLOCK: &[u8] = b"unlock";
+const B_FREEZE: &[u8] = b"freeze";
+const B_CLOSE: &[u8] = b"close";
+const B_TRANSFER: &[u8] = b"transfer";
+const B_TRANSFER_IN: &[u8] = b"transfer_in";
+const B_FEE: &[u8] = b"fee";
+const B_INTEREST: &[u8] = b"interest";
 
+#[allow(non_camel_case_types)]
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum TxRecordType {
     DEPOSIT = 0,
@@ -18,6 +31,14 @@ pub enum TxRecordType {
     DISPUTE,
     RESOLVE,
     CHARGEBACK,
+    UNLOCK,
+    FREEZE,
+    CLOSE,
+    TRANSFER,
+    // the credit side of a transfer, only found in the history of the recipient
+    TRANSFER_IN,
+    FEE,
+    INTEREST,
     NONE,
 }
 
@@ -29,6 +50,13 @@ impl TxRecordType {
             B_DISPUTE => Self::DISPUTE,
             B_RESOLVE => Self::RESOLVE,
             B_CHARGEBACK => Self::CHARGEBACK,
+            B_UNLOCK => Self::UNLOCK,
+            B_FREEZE => Self::FREEZE,
+            B_CLOSE => Self::CLOSE,
+            B_TRANSFER => Self::TRANSFER,
+            B_TRANSFER_IN => Self::TRANSFER_IN,
+            B_FEE => Self::FEE,
+            B_INTEREST => Self::INTEREST,
             _ => Self::NONE,
         };
 
@@ -44,6 +72,13 @@ impl TxRecordType {
                 "dispute" => Self::DISPUTE,
                 "resolve" => Self::RESOLVE,
                 "chargeback" => Self::CHARGEBACK,
+                "unlock" => Self::UNLOCK,
+                "freeze" => Self::FREEZE,
+                "close" => Self::CLOSE,
+                "transfer" => Self::TRANSFER,
+                "transfer_in" => Self::TRANSFER_IN,
+                "fee" => Self::FEE,
+                "interest" => Self::INTEREST,
                 _ => Self::NONE,
             };
         }
@@ -52,31 +87,7 @@ impl TxRecordType {
         Self::NONE
     }
 
-    pub fn header_type(record: &ByteRecord) -> bool {
-        if record.len() != MAX_CSV_ROW_LEN {
-            return false;
-        }
-
-        if &record[TYPE_POS] == b"type"
-            || &record[CLIENT_POS] == b"client"
-            || &record[TX_POS] == b"tx"
-            || &record[AMOUNT_POS] == b"amount"
-        {
-            return true;
-        }
-
-        for i in TYPE_POS..AMOUNT_POS {
-            if let Ok(string) = str::from_utf8(&record[i]) {
-                let s = string.to_lowercase().replace(" ", "");
-                if s == "type" || s == "client" || s == "tx" || s == "amount" {
-                    return true;
-                }
-            }
-        }
-
-        return false;
-    }
-
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             Self::DEPOSIT => "deposit".to_string(),
@@ -84,6 +95,13 @@ impl TxRecordType {
             Self::DISPUTE => "dispute".to_string(),
             Self::RESOLVE => "resolve".to_string(),
             Self::CHARGEBACK => "chargeback".to_string(),
+            Self::UNLOCK => "unlock".to_string(),
+            Self::FREEZE => "freeze".to_string(),
+            Self::CLOSE => "close".to_string(),
+            Self::TRANSFER => "transfer".to_string(),
+            Self::TRANSFER_IN => "transfer_in".to_string(),
+            Self::FEE => "fee".to_string(),
+            Self::INTEREST => "interest".to_string(),
             _ => "none".to_string(),
         }
     }
@@ -91,6 +109,36 @@ impl TxRecordType {
     pub fn conflict_type(&self) -> bool {
         *self == Self::DISPUTE || *self == Self::RESOLVE || *self == Self::CHARGEBACK
     }
+
+    // types that move funds under their own tx id
+    pub fn funds_type(&self) -> bool {
+        *self == Self::DEPOSIT
+            || *self == Self::WITHDRAW
+            || *self == Self::TRANSFER
+            || *self == Self::TRANSFER_IN
+            || *self == Self::FEE
+            || *self == Self::INTEREST
+    }
+
+    // paid to the client on its own: a dispute holds the amount, a chargeback takes it back
+    pub fn credit_type(&self) -> bool {
+        *self == Self::DEPOSIT || *self == Self::INTEREST
+    }
+
+    // taken from the client on its own: a chargeback refunds the amount
+    pub fn debit_type(&self) -> bool {
+        *self == Self::WITHDRAW || *self == Self::FEE
+    }
+
+    // types only a schedule makes, never read from an input
+    pub fn scheduled_type(&self) -> bool {
+        *self == Self::FEE || *self == Self::INTEREST
+    }
+
+    // types that change the state of an account, they carry no amount
+    pub fn admin_type(&self) -> bool {
+        *self == Self::UNLOCK || *self == Self::FREEZE || *self == Self::CLOSE
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
@@ -98,9 +146,9 @@ pub struct TxRecord<'a> {
     #[serde(rename(deserialize = "type", serialize = "type"))]
     pub type_id: &'a [u8],
     #[serde(rename(deserialize = "client", serialize = "client"))]
-    pub client_id: u16,
+    pub client_id: ClientId,
     #[serde(rename(deserialize = "tx", serialize = "tx"))]
-    pub tx_id: u32,
+    pub tx_id: TxId,
     #[serde(
         rename(deserialize = "amount", serialize = "amount"),
         with = "rust_decimal::serde::str"
@@ -113,98 +161,166 @@ pub struct TxRecordSmall<'a> {
     #[serde(rename(deserialize = "type", serialize = "type"))]
     pub type_id: &'a [u8],
     #[serde(rename(deserialize = "client", serialize = "client"))]
-    pub client_id: u16,
+    pub client_id: ClientId,
     #[serde(rename(deserialize = "tx", serialize = "tx"))]
-    pub tx_id: u32,
+    pub tx_id: TxId,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct TxRow {
     pub type_id: TxRecordType,
-    pub client_id: u16,
-    pub tx_id: u32,
+    pub client_id: ClientId,
+    pub tx_id: TxId,
     pub amount: Decimal,
+    // the client a transfer credits
+    pub to_client_id: Option<ClientId>,
+    // position of the input file in the run + line of the row in it, set by the reader
+    pub(crate) source_id: usize,
+    pub(crate) line: u64,
 }
 
 impl TxRow {
-    pub fn new(type_id: TxRecordType, client_id: u16, tx_id: u32, amount: Decimal) -> Self {
+    pub fn new(type_id: TxRecordType, client_id: ClientId, tx_id: TxId, amount: Decimal) -> Self {
         Self {
             type_id,
             client_id,
             tx_id,
             amount,
+            to_client_id: None,
+            source_id: 0,
+            line: 0,
         }
     }
 
-    pub fn new_from_string(string: &str) -> Self {
-        let a: Vec<&str> = string.split(",").collect();
+    // a value that can't be read is reported, a corrupt history is not applied to
+    pub fn new_from_string(string: &str) -> Result<Self, AppError> {
+        let invalid = || {
+            AppError::storage(
+                PATH,
+                "new_from_string",
+                "01",
+                ["invalid tx", string].join(" | "),
+            )
+        };
+
+        let a: Vec<&str> = string.split(',').collect();
+        if a.len() != 4 {
+            return Err(invalid());
+        }
         let type_id = TxRecordType::from_binary(a[0].as_bytes());
-        let client_id = a[1].parse::<u16>().unwrap();
-        let tx_id = a[2].parse::<u32>().unwrap();
-        let amount = Decimal::from_str(a[3]).unwrap();
-        Self {
+        let client_id = a[1].parse::<ClientId>().map_err(|_| invalid())?;
+        let tx_id = a[2].parse::<TxId>().map_err(|_| invalid())?;
+        let amount = Decimal::from_str(a[3]).map_err(|_| invalid())?;
+        Ok(Self {
             type_id,
             client_id,
             tx_id,
             amount,
-        }
+            to_client_id: None,
+            source_id: 0,
+            line: 0,
+        })
     }
 
     pub fn to_string(
         tx_type: &TxRecordType,
-        client_id: &u16,
-        tx_id: &u32,
+        client_id: &ClientId,
+        tx_id: &TxId,
         amount: &Decimal,
     ) -> String {
         format!(
-            "{},{},{},{}",
+            "{},{},{},{:.4}",
             tx_type.to_string(),
-            client_id.to_string(),
-            tx_id.to_string(),
-            format!("{:.4}", amount),
+            client_id,
+            tx_id,
+            amount,
         )
     }
 }
 
+// a part of the amount of a tx under a dispute, or resolved or charged back.
+// a tx can have several, they are kept together under its conflict key.
 #[derive(Debug, Clone, Copy)]
 pub struct TxConflict {
-    pub tx_id: u32,
+    pub tx_id: TxId,
     pub type_id: TxRecordType,
     pub state_id: TxRecordType,
     pub amount: Decimal,
 }
 
 impl TxConflict {
-    pub fn key(tx_id: &u32) -> String {
-        return ["c_", &tx_id.to_string()].join("");
+    pub fn key(tx_id: &TxId) -> String {
+        ["c_", &tx_id.to_string()].join("")
     }
 
-    pub fn new_from_string(string: &str) -> Self {
-        let a: Vec<&str> = string.split(",").collect();
-        let tx_id = a[0].replace("c_", "").parse::<u32>().unwrap();
+    // a value that can't be read is reported, a corrupt history is not applied to
+    pub fn new_from_string(string: &str) -> Result<Self, AppError> {
+        let invalid = || {
+            AppError::storage(
+                PATH,
+                "new_from_string",
+                "00",
+                ["invalid conflict", string].join(" | "),
+            )
+        };
+
+        let a: Vec<&str> = string.split(',').collect();
+        if a.len() != 4 {
+            return Err(invalid());
+        }
+        let tx_id = a[0]
+            .replace("c_", "")
+            .parse::<TxId>()
+            .map_err(|_| invalid())?;
         let type_id = TxRecordType::from_binary(a[1].as_bytes());
         let state_id = TxRecordType::from_binary(a[2].as_bytes());
-        let amount = Decimal::from_str(a[3]).unwrap();
-        Self {
+        let amount = Decimal::from_str(a[3]).map_err(|_| invalid())?;
+        Ok(Self {
             tx_id,
             type_id,
             state_id,
             amount,
-        }
+        })
     }
 
     pub fn to_string(
-        tx_id: &u32,
+        tx_id: &TxId,
         type_id: &TxRecordType,
         state_id: &TxRecordType,
         amount: &Decimal,
     ) -> String {
         format!(
             "c_{},{},{},{:.4}",
-            tx_id.to_string(),
+            tx_id,
             type_id.to_string(),
             state_id.to_string(),
             amount
         )
     }
+
+    // the parts of a tx are separated by ";", a value from before partial conflicts is a single part
+    pub fn list_from_string(string: &str) -> Result<Vec<Self>, AppError> {
+        string
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(Self::new_from_string)
+            .collect()
+    }
+
+    pub fn list_to_string(conflicts: &[Self]) -> String {
+        conflicts
+            .iter()
+            .map(|c| Self::to_string(&c.tx_id, &c.type_id, &c.state_id, &c.amount))
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+
+    // the amount of the parts still under dispute
+    pub fn disputed(conflicts: &[Self]) -> Decimal {
+        conflicts
+            .iter()
+            .filter(|c| c.state_id == TxRecordType::DISPUTE)
+            .map(|c| c.amount)
+            .sum()
+    }
 }
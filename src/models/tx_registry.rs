@@ -0,0 +1,172 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::tx_reader::TxReader;
+use crate::common::config::Config;
+use crate::common::constants::{REGISTRY_FILE, SUMMARY_REGISTRY_DIR};
+use crate::common::error::AppError;
+use crate::common::file;
+
+const PATH: &str = "model/tx_registry";
+
+// millis is when the entry was committed, 0 while it is staged or for entries from before it was kept
+#[derive(PartialEq, Debug, Clone)]
+pub struct TxRegistryEntry {
+    pub hash: String,
+    pub millis: i64,
+    pub name: String,
+}
+
+impl TxRegistryEntry {
+    // "hash,millis,name", or "hash,name"
+    pub fn new_from_string(string: &str) -> Option<Self> {
+        let v: Vec<&str> = string.splitn(3, ',').collect();
+        if v.len() < 2 || v[0].is_empty() {
+            return None;
+        }
+        let (millis, name) = match (v.len(), v[1].parse::<i64>()) {
+            (3, Ok(millis)) => (millis, v[2].to_string()),
+            _ => (0, v[1..].join(",")),
+        };
+        Some(Self {
+            hash: v[0].to_string(),
+            millis,
+            name,
+        })
+    }
+}
+
+impl fmt::Display for TxRegistryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.hash, self.millis, self.name)
+    }
+}
+
+// the input files that have been applied to the official data, keyed by content hash + file name.
+// the entries of a run are staged in its summary dir and only committed once its accounts are published.
+pub struct TxRegistry {
+    entries: Vec<TxRegistryEntry>,
+}
+
+impl TxRegistry {
+    pub fn new(config: &Config) -> Result<Self, AppError> {
+        Ok(Self {
+            entries: Self::read(&Self::path(config))?,
+        })
+    }
+
+    pub fn entry(source_csv_path: &str) -> Result<TxRegistryEntry, AppError> {
+        // the content is hashed decompressed, so it is the same however it was compressed
+        let mut f = TxReader::open(source_csv_path)?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut f, &mut hasher).map_err(|e| AppError::io(PATH, "entry", "01", e))?;
+
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let name = Path::new(source_csv_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(source_csv_path)
+            .to_string();
+        Ok(TxRegistryEntry {
+            hash,
+            millis: 0,
+            name,
+        })
+    }
+
+    // the same content was already applied, whatever the file was called
+    pub fn find_hash(&self, hash: &str) -> Option<&TxRegistryEntry> {
+        self.entries.iter().find(|e| e.hash == hash)
+    }
+
+    pub fn find_name(&self, name: &str) -> Option<&TxRegistryEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    pub fn stage(summary_dir: &str, entries: &[TxRegistryEntry]) -> Result<(), AppError> {
+        let dir = Self::staged_dir(summary_dir);
+        fs::create_dir_all(&dir).map_err(|e| AppError::io(PATH, "stage", "00", e))?;
+
+        let data: String = entries.iter().map(|e| e.to_string() + "\n").collect();
+        fs::write([&dir, "/", REGISTRY_FILE].join(""), data)
+            .map_err(|e| AppError::io(PATH, "stage", "01", e))
+    }
+
+    // append the entries staged by a run, once its accounts are all published, with the time of the commit.
+    // the entries are appended once, so recovery can run it again.
+    pub fn commit(summary_dir: &str, config: &Config) -> Result<(), AppError> {
+        let staged_path = [&Self::staged_dir(summary_dir), "/", REGISTRY_FILE].join("");
+        let entries = Self::read(&staged_path)?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(config.registry_dir())
+            .map_err(|e| AppError::io(PATH, "commit", "00", e))?;
+
+        let millis = Utc::now().timestamp_millis();
+        let data: String = entries
+            .into_iter()
+            .map(|e| TxRegistryEntry { millis, ..e }.to_string() + "\n")
+            .collect();
+        file::append_once(
+            &Self::path(config),
+            &[&staged_path, ".len"].join(""),
+            None,
+            &data,
+        )
+        .map_err(|e| AppError::io(PATH, "commit", "01", e))?;
+
+        fs::remove_file(&staged_path).map_err(|e| AppError::io(PATH, "commit", "02", e))?;
+        let _ = fs::remove_file([&staged_path, ".len"].join(""));
+        Ok(())
+    }
+
+    // drop the entries committed after a time, once a full restore undid their runs.
+    pub fn remove_after(millis: i64, config: &Config) -> Result<(), AppError> {
+        let registry_path = Self::path(config);
+        let entries = Self::read(&registry_path)?;
+        if entries.iter().all(|e| e.millis <= millis) {
+            return Ok(());
+        }
+
+        let data: String = entries
+            .iter()
+            .filter(|e| e.millis <= millis)
+            .map(|e| e.to_string() + "\n")
+            .collect();
+        let tmp_path = [&registry_path, ".tmp"].join("");
+        fs::write(&tmp_path, data).map_err(|e| AppError::io(PATH, "remove_after", "00", e))?;
+        fs::rename(&tmp_path, &registry_path)
+            .map_err(|e| AppError::io(PATH, "remove_after", "01", e))
+    }
+
+    fn read(file_path: &str) -> Result<Vec<TxRegistryEntry>, AppError> {
+        if !Path::new(file_path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let string =
+            fs::read_to_string(file_path).map_err(|e| AppError::io(PATH, "read", "00", e))?;
+        Ok(string
+            .lines()
+            .filter_map(TxRegistryEntry::new_from_string)
+            .collect())
+    }
+
+    fn path(config: &Config) -> String {
+        [&config.registry_dir(), "/", REGISTRY_FILE].join("")
+    }
+
+    fn staged_dir(summary_dir: &str) -> String {
+        [summary_dir, SUMMARY_REGISTRY_DIR].join("/")
+    }
+}
@@ -0,0 +1,196 @@
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::tx_record::{ClientId, TxId, TxRow};
+use crate::common::constants::SUMMARY_REJECTED_DIR;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_rejection";
+
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TxRejectReason {
+    ACCOUNT_LOCKED,
+    ACCOUNT_FROZEN,
+    ACCOUNT_CLOSED,
+    INVALID_TRANSITION,
+    NON_ZERO_BALANCE,
+    INSUFFICIENT_FUNDS,
+    RECIPIENT_REFUSED,
+    UNKNOWN_TX,
+    ALREADY_DISPUTED,
+    REDISPUTE_REFUSED,
+    NOT_DISPUTED,
+    EXCEEDS_UNDISPUTED,
+    EXCEEDS_DISPUTED,
+    DUPLICATE_TX,
+    NON_POSITIVE_AMOUNT,
+    TOO_PRECISE,
+    SCHEDULE_ONLY,
+    UNKNOWN_TYPE,
+}
+
+impl fmt::Display for TxRejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            Self::ACCOUNT_LOCKED => "account_locked",
+            Self::ACCOUNT_FROZEN => "account_frozen",
+            Self::ACCOUNT_CLOSED => "account_closed",
+            Self::INVALID_TRANSITION => "invalid_transition",
+            Self::NON_ZERO_BALANCE => "non_zero_balance",
+            Self::INSUFFICIENT_FUNDS => "insufficient_funds",
+            Self::RECIPIENT_REFUSED => "recipient_refused",
+            Self::UNKNOWN_TX => "unknown_tx",
+            Self::ALREADY_DISPUTED => "already_disputed",
+            Self::REDISPUTE_REFUSED => "redispute_refused",
+            Self::NOT_DISPUTED => "not_disputed",
+            Self::EXCEEDS_UNDISPUTED => "exceeds_undisputed",
+            Self::EXCEEDS_DISPUTED => "exceeds_disputed",
+            Self::DUPLICATE_TX => "duplicate_tx",
+            Self::NON_POSITIVE_AMOUNT => "non_positive_amount",
+            Self::TOO_PRECISE => "too_precise",
+            Self::SCHEDULE_ONLY => "schedule_only",
+            Self::UNKNOWN_TYPE => "unknown_type",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TxOutcome {
+    APPLIED,
+    REJECTED(TxRejectReason),
+}
+
+// a row that was read but not applied, as listed in the rejections output
+#[derive(Debug, Clone, Serialize)]
+pub struct TxRejection {
+    pub file: String,
+    pub line: u64,
+    #[serde(rename = "type")]
+    pub type_id: String,
+    pub client: ClientId,
+    pub tx: TxId,
+    pub amount: String,
+    pub reason: String,
+}
+
+// the rejected rows of a run are staged in its summary dir by whoever rejects them (the reader, the index, a worker or the transfers),
+// then merged in input order into a csv + json file next to the summary dir once the run is published.
+pub struct TxRejectionLog;
+
+impl TxRejectionLog {
+    // staged rows are "source,line,reason,type,client,tx,amount", source being the position of the input file
+    pub fn stage(
+        summary_dir: &str,
+        name: &str,
+        rows: &[(TxRow, TxRejectReason)],
+    ) -> Result<(), AppError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let dir = [summary_dir, SUMMARY_REJECTED_DIR].join("/");
+        fs::create_dir_all(&dir).map_err(|e| AppError::io(PATH, "stage", "00", e))?;
+
+        let data: String = rows
+            .iter()
+            .map(|(row, reason)| {
+                let string =
+                    TxRow::to_string(&row.type_id, &row.client_id, &row.tx_id, &row.amount);
+                format!("{},{},{},{}\n", row.source_id, row.line, reason, string)
+            })
+            .collect();
+        fs::write([&dir, "/", name, ".csv"].join(""), data)
+            .map_err(|e| AppError::io(PATH, "stage", "01", e))
+    }
+
+    pub fn collect(
+        summary_dir: &str,
+        source_csv_paths: &[String],
+    ) -> Result<Vec<TxRejection>, AppError> {
+        let mut keyed: Vec<(usize, TxRejection)> = Self::read(summary_dir)?
+            .into_iter()
+            .map(|(row, reason)| {
+                let rejection = TxRejection {
+                    file: source_csv_paths
+                        .get(row.source_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    line: row.line,
+                    type_id: row.type_id.to_string(),
+                    client: row.client_id,
+                    tx: row.tx_id,
+                    amount: format!("{:.4}", row.amount),
+                    reason,
+                };
+                (row.source_id, rejection)
+            })
+            .collect();
+
+        keyed.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.line.cmp(&b.1.line)));
+        Ok(keyed.into_iter().map(|(_, r)| r).collect())
+    }
+
+    // the rows staged so far, with their reason
+    pub fn read(summary_dir: &str) -> Result<Vec<(TxRow, String)>, AppError> {
+        let dir = [summary_dir, SUMMARY_REJECTED_DIR].join("/");
+        if !Path::new(&dir).exists() {
+            return Ok(Vec::new());
+        }
+
+        let p = fs::read_dir(&dir).map_err(|e| AppError::io(PATH, "read", "00", e))?;
+
+        let mut rows = Vec::new();
+        for e in p.flatten() {
+            let string =
+                fs::read_to_string(e.path()).map_err(|e| AppError::io(PATH, "read", "01", e))?;
+            for line in string.lines() {
+                let v: Vec<&str> = line.splitn(4, ',').collect();
+                if v.len() != 4 {
+                    continue;
+                }
+
+                let mut row = TxRow::new_from_string(v[3])?;
+                row.source_id = v[0].parse::<usize>().unwrap_or(0);
+                row.line = v[1].parse::<u64>().unwrap_or(0);
+                rows.push((row, v[2].to_string()));
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn csv_path(summary_dir: &str) -> String {
+        [summary_dir, "_rejected.csv"].join("")
+    }
+
+    pub fn json_path(summary_dir: &str) -> String {
+        [summary_dir, "_rejected.json"].join("")
+    }
+
+    pub fn write(summary_dir: &str, rejections: &[TxRejection]) -> Result<(), AppError> {
+        let mut writer = csv::Writer::from_path(Self::csv_path(summary_dir))
+            .map_err(|e| AppError::io(PATH, "write", "00", e))?;
+        if rejections.is_empty() {
+            writer
+                .write_record(["file", "line", "type", "client", "tx", "amount", "reason"])
+                .map_err(|e| AppError::io(PATH, "write", "01", e))?;
+        }
+        for rejection in rejections {
+            writer
+                .serialize(rejection)
+                .map_err(|e| AppError::io(PATH, "write", "01", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| AppError::io(PATH, "write", "02", e))?;
+
+        let json = serde_json::to_string_pretty(rejections)
+            .map_err(|e| AppError::io(PATH, "write", "03", e))?;
+        fs::write(Self::json_path(summary_dir), json)
+            .map_err(|e| AppError::io(PATH, "write", "04", e))
+    }
+}
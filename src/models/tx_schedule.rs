@@ -0,0 +1,289 @@
+use chrono::Utc;
+use rust_decimal::prelude::*;
+use rust_decimal::RoundingStrategy;
+use std::fmt;
+use std::fs;
+
+use super::account::Account;
+use super::tx_history::TxHistory;
+use super::tx_index::TxIndex;
+use super::tx_record::{ClientId, TxRecordType, TxRow};
+use super::tx_rejection::{TxOutcome, TxRejectionLog};
+use crate::common::config::Config;
+use crate::common::constants::AMOUNT_SCALE;
+use crate::common::error::AppError;
+
+const PATH: &str = "model/tx_schedule";
+const STAGE_NAME: &str = "schedule";
+
+// what the value of a rule is: an amount, or a rate of the available funds
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TxRuleBasis {
+    FLAT,
+    RATE,
+}
+
+impl TxRuleBasis {
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string.to_lowercase().as_str() {
+            "flat" => Some(Self::FLAT),
+            "rate" => Some(Self::RATE),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TxRuleBasis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            Self::FLAT => "flat",
+            Self::RATE => "rate",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+// a fee or interest row for every account with available funds from the min (included) to the max (excluded)
+#[derive(Debug, Clone)]
+pub struct TxRule {
+    pub type_id: TxRecordType,
+    pub basis: TxRuleBasis,
+    pub value: Decimal,
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+    // line of the rule in the schedule file
+    pub line: u64,
+}
+
+impl TxRule {
+    // the rule as written, the line aside. kept in the history of the clients it was applied to.
+    pub fn key(&self) -> String {
+        let bound = |b: Option<Decimal>| b.map(|b| b.normalize().to_string()).unwrap_or_default();
+        format!(
+            "s_{},{},{},{},{}",
+            self.type_id.to_string(),
+            self.basis,
+            self.value.normalize(),
+            bound(self.min),
+            bound(self.max)
+        )
+    }
+
+    // the amount of the rule for an account, none if it doesn't apply.
+    // amounts are rounded half to even at AMOUNT_SCALE decimal places.
+    pub fn amount(&self, account: &Account) -> Option<Decimal> {
+        if self.min.is_some_and(|min| account.available < min)
+            || self.max.is_some_and(|max| account.available >= max)
+        {
+            return None;
+        }
+
+        let amount = match self.basis {
+            TxRuleBasis::FLAT => self.value,
+            TxRuleBasis::RATE => account.available * self.value,
+        }
+        .round_dp_with_strategy(AMOUNT_SCALE, RoundingStrategy::MidpointNearestEven);
+        if amount <= Decimal::new(0, 0) {
+            return None;
+        }
+        Some(amount)
+    }
+}
+
+// the fees + interest of a run. once every input row is applied, the rules are applied to every account
+// in file order, as rows of their own that go through the account + history like any input row.
+// each row gets a generated tx id, and is listed in the rejections output under the schedule file + rule line.
+// a rule is applied to a client once a period, so a run of the same period again leaves it out.
+// once every rule was applied to every account in the period, a run only goes through the accounts it staged
+// + those the schedule has to go back to.
+#[derive(Debug, Clone)]
+pub struct TxSchedule {
+    path: String,
+    rules: Vec<TxRule>,
+    period: String,
+}
+
+impl TxSchedule {
+    // each line is "type,basis,value[,min[,max]]", e.g. "fee,flat,2.5,,1000" or "interest,rate,0.001,100".
+    // blank lines + # comments are skipped.
+    pub fn from_file(path: &str) -> Result<Self, AppError> {
+        let string =
+            fs::read_to_string(path).map_err(|e| AppError::io(PATH, "from_file", "00", e))?;
+
+        let mut rules = Vec::new();
+        for (i, line) in string.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let rule = Self::parse_rule(line, i as u64 + 1).map_err(|e| {
+                let tag = ["01", path, &(i + 1).to_string()].join(" | ");
+                AppError::parse(PATH, "from_file", &tag, e)
+            })?;
+            rules.push(rule);
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            rules,
+            period: Utc::now().format("%Y-%m").to_string(),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // the current month, e.g. "2022-03", by default
+    pub fn set_period(&mut self, period: &str) {
+        self.period = period.to_string();
+    }
+
+    // apply the rules to the staged or published accounts they may be due for. source_id is the position
+    // the schedule file is given in the sources of the rejections output.
+    pub fn apply(
+        &self,
+        source_id: usize,
+        summary_dir: &str,
+        tx_index: &TxIndex,
+        config: &Config,
+    ) -> Result<(), AppError> {
+        let due = tx_index.schedule_due()?;
+        let mut rejected = Vec::new();
+        for client_id in self.client_ids(&due, summary_dir, tx_index, config)? {
+            let mut account = Account::new(client_id, summary_dir, config);
+            // only opened for a client a rule applies to
+            let mut tx_history: Option<TxHistory> = None;
+            let mut applied = false;
+            let rejected_before = rejected.len();
+
+            for rule in &self.rules {
+                // an account that takes no such rows is left out, instead of getting a rejection every run
+                if account.state.refuses(&rule.type_id).is_some() {
+                    continue;
+                }
+                let amount = match rule.amount(&account) {
+                    Some(amount) => amount,
+                    None => continue,
+                };
+
+                if tx_history.is_none() {
+                    tx_history = Some(TxHistory::new(&client_id, summary_dir, config)?);
+                }
+                let history = tx_history.as_mut().unwrap();
+                let key = rule.key();
+                if history.get_period(&key)?.as_deref() == Some(self.period.as_str()) {
+                    continue;
+                }
+
+                let mut row = TxRow::new(
+                    rule.type_id,
+                    client_id,
+                    tx_index.generate(&client_id)?,
+                    amount,
+                );
+                row.source_id = source_id;
+                row.line = rule.line;
+
+                let outcome = account.handle_tx(&row.type_id, &row.tx_id, &row.amount, history)?;
+                if let TxOutcome::REJECTED(reason) = outcome {
+                    // only the ids of applied rows stay claimed
+                    tx_index.release(&[row.tx_id])?;
+                    rejected.push((row, reason));
+                    continue;
+                }
+                history.set_tx(&row.type_id, &row.client_id, &row.tx_id, &row.amount);
+                history.set_period(&key, &self.period);
+                applied = true;
+            }
+
+            // the account is only staged again if a row was applied
+            if let Some(mut history) = tx_history {
+                history.commit();
+            }
+            if applied {
+                account.write_to_csv(summary_dir)?;
+            }
+
+            // a rejected row is tried again by the next run
+            let due_again = rejected.len() > rejected_before;
+            if due_again || due.contains(&client_id) {
+                tx_index.set_schedule_due(&client_id, due_again)?;
+            }
+        }
+
+        for rule in &self.rules {
+            tx_index.set_schedule_period(&rule.key(), &self.period)?;
+        }
+        TxRejectionLog::stage(summary_dir, STAGE_NAME, &rejected)
+    }
+
+    // a run without the schedule leaves the accounts it staged for the next run with it
+    pub fn defer(summary_dir: &str, tx_index: &TxIndex) -> Result<(), AppError> {
+        for client_id in Account::staged_client_ids(summary_dir)? {
+            tx_index.set_schedule_due(&client_id, true)?;
+        }
+        Ok(())
+    }
+
+    // every account while a rule wasn't applied to all of them in the period,
+    // then only the accounts staged by the run + the clients the schedule has to go back to
+    fn client_ids(
+        &self,
+        due: &[ClientId],
+        summary_dir: &str,
+        tx_index: &TxIndex,
+        config: &Config,
+    ) -> Result<Vec<ClientId>, AppError> {
+        for rule in &self.rules {
+            if tx_index.schedule_period(&rule.key())?.as_deref() != Some(self.period.as_str()) {
+                return Account::client_ids(summary_dir, config);
+            }
+        }
+
+        let mut client_ids = due.to_vec();
+        client_ids.extend(Account::staged_client_ids(summary_dir)?);
+        client_ids.sort_unstable();
+        client_ids.dedup();
+        Ok(client_ids)
+    }
+
+    fn parse_rule(line: &str, line_number: u64) -> Result<TxRule, String> {
+        let v: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if v.len() < 3 || v.len() > 5 {
+            return Err("expected type,basis,value[,min[,max]]".to_string());
+        }
+
+        let type_id = TxRecordType::from_binary(v[0].as_bytes());
+        if !type_id.scheduled_type() {
+            return Err(["invalid rule type", v[0]].join(" | "));
+        }
+        let basis = TxRuleBasis::from_string(v[1])
+            .ok_or_else(|| ["invalid rule basis", v[1]].join(" | "))?;
+        let value = Decimal::from_str(v[2])
+            .map_err(|e| ["invalid rule value", v[2], &e.to_string()].join(" | "))?;
+        if value <= Decimal::new(0, 0) {
+            return Err("rule value must be positive".to_string());
+        }
+
+        let bound = |pos: usize| -> Result<Option<Decimal>, String> {
+            match v.get(pos) {
+                Some(f) if !f.is_empty() => Decimal::from_str(f)
+                    .map(Some)
+                    .map_err(|e| ["invalid rule bound", f, &e.to_string()].join(" | ")),
+                _ => Ok(None),
+            }
+        };
+
+        Ok(TxRule {
+            type_id,
+            basis,
+            value,
+            min: bound(3)?,
+            max: bound(4)?,
+            line: line_number,
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::Utc;
+
+use super::account::{Account, AccountState};
+use super::account_audit::AccountAuditLog;
+use super::tx_history::TxHistory;
+use super::tx_record::{ClientId, TxConflict, TxRecordType, TxRow};
+use super::tx_rejection::{TxOutcome, TxRejectReason, TxRejectionLog};
+use crate::common::config::Config;
+use crate::common::error::AppError;
+
+// transfers + the disputes on them touch two clients, so the balancer moves both to one worker,
+// which applies them here on top of what it staged for each.
+// the sender keeps the transfer in its history, the recipient a transfer_in under the same tx id.
+// only the sender can dispute a transfer: the amount is held from the recipient until it is resolved,
+// a chargeback takes it from the recipient + refunds the sender, who is locked like for any chargeback.
+// a locked or closed recipient refuses transfers + the disputes, resolves and chargebacks on them.
+pub struct TxTransfer {
+    summary_dir: String,
+    name: String,
+    config: Config,
+    rejected: Vec<(TxRow, TxRejectReason)>,
+    // time, row, from + to of every change of account state
+    state_changes: Vec<(i64, TxRow, AccountState, AccountState)>,
+}
+
+impl TxTransfer {
+    pub fn new(summary_dir: &str, name: &str, config: &Config) -> Self {
+        Self {
+            summary_dir: summary_dir.to_string(),
+            name: name.to_string(),
+            config: config.clone(),
+            rejected: Vec::new(),
+            state_changes: Vec::new(),
+        }
+    }
+
+    // a transfer row, or a dispute, resolve or chargeback on a transfer from the sender to the recipient
+    pub fn apply(&mut self, row: &TxRow, from: ClientId, to: ClientId) -> Result<(), AppError> {
+        let mut sender = Account::new(from, &self.summary_dir, &self.config);
+        let mut recipient = Account::new(to, &self.summary_dir, &self.config);
+        let mut sender_history = TxHistory::new(&from, &self.summary_dir, &self.config)?;
+        let mut recipient_history = TxHistory::new(&to, &self.summary_dir, &self.config)?;
+
+        let state = sender.state;
+        let outcome = if row.type_id == TxRecordType::TRANSFER {
+            Self::transfer(
+                row,
+                &mut sender,
+                &mut recipient,
+                &mut sender_history,
+                &mut recipient_history,
+            )?
+        } else if row.client_id != from {
+            TxOutcome::REJECTED(TxRejectReason::UNKNOWN_TX)
+        } else {
+            Self::conflict(
+                row,
+                &mut sender,
+                &mut recipient,
+                &mut sender_history,
+                &mut recipient_history,
+            )?
+        };
+
+        // nothing is written for a rejected row
+        if let TxOutcome::REJECTED(reason) = outcome {
+            self.rejected.push((*row, reason));
+            return Ok(());
+        }
+        if sender.state != state {
+            self.state_changes
+                .push((Utc::now().timestamp_millis(), *row, state, sender.state));
+        }
+
+        sender_history.commit();
+        recipient_history.commit();
+        sender.write_to_csv(&self.summary_dir)?;
+        recipient.write_to_csv(&self.summary_dir)
+    }
+
+    // stage the rejections + state changes, once every row is read
+    pub fn stage(&self) -> Result<(), AppError> {
+        TxRejectionLog::stage(&self.summary_dir, &self.name, &self.rejected)?;
+        AccountAuditLog::stage(&self.summary_dir, &self.name, &self.state_changes)
+    }
+
+    // a withdrawal from the sender + a deposit to the recipient, or neither
+    fn transfer(
+        row: &TxRow,
+        sender: &mut Account,
+        recipient: &mut Account,
+        sender_history: &mut TxHistory,
+        recipient_history: &mut TxHistory,
+    ) -> Result<TxOutcome, AppError> {
+        let outcome = sender.handle_tx(
+            &TxRecordType::WITHDRAW,
+            &row.tx_id,
+            &row.amount,
+            sender_history,
+        )?;
+        if outcome != TxOutcome::APPLIED {
+            return Ok(outcome);
+        }
+
+        if recipient.state.refuses(&TxRecordType::DEPOSIT).is_some() {
+            return Ok(TxOutcome::REJECTED(TxRejectReason::RECIPIENT_REFUSED));
+        }
+        recipient.handle_tx(
+            &TxRecordType::DEPOSIT,
+            &row.tx_id,
+            &row.amount,
+            recipient_history,
+        )?;
+
+        sender_history.set_tx(
+            &TxRecordType::TRANSFER,
+            &sender.client_id,
+            &row.tx_id,
+            &row.amount,
+        );
+        recipient_history.set_tx(
+            &TxRecordType::TRANSFER_IN,
+            &recipient.client_id,
+            &row.tx_id,
+            &row.amount,
+        );
+        Ok(TxOutcome::APPLIED)
+    }
+
+    // the conflicts + their events are kept in both histories, the sender's decide what can be done next
+    fn conflict(
+        row: &TxRow,
+        sender: &mut Account,
+        recipient: &mut Account,
+        sender_history: &mut TxHistory,
+        recipient_history: &mut TxHistory,
+    ) -> Result<TxOutcome, AppError> {
+        if let Some(reason) = sender.state.refuses(&row.type_id) {
+            return Ok(TxOutcome::REJECTED(reason));
+        }
+        // the balances of the recipient move too, a locked or closed recipient refuses them
+        if recipient.state.refuses(&row.type_id).is_some() {
+            return Ok(TxOutcome::REJECTED(TxRejectReason::RECIPIENT_REFUSED));
+        }
+
+        match row.type_id {
+            TxRecordType::DISPUTE => {
+                let tx = match sender_history.get_tx(&row.tx_id)? {
+                    Some(tx) if tx.type_id == TxRecordType::TRANSFER => tx,
+                    _ => return Ok(TxOutcome::REJECTED(TxRejectReason::UNKNOWN_TX)),
+                };
+                let amount = match sender_history.dispute(&tx, &row.amount)? {
+                    Ok(amount) => amount,
+                    Err(reason) => return Ok(TxOutcome::REJECTED(reason)),
+                };
+                recipient.held += amount;
+                recipient.available -= amount;
+            }
+            TxRecordType::RESOLVE | TxRecordType::CHARGEBACK => {
+                let amount = match sender_history.settle(&row.tx_id, &row.type_id, &row.amount)? {
+                    Ok((_, amount)) => amount,
+                    Err(reason) => return Ok(TxOutcome::REJECTED(reason)),
+                };
+                recipient.held -= amount;
+                if row.type_id == TxRecordType::RESOLVE {
+                    recipient.available += amount;
+                } else {
+                    recipient.total -= amount;
+                    sender.available += amount;
+                    sender.total += amount;
+                    sender.set_state(AccountState::LOCKED);
+                }
+            }
+            _ => return Ok(TxOutcome::REJECTED(TxRejectReason::UNKNOWN_TYPE)),
+        }
+
+        let conflicts: Vec<TxConflict> = sender_history
+            .get_conflicts(&row.tx_id)?
+            .into_iter()
+            .map(|c| TxConflict {
+                type_id: TxRecordType::TRANSFER_IN,
+                ..c
+            })
+            .collect();
+        recipient_history.set_conflicts(&row.tx_id, &conflicts);
+        let events = sender_history.get_events(&row.tx_id)?;
+        recipient_history.set_events(&row.tx_id, &events);
+        Ok(TxOutcome::APPLIED)
+    }
+}
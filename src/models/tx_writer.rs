@@ -1,7 +1,7 @@
 use csv::{ByteRecord, Writer};
 use std::fs::{self, File};
 
-use crate::lib::{constants::FN_NEW, error::AppError};
+use crate::common::{constants::FN_NEW, error::AppError};
 
 const PATH: &str = "models/tx_record";
 
@@ -21,19 +21,18 @@ impl TxWriter {
         for record in records {
             self.writer
                 .write_byte_record(record)
-                .map_err(|e| AppError::new(PATH, FN_WRITE_RECORDS, "04", &e.to_string()))?;
+                .map_err(|e| AppError::io(PATH, FN_WRITE_RECORDS, "04", e))?;
         }
 
         self.writer
             .flush()
-            .map_err(|e| AppError::new(PATH, FN_WRITE_RECORDS, "05", &e.to_string()))?;
+            .map_err(|e| AppError::io(PATH, FN_WRITE_RECORDS, "05", e))?;
 
         Ok(())
     }
 
     fn file_path(dir_path: &str, file_name: &str) -> Result<String, AppError> {
-        fs::create_dir_all(dir_path)
-            .map_err(|e| AppError::new(PATH, "file_path", "00", &e.to_string()))?;
+        fs::create_dir_all(dir_path).map_err(|e| AppError::io(PATH, "file_path", "00", e))?;
         Ok([dir_path, "/", file_name, ".csv"].join(""))
     }
 
@@ -43,7 +42,7 @@ impl TxWriter {
             .has_headers(false)
             .flexible(true)
             .from_path(&file_path)
-            .map_err(|e| AppError::new(PATH, FN_NEW, "00", &e.to_string()))?;
+            .map_err(|e| AppError::io(PATH, FN_NEW, "00", e))?;
         Ok(writer)
     }
 }
@@ -1,12 +1,10 @@
-use chrono::Utc;
 use crossbeam_channel::{bounded, select, unbounded, Receiver, Sender};
-use std::path::Path;
+use std::thread;
 use std::time::Duration;
-use std::{fs, thread};
 
-use super::account::AccountPath;
-use crate::lib::constants::{ACCOUNT_BACKUP_DIR, ACCOUNT_DIR};
-use crate::lib::error::AppError;
+use super::account::{Account, AccountPath};
+use crate::common::config::Config;
+use crate::common::error::AppError;
 
 const MAX_NUM_RECORDS: usize = 64_000;
 const THREAD_SLEEP_DURATION: u64 = 250;
@@ -16,16 +14,18 @@ const PATH: &str = "model/updater";
 
 pub struct Updater {
     started: bool,
+    config: Config,
     tx: Sender<Option<Vec<AccountPath>>>,
     rx: Receiver<Result<(), AppError>>,
 }
 
 impl Updater {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
         let (tx, _) = bounded(0);
         let (_, rx) = bounded(0);
         Self {
             started: false,
+            config: config.clone(),
             tx,
             rx,
         }
@@ -42,7 +42,7 @@ impl Updater {
     pub fn add(&self, account_paths: Vec<AccountPath>) -> Result<(), AppError> {
         self.tx
             .send(Some(account_paths))
-            .map_err(|e| AppError::new(PATH, "add", "00", &e.to_string()))?;
+            .map_err(|e| AppError::concurrency(PATH, "add", "00", e.to_string()))?;
         loop {
             if self.tx.len() >= MAX_NUM_RECORDS {
                 thread::sleep(Duration::from_millis(THREAD_SLEEP_DURATION));
@@ -77,7 +77,7 @@ impl Updater {
         self.tx = parent_tx;
         self.rx = parent_rx;
 
-        let mut manager = LoadManager::new(child_tx, child_rx);
+        let mut manager = LoadManager::new(&self.config, child_tx, child_rx);
         thread::spawn(move || {
             for _ in 0..NUM_WORKERS - 1 {
                 manager.spawn_worker();
@@ -90,6 +90,7 @@ impl Updater {
 }
 
 struct LoadManager {
+    config: Config,
     tx: Sender<Result<(), AppError>>,
     rx: Receiver<Option<Vec<AccountPath>>>,
     num_workers: u16,
@@ -99,8 +100,13 @@ struct LoadManager {
 }
 
 impl LoadManager {
-    fn new(tx: Sender<Result<(), AppError>>, rx: Receiver<Option<Vec<AccountPath>>>) -> Self {
+    fn new(
+        config: &Config,
+        tx: Sender<Result<(), AppError>>,
+        rx: Receiver<Option<Vec<AccountPath>>>,
+    ) -> Self {
         Self {
+            config: config.clone(),
             tx,
             rx,
             num_workers: 0,
@@ -114,20 +120,15 @@ impl LoadManager {
         loop {
             select! {
                 recv(self.rx) -> packet => {
-                    if let Ok(block) = packet {
-                        if let Some(account_paths) = block {
-                            let worker_id_ptr = self.worker_id_ptr.clone();
-                            let tx = self.worker_tx_channels.get(worker_id_ptr as usize).unwrap();
-                            tx.send(Some(account_paths)).unwrap();
-
-                            if (self.worker_id_ptr as usize) == self.worker_tx_channels.len() - 1 {
-                                self.worker_id_ptr = 0;
-                            } else {
-                                self.worker_id_ptr += 1;
-                            }
+                    if let Ok(Some(account_paths)) = packet {
+                        let worker_id_ptr = self.worker_id_ptr;
+                        let tx = self.worker_tx_channels.get(worker_id_ptr as usize).unwrap();
+                        tx.send(Some(account_paths)).unwrap();
+
+                        if (self.worker_id_ptr as usize) == self.worker_tx_channels.len() - 1 {
+                            self.worker_id_ptr = 0;
                         } else {
-                            self.shutdown();
-                            return;
+                            self.worker_id_ptr += 1;
                         }
                     } else {
                         self.shutdown();
@@ -142,10 +143,15 @@ impl LoadManager {
         for t in &self.worker_tx_channels {
             t.send(None).unwrap();
         }
+        let mut result = Ok(());
         for r in &self.worker_rx_channels {
-            let _ = r.recv().unwrap();
+            if let Ok(Err(err)) = r.recv() {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
         }
-        let _ = self.tx.send(Ok(()));
+        let _ = self.tx.send(result);
     }
 
     fn spawn_worker(&mut self) {
@@ -156,7 +162,7 @@ impl LoadManager {
         let wid = self.num_workers;
         let tx = worker_tx.clone();
         let rx = worker_rx.clone();
-        let mut worker = Worker::new(wid, tx, rx);
+        let mut worker = Worker::new(wid, &self.config, tx, rx);
         thread::spawn(move || worker.listen());
         self.num_workers += 1;
     }
@@ -164,6 +170,8 @@ impl LoadManager {
 
 struct Worker {
     id: u16,
+    config: Config,
+    error: Option<AppError>,
     tx: Sender<Result<u16, AppError>>,
     rx: Receiver<Option<Vec<AccountPath>>>,
 }
@@ -171,56 +179,34 @@ struct Worker {
 impl Worker {
     fn new(
         id: u16,
+        config: &Config,
         tx: Sender<Result<u16, AppError>>,
         rx: Receiver<Option<Vec<AccountPath>>>,
     ) -> Self {
-        Self { id, tx, rx }
+        Self {
+            id,
+            config: config.clone(),
+            error: None,
+            tx,
+            rx,
+        }
     }
 
     fn listen(&mut self) {
         loop {
             select! {
                 recv(self.rx) -> packet => {
-                    if let Ok(block) = packet {
-                        if let Some(account_paths) = block {
-                            for entry in account_paths {
-                                if entry.update_file {
-                                    let account_file = [ACCOUNT_DIR, &entry.file_name].join("/");
-                                    if Path::new(&account_file).exists() {
-                                        let backup_file = [
-                                            ACCOUNT_BACKUP_DIR,
-                                            "/",
-                                            &entry.file_name.replace(
-                                                ".csv",
-                                                &["_", &Utc::now().timestamp_millis().to_string(), ".csv"].join(""),
-                                            ),
-                                        ]
-                                        .join("");
-                                        let _ = fs::copy(&account_file, backup_file);
-                                        let _ = fs::remove_file(&account_file);
-                                    }
-
-                                    let _ = fs::copy(&entry.file_path, &account_file);
-                                    let _ = fs::remove_file(&entry.file_path);
-                                    continue;
-                                }
-
-                                let result = fs::read_to_string(&entry.file_path).map_err(|e| {
-                                    AppError::new(
-                                        PATH,
-                                        "process_entry",
-                                        &["00", &entry.file_path].join(" | "),
-                                        &e.to_string(),
-                                    )
-                                });
-
-                                if result.is_ok() {
-                                    println!("{}", result.unwrap().replace("\n", ""));
-                                }
+                    if let Ok(Some(account_paths)) = packet {
+                        // keep draining the queue after a failure so the manager never blocks
+                        if self.error.is_some() {
+                            continue;
+                        }
+
+                        for entry in account_paths {
+                            if let Err(err) = Account::publish(&entry.dir, &entry.file_name, &self.config) {
+                                self.error = Some(err);
+                                break;
                             }
-                        } else {
-                            self.shutdown();
-                            break;
                         }
                     } else {
                         self.shutdown();
@@ -232,6 +218,10 @@ impl Worker {
     }
 
     fn shutdown(&mut self) {
+        if let Some(err) = self.error.take() {
+            let _ = self.tx.send(Err(err));
+            return;
+        }
         let _ = self.tx.send(Ok(self.id));
     }
 }
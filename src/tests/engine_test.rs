@@ -0,0 +1,157 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::AccountState;
+use crate::models::engine::Engine;
+use crate::models::tx_dispute::DisputePolicy;
+use crate::models::tx_reader::{ParseMode, PrecisionPolicy};
+use crate::models::tx_record::{TxRecordType, TxRow};
+
+#[test]
+fn engine_commit_test() {
+    let config = TestHelper::config("engine_commit_test");
+    let mut engine = Engine::new(&config);
+
+    // nothing to commit
+    let result = engine.commit();
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_none());
+
+    engine.feed(TxRow::new(
+        TxRecordType::DEPOSIT,
+        45,
+        1,
+        Decimal::new(10, 0),
+    ));
+    engine.feed(TxRow::new(
+        TxRecordType::WITHDRAW,
+        45,
+        2,
+        Decimal::new(3, 0),
+    ));
+    engine.feed(TxRow::new(TxRecordType::DEPOSIT, 46, 3, Decimal::new(5, 0)));
+    engine.feed(TxRow::new(TxRecordType::DISPUTE, 46, 3, Decimal::new(0, 0)));
+    assert_eq!(engine.pending().len(), 4);
+
+    // fed rows aren't visible until committed
+    assert!(engine.account(45).is_none());
+
+    let result = engine.commit();
+    assert!(result.is_ok());
+    let report = result.unwrap().unwrap();
+    assert_eq!(report.rows, 4);
+    assert!(!report.skipped);
+    assert!(engine.pending().is_empty());
+
+    let account = engine.account(45).unwrap();
+    assert_eq!(account.available, Decimal::new(7, 0));
+    assert_eq!(account.total, Decimal::new(7, 0));
+
+    let account = engine.account(46).unwrap();
+    assert_eq!(account.available, Decimal::new(0, 0));
+    assert_eq!(account.held, Decimal::new(5, 0));
+
+    // later commits build on the committed accounts
+    engine.feed(TxRow::new(TxRecordType::RESOLVE, 46, 3, Decimal::new(0, 0)));
+    engine.feed(TxRow::new(
+        TxRecordType::DEPOSIT,
+        45,
+        1,
+        Decimal::new(10, 0),
+    ));
+    let report = engine.commit().unwrap().unwrap();
+    assert_eq!(report.rows, 2);
+    assert_eq!(report.duplicates.len(), 1);
+
+    let accounts = engine.accounts().unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].client_id, 45);
+    assert_eq!(accounts[0].total, Decimal::new(7, 0));
+    assert_eq!(accounts[1].client_id, 46);
+    assert_eq!(accounts[1].available, Decimal::new(5, 0));
+    assert_eq!(accounts[1].held, Decimal::new(0, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn engine_settings_test() {
+    let config = TestHelper::config("engine_settings_test");
+    let mut engine = Engine::new(&config);
+    engine.set_parse_mode(ParseMode::LENIENT);
+    engine.set_precision(PrecisionPolicy::REJECT);
+    engine.set_dispute_policy(DisputePolicy::new(1, vec![TxRecordType::RESOLVE]));
+
+    // the row with too many decimal places is rejected instead of failing the commit
+    engine.feed(TxRow::new(TxRecordType::DEPOSIT, 47, 1, Decimal::new(5, 0)));
+    engine.feed(TxRow::new(
+        TxRecordType::DEPOSIT,
+        47,
+        2,
+        Decimal::new(100001, 5),
+    ));
+    engine.feed(TxRow::new(TxRecordType::DISPUTE, 47, 1, Decimal::new(0, 0)));
+    engine.feed(TxRow::new(TxRecordType::RESOLVE, 47, 1, Decimal::new(0, 0)));
+    engine.feed(TxRow::new(TxRecordType::DISPUTE, 47, 1, Decimal::new(0, 0)));
+    let report = engine.commit().unwrap().unwrap();
+    assert_eq!(report.rows, 5);
+    assert!(report.quarantined.is_empty());
+
+    // disputed again under the policy
+    let account = engine.account(47).unwrap();
+    assert_eq!(account.held, Decimal::new(5, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    // nothing is left of the fed rows once committed
+    let feed_files = fs::read_dir(config.feed_dir()).unwrap().count();
+    assert_eq!(feed_files, 0);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn engine_commit_again_test() {
+    let config = TestHelper::config("engine_commit_again_test");
+    let mut engine = Engine::new(&config);
+
+    engine.feed(TxRow::new(TxRecordType::DEPOSIT, 96, 1, Decimal::new(5, 0)));
+    engine.feed(TxRow::new(TxRecordType::FREEZE, 96, 2, Decimal::new(0, 0)));
+    engine.commit().unwrap();
+    engine.feed(TxRow::new(TxRecordType::UNLOCK, 96, 3, Decimal::new(0, 0)));
+    engine.commit().unwrap();
+    assert_eq!(engine.account(96).unwrap().state, AccountState::ACTIVE);
+
+    // the same rows as an earlier commit are applied again, not skipped
+    engine.feed(TxRow::new(TxRecordType::FREEZE, 96, 2, Decimal::new(0, 0)));
+    let report = engine.commit().unwrap().unwrap();
+    assert!(!report.skipped);
+    assert_eq!(report.rows, 1);
+    assert_eq!(engine.account(96).unwrap().state, AccountState::FROZEN);
+
+    // a zero deposit is a rejection, not a malformed row
+    engine.feed(TxRow::new(TxRecordType::UNLOCK, 96, 4, Decimal::new(0, 0)));
+    engine.feed(TxRow::new(TxRecordType::DEPOSIT, 96, 5, Decimal::new(0, 0)));
+    let report = engine.commit().unwrap().unwrap();
+    assert!(report.quarantined.is_empty());
+
+    let rejections: Vec<String> = fs::read_dir(config.summary_dir())
+        .unwrap()
+        .flatten()
+        .map(|e| e.path().display().to_string())
+        .filter(|p| p.ends_with("_rejected.csv"))
+        .flat_map(|p| {
+            fs::read_to_string(p)
+                .unwrap()
+                .lines()
+                .map(|l| l.to_string())
+                .collect::<Vec<String>>()
+        })
+        .collect();
+    assert!(rejections
+        .iter()
+        .any(|l| l.ends_with(",deposit,96,5,0.0000,non_positive_amount")));
+    assert_eq!(engine.account(96).unwrap().total, Decimal::new(5, 0));
+
+    TestHelper::clean(&config);
+}
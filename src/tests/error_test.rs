@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::io;
+
+use crate::common::error::{AppError, ErrorKind};
+
+#[test]
+fn error_kind_test() {
+    let errors = [
+        (AppError::io("model/x", "m", "00", "io"), ErrorKind::IO),
+        (
+            AppError::parse("model/x", "m", "01", "parse"),
+            ErrorKind::PARSE,
+        ),
+        (
+            AppError::storage("model/x", "m", "02", "storage"),
+            ErrorKind::STORAGE,
+        ),
+        (
+            AppError::rule("model/x", "m", "03", "rule"),
+            ErrorKind::RULE,
+        ),
+        (
+            AppError::concurrency("model/x", "m", "04", "concurrency"),
+            ErrorKind::CONCURRENCY,
+        ),
+    ];
+
+    for (i, (err, kind)) in errors.iter().enumerate() {
+        assert_eq!(err.kind(), *kind);
+
+        let c = err.context();
+        assert_eq!(c.path, "model/x");
+        assert_eq!(c.method, "m");
+        assert_eq!(c.tag, format!("{:02}", i));
+        assert!(err
+            .to_string()
+            .ends_with(&["model/x", "m", &c.tag, &c.message].join(" | ")));
+
+        // a message has no source
+        assert!(err.source().is_none());
+    }
+}
+
+#[test]
+fn error_source_test() {
+    // an underlying error is kept as the source, its message as the message
+    let err = AppError::io(
+        "model/x",
+        "m",
+        "00",
+        io::Error::new(io::ErrorKind::NotFound, "missing"),
+    );
+    assert_eq!(err.context().message, "missing");
+    let source = err.source().unwrap();
+    assert_eq!(
+        source.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::NotFound
+    );
+
+    // an app error can be the source of another
+    let err = AppError::storage(
+        "model/y",
+        "n",
+        "01",
+        AppError::parse("model/x", "m", "00", "bad"),
+    );
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+    let source = err.source().unwrap().downcast_ref::<AppError>().unwrap();
+    assert_eq!(source.kind(), ErrorKind::PARSE);
+}
@@ -1,17 +1,23 @@
 use std::fs;
 
-use crate::lib::constants::{ACCOUNT_DIR, TRANSACTION_DIR};
+use crate::common::config::Config;
+
+const TEST_DATA_DIR: &str = "data/tests";
 
 #[allow(dead_code)]
 pub struct TestHelper;
 
 impl TestHelper {
+    // every test gets its own data root, so tests never share accounts or history.
     #[allow(dead_code)]
-    pub fn clean(client_id: &u16) {
-        let account = [ACCOUNT_DIR, "/", &client_id.to_string(), ".csv"].join("");
-        let _ = fs::remove_file(account);
+    pub fn config(test_name: &str) -> Config {
+        let data_dir = [TEST_DATA_DIR, "/", test_name].join("");
+        let _ = fs::remove_dir_all(&data_dir);
+        Config::new(&data_dir)
+    }
 
-        let db = [TRANSACTION_DIR, "/", &client_id.to_string(), "_db"].join("");
-        let _ = fs::remove_dir_all(db);
+    #[allow(dead_code)]
+    pub fn clean(config: &Config) {
+        let _ = fs::remove_dir_all(config.data_dir());
     }
 }
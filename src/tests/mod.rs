@@ -17,3 +17,72 @@ mod processor_resolve_test;
 
 #[cfg(test)]
 mod processor_chargeback_test;
+
+#[cfg(test)]
+mod processor_rollback_test;
+
+#[cfg(test)]
+mod processor_journal_test;
+
+#[cfg(test)]
+mod processor_batch_test;
+
+#[cfg(test)]
+mod processor_registry_test;
+
+#[cfg(test)]
+mod processor_duplicate_test;
+
+#[cfg(test)]
+mod processor_rejection_test;
+
+#[cfg(test)]
+mod processor_parse_test;
+
+#[cfg(test)]
+mod engine_test;
+
+#[cfg(test)]
+mod processor_output_test;
+
+#[cfg(test)]
+mod processor_ndjson_test;
+
+#[cfg(test)]
+mod processor_stream_test;
+
+#[cfg(test)]
+mod processor_columns_test;
+
+#[cfg(test)]
+mod processor_store_test;
+
+#[cfg(test)]
+mod processor_precision_test;
+
+#[cfg(test)]
+mod processor_statement_test;
+
+#[cfg(test)]
+mod processor_show_test;
+
+#[cfg(test)]
+mod processor_restore_test;
+
+#[cfg(test)]
+mod processor_admin_test;
+
+#[cfg(test)]
+mod processor_transfer_test;
+
+#[cfg(test)]
+mod processor_schedule_test;
+
+#[cfg(test)]
+mod processor_partial_test;
+
+#[cfg(test)]
+mod processor_redispute_test;
+
+#[cfg(test)]
+mod error_test;
@@ -0,0 +1,125 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::{Account, AccountState};
+use crate::models::account_audit::AccountAuditLog;
+use crate::models::processor::Processor;
+use crate::models::tx_rejection::TxRejectionLog;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount
+// deposit,68,1,10
+// deposit,68,2,5
+// dispute,68,2,
+// chargeback,68,2,
+// deposit,68,3,1
+// unlock,68,4,
+// deposit,68,5,1
+// unlock,68,6,
+// freeze,68,7,
+// withdrawal,68,8,1
+// dispute,68,1,
+// resolve,68,1,
+// close,68,9,
+// deposit,69,10,2
+// withdrawal,69,11,2
+// close,69,12,
+// deposit,69,13,1
+// unlock,69,14,
+
+#[test]
+fn process_admin_test() {
+    let config = TestHelper::config("process_admin_test");
+    let result = Processor::new("src/tests/csv/admin.csv", &config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    p.set_actor("ops");
+    assert!(p.process_data(false).is_ok());
+
+    // unlocked after the chargeback, then frozen. disputes go on while frozen.
+    let account = Account::find(68, &config).unwrap();
+    assert_eq!(account.state, AccountState::FROZEN);
+    assert!(account.locked);
+    assert_eq!(account.available, Decimal::new(11, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+
+    let account = Account::find(69, &config).unwrap();
+    assert_eq!(account.state, AccountState::CLOSED);
+    assert_eq!(account.total, Decimal::new(0, 0));
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/admin.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",6,deposit,68,3,1.0000,account_locked"].join(""),
+        [file, ",9,unlock,68,6,0.0000,invalid_transition"].join(""),
+        [file, ",11,withdrawal,68,8,1.0000,account_frozen"].join(""),
+        [file, ",14,close,68,9,0.0000,non_zero_balance"].join(""),
+        [file, ",18,deposit,69,13,1.0000,account_closed"].join(""),
+        [file, ",19,unlock,69,14,0.0000,invalid_transition"].join(""),
+    ];
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines, expected);
+
+    // every change of state is in the audit trail, with who made it + the row that made it
+    let audits = AccountAuditLog::read(Some(68), &config).unwrap();
+    let changes: Vec<(&str, &str, &str, u64)> = audits
+        .iter()
+        .map(|a| (a.action.as_str(), a.from.as_str(), a.to.as_str(), a.line))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            ("chargeback", "active", "locked", 5),
+            ("unlock", "locked", "active", 7),
+            ("freeze", "active", "frozen", 10),
+        ]
+    );
+    assert!(audits.iter().all(|a| a.actor == "ops" && a.file == file));
+    assert!(!audits[0].time.is_empty());
+
+    let audits = AccountAuditLog::read(None, &config).unwrap();
+    assert_eq!(audits.len(), 4);
+    assert_eq!(audits[3].client, 69);
+    assert_eq!(audits[3].to, "closed");
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_admin_legacy_test() {
+    let config = TestHelper::config("process_admin_legacy_test");
+
+    // an account file from before account states, locked by a chargeback
+    fs::create_dir_all(config.account_dir()).unwrap();
+    fs::write(
+        [&config.account_dir(), "/71.csv"].join(""),
+        "71,1.0000,0.0000,1.0000,true\n",
+    )
+    .unwrap();
+
+    let account = Account::find(71, &config).unwrap();
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    let path = [config.data_dir(), "/unlock.csv"].join("");
+    fs::write(
+        &path,
+        "type,client,tx,amount\nunlock,71,1,\ndeposit,71,2,1\n",
+    )
+    .unwrap();
+    let result = Processor::new(&path, &config);
+    assert!(result.is_ok());
+    assert!(result.unwrap().process_data(false).is_ok());
+
+    let account = Account::find(71, &config).unwrap();
+    assert_eq!(account.state, AccountState::ACTIVE);
+    assert!(!account.locked);
+    assert_eq!(account.total, Decimal::new(2, 0));
+
+    TestHelper::clean(&config);
+}
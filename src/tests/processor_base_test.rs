@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -18,7 +17,8 @@ fn process_base_test() {
     // withdrawal, 27, 4, 1.5
     // withdrawal, 28, 5, 3.0
 
-    let result = Processor::new("src/tests/csv/base.csv");
+    let config = TestHelper::config("process_base_test");
+    let result = Processor::new("src/tests/csv/base.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
@@ -26,22 +26,20 @@ fn process_base_test() {
     assert!(result.is_ok());
 
     let client_id = 27;
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(15, 1));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(15, 1));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
-
     let client_id = 28;
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(2, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(2, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use rust_decimal::Decimal;
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_input::{InputOrder, TxInput};
+
+// --------- //
+// input csv //
+// --------- //
+
+// batch_a.csv
+// type,client,tx,amount
+// deposit,38,1,10
+
+// batch_b.csv
+// type,client,tx,amount
+// withdrawal,38,2,4
+
+#[test]
+fn process_batch_dir_test() {
+    let config = TestHelper::config("process_batch_dir_test");
+    let paths = TxInput::resolve(&["src/tests/csv/batch".to_string()], InputOrder::NAME).unwrap();
+    assert_eq!(
+        paths,
+        vec![
+            "src/tests/csv/batch/batch_a.csv".to_string(),
+            "src/tests/csv/batch/batch_b.csv".to_string()
+        ]
+    );
+
+    let result = Processor::new_batch(paths, &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let reports = result.unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].path, "src/tests/csv/batch/batch_a.csv");
+    assert_eq!(reports[0].rows, 1);
+    assert_eq!(reports[1].path, "src/tests/csv/batch/batch_b.csv");
+    assert_eq!(reports[1].rows, 1);
+
+    // deposit then withdrawal
+    let account = Account::new(38, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(6, 0));
+    assert_eq!(account.total, Decimal::new(6, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_batch_glob_test() {
+    let config = TestHelper::config("process_batch_glob_test");
+    let paths = TxInput::resolve(
+        &["src/tests/csv/batch/batch_?.csv".to_string()],
+        InputOrder::NAME,
+    )
+    .unwrap();
+    assert_eq!(paths.len(), 2);
+
+    let result = Processor::new_batch(paths, &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let account = Account::new(38, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(6, 0));
+    assert_eq!(account.total, Decimal::new(6, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_batch_glob_star_test() {
+    let config = TestHelper::config("process_batch_glob_star_test");
+    let paths = TxInput::resolve(
+        &["src/tests/csv/batch/*_*.c*v".to_string()],
+        InputOrder::NAME,
+    )
+    .unwrap();
+    assert_eq!(paths.len(), 2);
+
+    // a name that almost matches a pattern of many stars is turned down without trying every split
+    let dir = [config.data_dir(), "/glob"].join("");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write([&dir, "/", &"a".repeat(100)].join(""), "").unwrap();
+    let pattern = [&dir, "/", &"*a".repeat(20), "*b"].join("");
+    let paths = TxInput::resolve(&[pattern], InputOrder::NAME).unwrap();
+    assert!(paths.is_empty());
+
+    let pattern = [&dir, "/", &"*a".repeat(20), "*"].join("");
+    let paths = TxInput::resolve(&[pattern], InputOrder::NAME).unwrap();
+    assert_eq!(paths.len(), 1);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_batch_manifest_test() {
+    // manifest.txt
+    // batch_b.csv
+    // batch_a.csv
+
+    let config = TestHelper::config("process_batch_manifest_test");
+    let paths = TxInput::from_manifest("src/tests/csv/batch/manifest.txt").unwrap();
+    assert_eq!(paths.len(), 2);
+
+    let result = Processor::new_batch(paths, &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    // the withdrawal is ignored, there are no funds yet
+    let account = Account::new(38, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(10, 0));
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_batch_mtime_test() {
+    let config = TestHelper::config("process_batch_mtime_test");
+    let input_dir = [config.data_dir(), "/input"].join("");
+    assert!(fs::create_dir_all(&input_dir).is_ok());
+
+    // batch_b.csv is older than batch_a.csv
+    let now = SystemTime::now();
+    for (name, age) in [("batch_a.csv", 0), ("batch_b.csv", 60)] {
+        let path = [&input_dir, "/", name].join("");
+        assert!(fs::copy(["src/tests/csv/batch/", name].join(""), &path).is_ok());
+        let f = File::options().write(true).open(&path).unwrap();
+        assert!(f.set_modified(now - Duration::from_secs(age)).is_ok());
+    }
+
+    let paths = TxInput::resolve(std::slice::from_ref(&input_dir), InputOrder::MTIME).unwrap();
+    assert_eq!(paths[0], [&input_dir, "/batch_b.csv"].join(""));
+
+    let result = Processor::new_batch(paths, &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let account = Account::new(38, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(10, 0));
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    TestHelper::clean(&config);
+}
@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -21,22 +20,23 @@ fn process_chargeback_base_test() {
     // deposit,12,6,12
     // chargeback,12,1
 
+    let config = TestHelper::config("process_chargeback_base_test");
     let client_id = 12;
-    let result = Processor::new("src/tests/csv/chargeback_base.csv");
+    let result = Processor::new("src/tests/csv/chargeback_base.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -54,22 +54,23 @@ fn process_chargeback_tx_dne_test() {
     // deposit,13,6,12
     // chargeback,13,155
 
+    let config = TestHelper::config("process_chargeback_tx_dne_test");
     let client_id = 13;
-    let result = Processor::new("src/tests/csv/chargeback_tx_dne.csv");
+    let result = Processor::new("src/tests/csv/chargeback_tx_dne.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -87,22 +88,23 @@ fn process_chargeback_on_non_dispute_test() {
     // deposit,14,6,12
     // chargeback,14,1
 
+    let config = TestHelper::config("process_chargeback_on_non_dispute_test");
     let client_id = 14;
-    let result = Processor::new("src/tests/csv/chargeback_on_non_dispute.csv");
+    let result = Processor::new("src/tests/csv/chargeback_on_non_dispute.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -121,22 +123,23 @@ fn process_chargeback_on_withdraw_test() {
     // deposit,15,6,12
     // chargeback,15,4
 
+    let config = TestHelper::config("process_chargeback_on_withdraw_test");
     let client_id = 15;
-    let result = Processor::new("src/tests/csv/chargeback_on_withdraw.csv");
+    let result = Processor::new("src/tests/csv/chargeback_on_withdraw.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -156,22 +159,23 @@ fn process_chargeback_on_resolve_test() {
     // resolved,16,2
     // chargeback,16,2
 
+    let config = TestHelper::config("process_chargeback_on_resolve_test");
     let client_id = 16;
-    let result = Processor::new("src/tests/csv/chargeback_on_resolved.csv");
+    let result = Processor::new("src/tests/csv/chargeback_on_resolved.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(22, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(22, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -191,22 +195,23 @@ fn process_chargeback_deposit_test() {
     // chargeback,17,1
     // deposit,17,7,50
 
+    let config = TestHelper::config("process_chargeback_deposit_test");
     let client_id = 17;
-    let result = Processor::new("src/tests/csv/chargeback_deposit.csv");
+    let result = Processor::new("src/tests/csv/chargeback_deposit.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -226,22 +231,23 @@ fn process_chargeback_withdraw_test() {
     // chargeback,18,1
     // withdrawal,18,7,10
 
+    let config = TestHelper::config("process_chargeback_withdraw_test");
     let client_id = 18;
-    let result = Processor::new("src/tests/csv/chargeback_withdraw.csv");
+    let result = Processor::new("src/tests/csv/chargeback_withdraw.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -261,22 +267,23 @@ fn process_chargeback_dispute_test() {
     // chargeback,19,1
     // dispute,19,1
 
+    let config = TestHelper::config("process_chargeback_dispute_test");
     let client_id = 19;
-    let result = Processor::new("src/tests/csv/chargeback_dispute.csv");
+    let result = Processor::new("src/tests/csv/chargeback_dispute.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -296,22 +303,23 @@ fn process_chargeback_resolve_test() {
     // chargeback,20,1
     // resolve,20,1
 
+    let config = TestHelper::config("process_chargeback_resolve_test");
     let client_id = 20;
-    let result = Processor::new("src/tests/csv/chargeback_resolve.csv");
+    let result = Processor::new("src/tests/csv/chargeback_resolve.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -320,22 +328,23 @@ fn process_chargeback_chargeback_test() {
     // input csv //
     // --------- //
 
+    let config = TestHelper::config("process_chargeback_chargeback_test");
     let client_id = 21;
-    let result = Processor::new("src/tests/csv/chargeback_chargeback.csv");
+    let result = Processor::new("src/tests/csv/chargeback_chargeback.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -358,8 +367,9 @@ fn process_chargeback_multi_test() {
     // deposit,26,6,12
     // chargeback,26,1
 
+    let config = TestHelper::config("process_chargeback_multi_test");
     let client_id = 26;
-    let result = Processor::new("src/tests/csv/chargeback_multi_0.csv");
+    let result = Processor::new("src/tests/csv/chargeback_multi_0.csv", &config);
     assert!(result.is_ok());
 
     let mut p = result.unwrap();
@@ -378,13 +388,13 @@ fn process_chargeback_multi_test() {
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(24, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(24, 0));
     assert!(account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
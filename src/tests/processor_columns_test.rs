@@ -0,0 +1,104 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_columns::TxColumnMap;
+
+// --------- //
+// input csv //
+// --------- //
+
+// customer,note,kind,id,value
+// 56,a,deposit,1,10
+// 56,b,withdrawal,2,4
+// 56,c,deposit,3,1.5
+
+#[test]
+fn process_columns_test() {
+    let config = TestHelper::config("process_columns_test");
+    let result = Processor::new("src/tests/csv/partner.csv", &config);
+    assert!(result.is_ok());
+
+    let mut column_map = TxColumnMap::from_file("src/tests/csv/partner_columns.csv").unwrap();
+    column_map.set_ignore_unknown(true);
+
+    let mut p = result.unwrap();
+    p.set_column_map(column_map);
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap()[0].rows, 3);
+
+    let account = Account::find(56, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(75, 1));
+    assert_eq!(account.total, Decimal::new(75, 1));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_columns_unknown_test() {
+    let config = TestHelper::config("process_columns_unknown_test");
+    let result = Processor::new("src/tests/csv/partner.csv", &config);
+    assert!(result.is_ok());
+
+    // the note column has no known name
+    let mut p = result.unwrap();
+    p.set_column_map(TxColumnMap::from_file("src/tests/csv/partner_columns.csv").unwrap());
+    let result = p.process_data(false);
+    assert!(result.is_err());
+
+    let err = result.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::PARSE);
+    assert_eq!(
+        err.context().message,
+        "line 1 | byte 0 | unknown column | note"
+    );
+    assert!(Account::find(56, &config).is_none());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_columns_missing_test() {
+    let config = TestHelper::config("process_columns_missing_test");
+    fs::create_dir_all(config.data_dir()).unwrap();
+
+    let path = [config.data_dir(), "/missing.csv"].join("");
+    fs::write(&path, "type,client,amount\ndeposit,58,1\n").unwrap();
+
+    let result = Processor::new(&path, &config);
+    assert!(result.is_ok());
+
+    let result = result.unwrap().process_data(false);
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .context()
+        .message
+        .ends_with("missing column | tx"));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_columns_ndjson_test() {
+    // {"kind":"deposit","customer":57,"id":9,"value":"2","note":"x"}
+
+    let config = TestHelper::config("process_columns_ndjson_test");
+    let result = Processor::new("src/tests/csv/partner.ndjson", &config);
+    assert!(result.is_ok());
+
+    // json keys go through the same aliases
+    let mut p = result.unwrap();
+    p.set_column_map(TxColumnMap::from_file("src/tests/csv/partner_columns.csv").unwrap());
+    assert!(p.process_data(false).is_ok());
+
+    let account = Account::find(57, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(2, 0));
+
+    TestHelper::clean(&config);
+}
@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -21,7 +20,8 @@ fn process_deposit_test() {
     // deposit,3,7,2
     // deposit,1,8,2
 
-    let result = Processor::new("src/tests/csv/deposit.csv");
+    let config = TestHelper::config("process_deposit_test");
+    let result = Processor::new("src/tests/csv/deposit.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
@@ -30,17 +30,14 @@ fn process_deposit_test() {
     // return;
     assert!(result.is_ok());
 
-    let account = Account::new(4, ACCOUNT_DIR);
+    let account = Account::new(4, &config.account_dir(), &config);
     assert_eq!(account.client_id, 4);
     assert_eq!(account.available, Decimal::new(3, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(3, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&1);
-    TestHelper::clean(&2);
-    TestHelper::clean(&3);
-    TestHelper::clean(&4);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -61,27 +58,28 @@ fn process_deposit_multi_test() {
     // deposit,22,7,2
     // deposit,22,8,2
 
+    let config = TestHelper::config("process_deposit_multi_test");
     let client_id = 22;
-    let result = Processor::new("src/tests/csv/deposit_multi_0.csv");
+    let result = Processor::new("src/tests/csv/deposit_multi_0.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let result = Processor::new("src/tests/csv/deposit_multi_1.csv");
+    let result = Processor::new("src/tests/csv/deposit_multi_1.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(12, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(12, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -19,8 +18,9 @@ fn process_dispute_base_test() {
     // withdrawal,5,5,1
     // dispute,5,1
 
+    let config = TestHelper::config("process_dispute_base_test");
     let client_id = 5;
-    let result = Processor::new("src/tests/csv/dispute_base.csv");
+    let result = Processor::new("src/tests/csv/dispute_base.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
@@ -28,14 +28,14 @@ fn process_dispute_base_test() {
     assert!(result.is_ok());
 
     // check balance
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(1, 0));
     assert_eq!(account.held, Decimal::new(1, 0));
     assert_eq!(account.total, Decimal::new(2, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -51,8 +51,9 @@ fn process_dispute_tx_dne_test() {
     // withdrawal,6,4,1
     // dispute,6,50
 
+    let config = TestHelper::config("process_dispute_tx_dne_test");
     let client_id = 6;
-    let result = Processor::new("src/tests/csv/dispute_tx_dne.csv");
+    let result = Processor::new("src/tests/csv/dispute_tx_dne.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
@@ -60,14 +61,14 @@ fn process_dispute_tx_dne_test() {
     assert!(result.is_ok());
 
     // check balance
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(14, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(14, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -85,8 +86,9 @@ fn process_dispute_existing_dispute_test() {
     // dispute,8,1
     // dispute,8,1
 
+    let config = TestHelper::config("process_dispute_existing_dispute_test");
     let client_id = 8;
-    let result = Processor::new("src/tests/csv/dispute_existing_dispute.csv");
+    let result = Processor::new("src/tests/csv/dispute_existing_dispute.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
@@ -94,14 +96,14 @@ fn process_dispute_existing_dispute_test() {
     assert!(result.is_ok());
 
     // check balance
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(12, 0));
     assert_eq!(account.held, Decimal::new(5, 0));
     assert_eq!(account.total, Decimal::new(17, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -120,8 +122,9 @@ fn process_dispute_multi_test() {
     // withdrawal,24,5,1
     // dispute,24,1
 
+    let config = TestHelper::config("process_dispute_multi_test");
     let client_id = 24;
-    let result = Processor::new("src/tests/csv/dispute_multi_0.csv");
+    let result = Processor::new("src/tests/csv/dispute_multi_0.csv", &config);
     assert!(result.is_ok());
 
     let mut p = result.unwrap();
@@ -134,12 +137,12 @@ fn process_dispute_multi_test() {
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(1, 0));
     assert_eq!(account.held, Decimal::new(1, 0));
     assert_eq!(account.total, Decimal::new(2, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
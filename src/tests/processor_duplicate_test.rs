@@ -0,0 +1,92 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+
+// --------- //
+// input csv //
+// --------- //
+
+// duplicate.csv
+// type,client,tx,amount
+// deposit,40,1,10
+// deposit,41,1,10
+// deposit,40,1,5
+// dispute,40,1,
+// deposit,41,2,3
+
+// duplicate_next.csv
+// type,client,tx,amount
+// deposit,41,2,4
+// deposit,41,3,4
+
+#[test]
+fn process_duplicate_test() {
+    let config = TestHelper::config("process_duplicate_test");
+    let p = Processor::new("src/tests/csv/duplicate.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+
+    // tx 1 is taken by client 40, for any client
+    assert_eq!(reports[0].rows, 5);
+    assert_eq!(reports[0].duplicates.len(), 2);
+    assert_eq!(reports[0].duplicates[0].client_id, 41);
+    assert_eq!(reports[0].duplicates[1].client_id, 40);
+    assert_eq!(
+        reports[0].duplicates_to_string()[1],
+        "src/tests/csv/duplicate.csv,duplicate,deposit,40,1,5.0000"
+    );
+
+    // the dispute holds the first deposit, not the duplicate
+    let account = Account::new(40, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(0, 0));
+    assert_eq!(account.held, Decimal::new(10, 0));
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    let account = Account::new(41, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(3, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_duplicate_runs_test() {
+    let config = TestHelper::config("process_duplicate_runs_test");
+    let p = Processor::new("src/tests/csv/duplicate.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // tx 2 was used by the previous run
+    let p = Processor::new("src/tests/csv/duplicate_next.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert_eq!(reports[0].duplicates.len(), 1);
+    assert_eq!(reports[0].duplicates[0].tx_id, 2);
+
+    let account = Account::new(41, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(7, 0));
+    assert_eq!(account.total, Decimal::new(7, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_duplicate_failed_test() {
+    let config = TestHelper::config("process_duplicate_failed_test");
+
+    // stage 2 fails, the tx ids of the run are not kept
+    let account_path = [&config.account_dir(), "/41.csv"].join("");
+    assert!(fs::create_dir_all(&account_path).is_ok());
+
+    let p = Processor::new("src/tests/csv/duplicate_next.csv", &config).unwrap();
+    assert!(p.process_data(true).is_err());
+    assert!(fs::remove_dir_all(&account_path).is_ok());
+
+    let p = Processor::new("src/tests/csv/duplicate_next.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert!(reports[0].duplicates.is_empty());
+
+    let account = Account::new(41, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(8, 0));
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,280 @@
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::Path;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::journal::{Journal, JournalState};
+use crate::models::processor::Processor;
+use crate::models::tx_history::TxHistory;
+use crate::models::tx_index::TxIndex;
+
+#[test]
+fn process_journal_cluster_crash_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,33,1,10
+
+    let config = TestHelper::config("process_journal_cluster_crash_test");
+    let result = Processor::new("src/tests/csv/journal_cluster.csv", &config);
+    assert!(result.is_ok());
+
+    // crash in stage 1
+    let p = result.unwrap();
+    let journal = Journal::new(p.csv_summary_dir(), &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+    drop(p);
+    assert_eq!(journal.state(), JournalState::CLUSTER);
+
+    // recover on startup
+    let result = Processor::new("src/tests/csv/journal_cluster.csv", &config);
+    assert!(result.is_ok());
+    assert_eq!(journal.state(), JournalState::NONE);
+
+    assert!(!Path::new(&[&config.account_dir(), "/33.csv"].join("")).exists());
+    assert!(!Path::new(&[&config.transaction_dir(), "/33_db"].join("")).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_update_crash_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,34,2,5
+
+    // type,client,tx,amount
+    // deposit,34,5,10
+    // deposit,36,6,10
+
+    let config = TestHelper::config("process_journal_update_crash_test");
+    let client_id = 34;
+    let result = Processor::new("src/tests/csv/journal_update_base.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let result = Processor::new("src/tests/csv/journal_update.csv", &config);
+    assert!(result.is_ok());
+
+    // crash in stage 2, after client 34 was published
+    let p = result.unwrap();
+    let journal = Journal::new(p.csv_summary_dir(), &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+    assert!(p.journal().write(JournalState::UPDATE).is_ok());
+    assert!(Account::publish(p.csv_summary_dir(), "34.csv", &config).is_ok());
+    drop(p);
+
+    let account = Account::new(client_id, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(15, 0));
+
+    // recover on startup
+    let result = Processor::new("src/tests/csv/journal_update.csv", &config);
+    assert!(result.is_ok());
+    assert_eq!(journal.state(), JournalState::NONE);
+
+    let account = Account::new(client_id, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(5, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    {
+        let db = TxHistory::open_committed(&34, &config).unwrap();
+        assert!(db.contains_key(b"2").unwrap());
+        assert!(!db.contains_key(b"5").unwrap());
+    }
+
+    assert!(!Path::new(&[&config.account_dir(), "/36.csv"].join("")).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_finish_crash_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,35,7,10
+    // deposit,37,8,10
+
+    let config = TestHelper::config("process_journal_finish_crash_test");
+    let result = Processor::new("src/tests/csv/journal_finish.csv", &config);
+    assert!(result.is_ok());
+
+    // crash in stage 2, after every client was published
+    let p = result.unwrap();
+    let journal = Journal::new(p.csv_summary_dir(), &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+    assert!(p.journal().write(JournalState::UPDATE).is_ok());
+    assert!(Account::publish(p.csv_summary_dir(), "35.csv", &config).is_ok());
+    assert!(Account::publish(p.csv_summary_dir(), "37.csv", &config).is_ok());
+    drop(p);
+
+    // recover on startup
+    let result = Processor::new("src/tests/csv/journal_finish.csv", &config);
+    assert!(result.is_ok());
+    assert_eq!(journal.state(), JournalState::NONE);
+
+    for client_id in [35, 37] {
+        let account = Account::new(client_id, &config.account_dir(), &config);
+        assert_eq!(account.available, Decimal::new(10, 0));
+        assert_eq!(account.total, Decimal::new(10, 0));
+    }
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_lock_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,87,1,10
+
+    let config = TestHelper::config("process_journal_lock_test");
+    let result = Processor::new("src/tests/csv/journal_lock.csv", &config);
+    assert!(result.is_ok());
+
+    // a run of another process that is still alive, pid 1 never goes away
+    let p = result.unwrap();
+    let journal = Journal::new(p.csv_summary_dir(), &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+    drop(p);
+    let lock_path = [&config.journal_dir(), "/journal_lock.lock"].join("");
+    assert!(fs::write(&lock_path, "1").is_ok());
+
+    // its journal is left alone, and can't be taken over
+    assert!(Processor::new("src/tests/csv/journal_finish.csv", &config).is_ok());
+    assert_eq!(journal.state(), JournalState::CLUSTER);
+    let result = journal.write(JournalState::UPDATE);
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::CONCURRENCY);
+
+    // once the process is gone the lock is stale + the run is recovered
+    assert!(fs::write(&lock_path, u32::MAX.to_string()).is_ok());
+    assert!(Processor::new("src/tests/csv/journal_finish.csv", &config).is_ok());
+    assert_eq!(journal.state(), JournalState::NONE);
+    assert!(!Path::new(&lock_path).exists());
+    assert!(!Path::new(&[&config.account_dir(), "/87.csv"].join("")).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_publish_failure_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,88,1,10
+    // deposit,89,2,5
+
+    let config = TestHelper::config("process_journal_publish_failure_test");
+
+    // the publish of client 89 fails in the updater, the run rolls itself back
+    let account_89 = [&config.account_dir(), "/89.csv"].join("");
+    assert!(fs::create_dir_all(&account_89).is_ok());
+    let p = Processor::new("src/tests/csv/journal_publish.csv", &config).unwrap();
+    assert!(p.process_data(false).is_err());
+    assert!(!Path::new(&[&config.account_dir(), "/88.csv"].join("")).exists());
+    assert!(fs::remove_dir_all(&account_89).is_ok());
+
+    // the registry can't be written once the accounts are published, the journal is left for recovery
+    assert!(fs::write(config.registry_dir(), "").is_ok());
+    let p = Processor::new("src/tests/csv/journal_publish.csv", &config).unwrap();
+    assert!(p.process_data(false).is_err());
+    let journal = Journal::new(p.csv_summary_dir(), &config).unwrap();
+    assert_eq!(journal.state(), JournalState::UPDATE);
+
+    // recovery registers the input, so it isn't applied twice
+    assert!(fs::remove_file(config.registry_dir()).is_ok());
+    let p = Processor::new("src/tests/csv/journal_publish.csv", &config).unwrap();
+    assert_eq!(journal.state(), JournalState::NONE);
+    let reports = p.process_data(false).unwrap();
+    assert!(reports[0].skipped);
+
+    let account = Account::find(88, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(10, 0));
+    let account = Account::find(89, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_pid_reuse_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,87,1,10
+
+    let config = TestHelper::config("process_journal_pid_reuse_test");
+    let p = Processor::new("src/tests/csv/journal_lock.csv", &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+    drop(p);
+
+    // a run of a former process with the same pid, as in a container
+    let lock_path = [&config.journal_dir(), "/journal_lock.lock"].join("");
+    let holder = [std::process::id().to_string(), "0,0".to_string()].join(",");
+    assert!(fs::write(&lock_path, holder).is_ok());
+
+    // the lock is stale + the run is recovered
+    let journal =
+        Journal::new(&[&config.summary_dir(), "/journal_lock"].join(""), &config).unwrap();
+    assert!(Processor::new("src/tests/csv/journal_finish.csv", &config).is_ok());
+    assert_eq!(journal.state(), JournalState::NONE);
+    assert!(!Path::new(&lock_path).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_journal_live_run_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,87,1,10
+
+    let config = TestHelper::config("process_journal_live_run_test");
+    let p = Processor::new("src/tests/csv/journal_lock.csv", &config).unwrap();
+    assert!(p.journal().write(JournalState::CLUSTER).is_ok());
+    assert!(p.cluster_transactions().is_ok());
+
+    // a second run on the same input can't start, + leaves the staged data of the live run alone
+    let result = Processor::new("src/tests/csv/journal_lock.csv", &config);
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::CONCURRENCY);
+    assert!(Path::new(p.csv_summary_dir()).exists());
+    assert_eq!(p.journal().state(), JournalState::CLUSTER);
+
+    // nor can a run on another input while the tx index is held
+    let other = Processor::new("src/tests/csv/journal_finish.csv", &config).unwrap();
+    let index_lock = TxIndex::acquire(&config).unwrap();
+    let result = other.process_data(false);
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::CONCURRENCY);
+    drop(index_lock);
+    assert!(other.process_data(false).is_ok());
+
+    TestHelper::clean(&config);
+}
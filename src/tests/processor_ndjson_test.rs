@@ -0,0 +1,95 @@
+use rust_decimal::Decimal;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_reader::{InputFormat, ParseMode};
+
+// ------------ //
+// input ndjson //
+// ------------ //
+
+// {"type":"deposit","client":50,"tx":1,"amount":"10"}
+// {"type":"withdrawal","client":50,"tx":2,"amount":2.5}
+//
+// {"type":"deposit","client":50,"tx":"x","amount":"5"}
+// not json
+// {"type":"deposit","client":51,"tx":3,"amount":"1"}
+// {"type":"dispute","client":51,"tx":3}
+
+#[test]
+fn process_ndjson_strict_test() {
+    let config = TestHelper::config("process_ndjson_strict_test");
+    let result = Processor::new("src/tests/csv/events.ndjson", &config);
+    assert!(result.is_ok());
+
+    // same validation as csv, the run stops at the first malformed line
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_err());
+
+    let err = result.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::PARSE);
+    assert!(err.context().message.starts_with("line 4 | byte 107"));
+    assert!(Account::find(50, &config).is_none());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_ndjson_lenient_test() {
+    let config = TestHelper::config("process_ndjson_lenient_test");
+    let result = Processor::new("src/tests/csv/events.ndjson", &config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    p.set_parse_mode(ParseMode::LENIENT);
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let reports = result.unwrap();
+    assert_eq!(reports[0].rows, 4);
+
+    let quarantined = &reports[0].quarantined;
+    assert_eq!(quarantined.len(), 2);
+    assert_eq!(quarantined[0].line, 4);
+    assert_eq!(quarantined[0].byte, 107);
+    assert_eq!(
+        quarantined[0].record,
+        r#"{"type":"deposit","client":50,"tx":"x","amount":"5"}"#
+    );
+    assert_eq!(quarantined[1].line, 5);
+    assert_eq!(quarantined[1].record, "not json");
+    assert!(quarantined[1].error.starts_with("invalid json"));
+
+    let account = Account::find(50, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(75, 1));
+    assert_eq!(account.total, Decimal::new(75, 1));
+
+    let account = Account::find(51, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(0, 0));
+    assert_eq!(account.held, Decimal::new(1, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_ndjson_input_format_test() {
+    // {"type":"deposit","client":52,"tx":1,"amount":"4"}
+    // {"type":"withdrawal","client":52,"tx":2,"amount":"1.5"}
+
+    let config = TestHelper::config("process_ndjson_input_format_test");
+    let result = Processor::new("src/tests/csv/events.txt", &config);
+    assert!(result.is_ok());
+
+    // the extension says csv, the input format overrides it
+    let mut p = result.unwrap();
+    p.set_input_format(Some(InputFormat::NDJSON));
+    assert!(p.process_data(false).is_ok());
+
+    let account = Account::find(52, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(25, 1));
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,110 @@
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::output::OutputFormat;
+use crate::models::processor::Processor;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount
+// deposit,49,1,3
+// deposit,47,2,1.5
+// deposit,48,3,2
+// withdrawal,49,4,1
+
+#[test]
+fn process_output_test() {
+    let config = TestHelper::config("process_output_test");
+    let result = Processor::new("src/tests/csv/output.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let output = [config.data_dir(), "/accounts.csv"].join("");
+    assert!(p.write_accounts(Some(&output), OutputFormat::CSV).is_ok());
+
+    // header first, then sorted by client id
+    let string = fs::read_to_string(&output).unwrap();
+    assert_eq!(
+        string,
+        "client,available,held,total,locked,state\n\
+         47,1.5000,0.0000,1.5000,false,active\n\
+         48,2.0000,0.0000,2.0000,false,active\n\
+         49,2.0000,0.0000,2.0000,false,active\n"
+    );
+
+    // the same data gives the same output
+    assert!(p.write_accounts(Some(&output), OutputFormat::CSV).is_ok());
+    assert_eq!(fs::read_to_string(&output).unwrap(), string);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_output_json_test() {
+    let config = TestHelper::config("process_output_json_test");
+    let result = Processor::new("src/tests/csv/output.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let output = [config.data_dir(), "/accounts.json"].join("");
+    assert!(p.write_accounts(Some(&output), OutputFormat::JSON).is_ok());
+
+    // decimals are strings at 4 decimal places
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    let accounts = json.as_array().unwrap();
+    assert_eq!(accounts.len(), 3);
+    assert_eq!(accounts[0]["client"], 47);
+    assert_eq!(accounts[0]["available"], "1.5000");
+    assert_eq!(accounts[0]["held"], "0.0000");
+    assert_eq!(accounts[0]["total"], "1.5000");
+    assert_eq!(accounts[0]["locked"], false);
+    assert_eq!(accounts[0]["state"], "active");
+    assert_eq!(accounts[2]["client"], 49);
+
+    let output = [config.data_dir(), "/accounts.ndjson"].join("");
+    assert!(p
+        .write_accounts(Some(&output), OutputFormat::NDJSON)
+        .is_ok());
+
+    let string = fs::read_to_string(&output).unwrap();
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[1],
+        r#"{"client":48,"available":"2.0000","held":"0.0000","total":"2.0000","locked":false,"state":"active"}"#
+    );
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_output_stray_file_test() {
+    let config = TestHelper::config("process_output_stray_file_test");
+    let p = Processor::new("src/tests/csv/output.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // files in the account dir that aren't named after a client are left out
+    fs::write([&config.account_dir(), "/notes.txt"].join(""), "notes").unwrap();
+    fs::write([&config.account_dir(), "/47.csv~"].join(""), "").unwrap();
+
+    let output = [config.data_dir(), "/accounts.csv"].join("");
+    assert!(p.write_accounts(Some(&output), OutputFormat::CSV).is_ok());
+    let string = fs::read_to_string(&output).unwrap();
+    assert_eq!(string.lines().count(), 4);
+
+    // + listed for the cli to warn about
+    let unlisted = Account::unlisted(&config).unwrap();
+    assert_eq!(unlisted.len(), 2);
+    assert!(unlisted.iter().all(|e| e.kind() == ErrorKind::PARSE));
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,74 @@
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::Path;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_reader::{ParseMode, TxReader};
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount
+// deposit,44,1,10
+// deposit,44,x,5
+// exchange,44,3,1
+// deposit,44
+// withdrawal,44,4,2
+
+#[test]
+fn process_parse_strict_test() {
+    let config = TestHelper::config("process_parse_strict_test");
+    let result = Processor::new("src/tests/csv/malformed.csv", &config);
+    assert!(result.is_ok());
+
+    // the run stops at the first malformed row
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_err());
+
+    let err = result.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::PARSE);
+    assert!(err.context().message.starts_with("line 3 | byte 38"));
+    assert!(err.to_string().contains("line 3 | byte 38"));
+
+    assert!(!Path::new(&[&config.account_dir(), "/44.csv"].join("")).exists());
+    assert!(!Path::new(&TxReader::quarantine_path(p.csv_summary_dir())).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_parse_lenient_test() {
+    let config = TestHelper::config("process_parse_lenient_test");
+    let result = Processor::new("src/tests/csv/malformed.csv", &config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    p.set_parse_mode(ParseMode::LENIENT);
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let reports = result.unwrap();
+    assert_eq!(reports[0].rows, 2);
+    assert_eq!(reports[0].quarantined.len(), 3);
+
+    // the valid rows are applied
+    let account = Account::new(44, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(8, 0));
+    assert_eq!(account.total, Decimal::new(8, 0));
+
+    let string = fs::read_to_string(TxReader::quarantine_path(p.csv_summary_dir())).unwrap();
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "file,line,byte,record,error");
+    assert!(lines[1].starts_with("src/tests/csv/malformed.csv,3,38,\"deposit,44,x,5\","));
+    assert!(lines[2].starts_with("src/tests/csv/malformed.csv,4,53,\"exchange,44,3,1\","));
+    assert!(lines[2].ends_with("invalid transaction record type"));
+    assert!(lines[3].starts_with("src/tests/csv/malformed.csv,5,69,\"deposit,44\","));
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,94 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::{Account, AccountState};
+use crate::models::account_detail::AccountDetail;
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+use crate::models::tx_rejection::TxRejectionLog;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount,to
+// deposit,80,1,100,
+// dispute,80,1,20,
+// dispute,80,1,30,
+// dispute,80,1,60,
+// resolve,80,1,25,
+// dispute,80,1,,
+// chargeback,80,1,80,
+// chargeback,80,1,15,
+// deposit,81,2,50,
+// withdrawal,81,3,30,
+// dispute,81,3,10,
+// resolve,81,4,,
+// chargeback,81,3,,
+// deposit,82,5,10,
+// transfer,82,6,10,83
+// dispute,82,6,4,
+// resolve,82,6,1,
+// chargeback,82,6,,
+
+#[test]
+fn process_partial_test() {
+    let config = TestHelper::config("process_partial_test");
+    let result = Processor::new("src/tests/csv/partial.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // 25 resolved, 15 charged back + 60 still under dispute
+    let account = Account::find(80, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(25, 0));
+    assert_eq!(account.held, Decimal::new(60, 0));
+    assert_eq!(account.total, Decimal::new(85, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    // only the disputed part of the withdrawal is refunded
+    let account = Account::find(81, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(30, 0));
+    assert_eq!(account.total, Decimal::new(30, 0));
+
+    let account = Account::find(82, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(3, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    let account = Account::find(83, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(7, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(7, 0));
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/partial.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",5,dispute,80,1,60.0000,exceeds_undisputed"].join(""),
+        [file, ",8,chargeback,80,1,80.0000,exceeds_disputed"].join(""),
+        [file, ",13,resolve,81,4,0.0000,not_disputed"].join(""),
+    ];
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines, expected);
+
+    // the statement + open disputes follow the parts
+    let lines = Statement::lines(80, &config).unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].state, "chargeback");
+    assert_eq!(lines[0].available, "25.0000");
+    assert_eq!(lines[0].held, "60.0000");
+    assert_eq!(lines[0].total, "85.0000");
+
+    let lines = Statement::lines(83, &config).unwrap();
+    assert_eq!(lines[0].available, "7.0000");
+    assert_eq!(lines[0].total, "7.0000");
+
+    let detail = AccountDetail::load(80, &config, true, false, false).unwrap();
+    let disputes = detail.disputes.unwrap();
+    assert_eq!(disputes.len(), 1);
+    assert_eq!(disputes[0].amount, "60.0000");
+
+    TestHelper::clean(&config);
+}
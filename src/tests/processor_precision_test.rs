@@ -0,0 +1,107 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_reader::{ParseMode, PrecisionPolicy};
+use crate::models::tx_rejection::TxRejectionLog;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount
+// deposit,60,1,1.00005
+// deposit,60,2,1.00015
+// withdrawal,60,3,0.00001
+// deposit,60,4,-1
+// deposit,60,5,0
+
+fn process(test_name: &str, precision: PrecisionPolicy) -> (usize, Vec<String>, Option<Account>) {
+    let config = TestHelper::config(test_name);
+    let mut p = Processor::new("src/tests/csv/precision.csv", &config).unwrap();
+    p.set_parse_mode(ParseMode::LENIENT);
+    p.set_precision(precision);
+
+    let reports = p.process_data(false).unwrap();
+    let account = Account::find(60, &config);
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let rejected = string.lines().skip(1).map(|l| l.to_string()).collect();
+    TestHelper::clean(&config);
+    (reports[0].quarantined.len(), rejected, account)
+}
+
+#[test]
+fn process_precision_round_test() {
+    // half to even. the withdrawal rounds to zero + is rejected with the negative + zero deposits.
+    let (quarantined, rejected, account) =
+        process("process_precision_round_test", PrecisionPolicy::ROUND);
+    assert_eq!(quarantined, 0);
+    let file = "src/tests/csv/precision.csv";
+    let expected = [
+        [file, ",4,withdrawal,60,3,0.0000,non_positive_amount"].join(""),
+        [file, ",5,deposit,60,4,-1.0000,non_positive_amount"].join(""),
+        [file, ",6,deposit,60,5,0.0000,non_positive_amount"].join(""),
+    ];
+    assert_eq!(rejected, expected);
+    assert_eq!(account.unwrap().total, Decimal::new(20002, 4));
+}
+
+#[test]
+fn process_precision_truncate_test() {
+    let (quarantined, rejected, account) =
+        process("process_precision_truncate_test", PrecisionPolicy::TRUNCATE);
+    assert_eq!(quarantined, 0);
+    assert_eq!(rejected.len(), 3);
+    assert_eq!(account.unwrap().total, Decimal::new(20001, 4));
+}
+
+#[test]
+fn process_precision_strict_test() {
+    // strict mode reports the non positive amounts instead of stopping at them
+    let config = TestHelper::config("process_precision_strict_test");
+    let p = Processor::new("src/tests/csv/precision.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert!(reports[0].quarantined.is_empty());
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    assert_eq!(string.lines().count(), 4);
+    assert!(string
+        .lines()
+        .all(|l| l.starts_with("file,") || l.ends_with(",non_positive_amount")));
+
+    let account = Account::find(60, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(20002, 4));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_precision_reject_test() {
+    // the amounts with too many decimal places are rejected, not malformed
+    let (quarantined, rejected, account) =
+        process("process_precision_reject_test", PrecisionPolicy::REJECT);
+    assert_eq!(quarantined, 0);
+    let file = "src/tests/csv/precision.csv";
+    let expected = [
+        [file, ",2,deposit,60,1,1.0000,too_precise"].join(""),
+        [file, ",3,deposit,60,2,1.0001,too_precise"].join(""),
+        [file, ",4,withdrawal,60,3,0.0000,too_precise"].join(""),
+        [file, ",5,deposit,60,4,-1.0000,non_positive_amount"].join(""),
+        [file, ",6,deposit,60,5,0.0000,non_positive_amount"].join(""),
+    ];
+    assert_eq!(rejected, expected);
+    assert!(account.is_none());
+
+    // strict mode reports them instead of stopping at them
+    let config = TestHelper::config("process_precision_reject_strict_test");
+    let mut p = Processor::new("src/tests/csv/precision.csv", &config).unwrap();
+    p.set_precision(PrecisionPolicy::REJECT);
+    assert!(p.process_data(false).is_ok());
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    assert_eq!(string.lines().count(), 6);
+
+    TestHelper::clean(&config);
+}
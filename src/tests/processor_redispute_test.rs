@@ -0,0 +1,176 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::config::Config;
+use crate::common::error::ErrorKind;
+use crate::models::account::{Account, AccountState};
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+use crate::models::tx_dispute::{DisputeLog, DisputePolicy};
+use crate::models::tx_history::TxHistory;
+use crate::models::tx_record::TxRecordType;
+use crate::models::tx_rejection::TxRejectionLog;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount,to
+// deposit,84,1,100,
+// dispute,84,1,,
+// resolve,84,1,,
+// dispute,84,1,40,
+// resolve,84,1,,
+// dispute,84,1,,
+// deposit,85,2,50,
+// dispute,85,2,20,
+// resolve,85,2,,
+// dispute,85,2,10,
+// dispute,85,2,30,
+// dispute,85,2,20,
+// chargeback,85,2,,
+
+fn process(policy: Option<DisputePolicy>, config: &Config) -> Vec<String> {
+    let result = Processor::new("src/tests/csv/redispute.csv", config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    if let Some(policy) = policy {
+        p.set_dispute_policy(policy);
+    }
+    assert!(p.process_data(false).is_ok());
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    string.lines().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn process_redispute_test() {
+    let config = TestHelper::config("process_redispute_test");
+    let policy = DisputePolicy::new(1, vec![TxRecordType::RESOLVE]);
+    let lines = process(Some(policy), &config);
+
+    // once disputed again the limit is reached, a tx still under dispute can't take resolved parts
+    let file = "src/tests/csv/redispute.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",7,dispute,84,1,0.0000,redispute_refused"].join(""),
+        [file, ",12,dispute,85,2,30.0000,redispute_refused"].join(""),
+    ];
+    assert_eq!(lines, expected);
+
+    let account = Account::find(84, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(100, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.state, AccountState::ACTIVE);
+
+    let account = Account::find(85, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(20, 0));
+    assert_eq!(account.total, Decimal::new(20, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    // every event is kept, the round counts the re-disputes
+    let lines = DisputeLog::lines(84, &config).unwrap();
+    let v: Vec<(u64, usize, &str, &str, u32)> = lines
+        .iter()
+        .map(|l| (l.tx, l.seq, l.state.as_str(), l.amount.as_str(), l.round))
+        .collect();
+    assert_eq!(
+        v,
+        vec![
+            (1, 1, "dispute", "100.0000", 0),
+            (1, 2, "resolve", "100.0000", 0),
+            (1, 3, "dispute", "40.0000", 1),
+            (1, 4, "resolve", "40.0000", 1),
+        ]
+    );
+    assert_eq!(DisputeLog::lines(85, &config).unwrap().len(), 5);
+
+    // the events are not statement lines
+    let lines = Statement::lines(84, &config).unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].state, "resolve");
+    assert_eq!(lines[0].available, "100.0000");
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_redispute_default_test() {
+    let config = TestHelper::config("process_redispute_default_test");
+    let lines = process(None, &config);
+
+    // without a policy a resolved part is settled for good
+    let file = "src/tests/csv/redispute.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",5,dispute,84,1,40.0000,already_disputed"].join(""),
+        [file, ",6,resolve,84,1,0.0000,not_disputed"].join(""),
+        [file, ",7,dispute,84,1,0.0000,already_disputed"].join(""),
+        [file, ",12,dispute,85,2,30.0000,exceeds_undisputed"].join(""),
+    ];
+    assert_eq!(lines, expected);
+
+    let lines = DisputeLog::lines(84, &config).unwrap();
+    assert_eq!(lines.len(), 2);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_redispute_corrupt_test() {
+    let config = TestHelper::config("process_redispute_corrupt_test");
+    process(None, &config);
+
+    {
+        let db = TxHistory::open_committed(&84, &config).unwrap();
+        db.insert(b"e_1", "e_1,dispute,x".as_bytes()).unwrap();
+        db.insert(b"c_1", "c_1,deposit".as_bytes()).unwrap();
+        db.flush().unwrap();
+    }
+
+    // a corrupt event or conflict is an error, not a panic
+    let err = DisputeLog::lines(84, &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+    let err = Statement::lines(84, &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+
+    // nor is it applied to
+    let mut p = Processor::new("src/tests/csv/redispute.csv", &config).unwrap();
+    p.set_force(true);
+    let err = p.process_data(false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+
+    let account = Account::find(84, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(100, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_redispute_corrupt_row_test() {
+    let config = TestHelper::config("process_redispute_corrupt_row_test");
+    process(None, &config);
+
+    {
+        let db = TxHistory::open_committed(&84, &config).unwrap();
+        db.insert(b"1", "deposit,84,x".as_bytes()).unwrap();
+        db.flush().unwrap();
+    }
+
+    // a corrupt row is an error, not a panic or an unknown tx
+    let err = Statement::lines(84, &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+
+    let mut p = Processor::new("src/tests/csv/redispute.csv", &config).unwrap();
+    p.set_force(true);
+    let err = p.process_data(false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::STORAGE);
+
+    let account = Account::find(84, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(100, 0));
+
+    TestHelper::clean(&config);
+}
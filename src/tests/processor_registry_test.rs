@@ -0,0 +1,168 @@
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::Path;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_registry::TxRegistry;
+
+// --------- //
+// input csv //
+// --------- //
+
+// registry.csv
+// type,client,tx,amount
+// deposit,39,1,10
+
+// registry/registry.csv
+// type,client,tx,amount
+// deposit,39,2,5
+
+#[test]
+fn process_registry_skip_test() {
+    let config = TestHelper::config("process_registry_skip_test");
+    for _ in 0..2 {
+        let result = Processor::new("src/tests/csv/registry.csv", &config);
+        assert!(result.is_ok());
+
+        let p = result.unwrap();
+        let result = p.process_data(false);
+        assert!(result.is_ok());
+    }
+
+    // the second run is skipped, the deposit is only applied once
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(10, 0));
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    // the same content under another name is skipped as well
+    let copy_path = [config.data_dir(), "/registry_copy.csv"].join("");
+    assert!(fs::copy("src/tests/csv/registry.csv", &copy_path).is_ok());
+
+    let p = Processor::new(&copy_path, &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].skipped);
+    assert_eq!(
+        reports[0].to_string(),
+        [&copy_path, ",0,0,0,skipped"].join("")
+    );
+
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    // + compressed, since the content is hashed decompressed
+    let zst_path = [config.data_dir(), "/registry_zst.csv.zst"].join("");
+    let data = fs::read("src/tests/csv/registry.csv").unwrap();
+    fs::write(&zst_path, zstd::encode_all(data.as_slice(), 0).unwrap()).unwrap();
+
+    let p = Processor::new(&zst_path, &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert!(reports[0].skipped);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_registry_force_test() {
+    let config = TestHelper::config("process_registry_force_test");
+    let p = Processor::new("src/tests/csv/registry.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let mut p = Processor::new("src/tests/csv/registry.csv", &config).unwrap();
+    p.set_force(true);
+    let reports = p.process_data(false).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(!reports[0].skipped);
+    assert_eq!(reports[0].rows, 1);
+
+    // the input is read again, but tx 1 is already used
+    assert_eq!(reports[0].duplicates.len(), 1);
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_registry_name_test() {
+    let config = TestHelper::config("process_registry_name_test");
+    let p = Processor::new("src/tests/csv/registry.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // same name, other content
+    let p = Processor::new("src/tests/csv/registry/registry.csv", &config).unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_err());
+
+    let err = result.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::RULE);
+    assert_eq!(err.context().method, "registry_inputs");
+
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    // the failed run holds its summary dir until it is dropped
+    drop(p);
+    let mut p = Processor::new("src/tests/csv/registry/registry.csv", &config).unwrap();
+    p.set_force(true);
+    assert!(p.process_data(false).is_ok());
+
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(15, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_registry_failed_test() {
+    let config = TestHelper::config("process_registry_failed_test");
+
+    // stage 2 fails, the input is not registered
+    let account_path = [&config.account_dir(), "/39.csv"].join("");
+    assert!(fs::create_dir_all(&account_path).is_ok());
+
+    let p = Processor::new("src/tests/csv/registry.csv", &config).unwrap();
+    assert!(p.process_data(true).is_err());
+    assert!(fs::remove_dir_all(&account_path).is_ok());
+
+    let p = Processor::new("src/tests/csv/registry.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert!(!reports[0].skipped);
+
+    let account = Account::new(39, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_registry_commit_again_test() {
+    let config = TestHelper::config("process_registry_commit_again_test");
+    let summary_dir = [&config.summary_dir(), "/registry_commit"].join("");
+    let entry = TxRegistry::entry("src/tests/csv/registry.csv").unwrap();
+
+    assert!(TxRegistry::stage(&summary_dir, std::slice::from_ref(&entry)).is_ok());
+    assert!(TxRegistry::commit(&summary_dir, &config).is_ok());
+
+    // a crash after the append, before the staged file is removed, commits it again on recovery
+    let mark_path = [&summary_dir, "/registry/inputs.csv.len"].join("");
+    assert!(TxRegistry::stage(&summary_dir, std::slice::from_ref(&entry)).is_ok());
+    assert!(fs::write(&mark_path, "0").is_ok());
+    assert!(TxRegistry::commit(&summary_dir, &config).is_ok());
+    assert!(!Path::new(&mark_path).exists());
+
+    let registry_path = [&config.registry_dir(), "/inputs.csv"].join("");
+    let string = fs::read_to_string(&registry_path).unwrap();
+    assert_eq!(string.lines().count(), 1);
+
+    // the next run appends after it
+    assert!(TxRegistry::stage(&summary_dir, std::slice::from_ref(&entry)).is_ok());
+    assert!(TxRegistry::commit(&summary_dir, &config).is_ok());
+    let string = fs::read_to_string(&registry_path).unwrap();
+    assert_eq!(string.lines().count(), 2);
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,84 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_rejection::TxRejectionLog;
+
+#[test]
+fn process_rejection_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,42,1,10
+    // withdrawal,42,2,50
+    // dispute,42,99,
+    // resolve,42,1,
+    // dispute,42,1,
+    // dispute,42,1,
+    // chargeback,42,1,
+    // deposit,42,3,5
+    // deposit,43,1,5
+
+    let config = TestHelper::config("process_rejection_test");
+    let result = Processor::new("src/tests/csv/rejection.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let account = Account::new(42, &config.account_dir(), &config);
+    assert_eq!(account.total, Decimal::new(0, 0));
+    assert!(account.locked);
+
+    // every ignored row is listed in input order with its reason
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/rejection.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",3,withdrawal,42,2,50.0000,insufficient_funds"].join(""),
+        [file, ",4,dispute,42,99,0.0000,unknown_tx"].join(""),
+        [file, ",5,resolve,42,1,0.0000,not_disputed"].join(""),
+        [file, ",7,dispute,42,1,0.0000,already_disputed"].join(""),
+        [file, ",9,deposit,42,3,5.0000,account_locked"].join(""),
+        [file, ",10,deposit,43,1,5.0000,duplicate_tx"].join(""),
+    ];
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines, expected);
+
+    let string = fs::read_to_string(TxRejectionLog::json_path(p.csv_summary_dir())).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&string).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 6);
+    assert_eq!(json[0]["line"], 3);
+    assert_eq!(json[0]["reason"], "insufficient_funds");
+    assert_eq!(json[5]["client"], 43);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_rejection_empty_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,38,1,10
+
+    let config = TestHelper::config("process_rejection_empty_test");
+    let p = Processor::new("src/tests/csv/batch/batch_a.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // the outputs are written even if nothing was rejected
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    assert_eq!(string, "file,line,type,client,tx,amount,reason\n");
+
+    let string = fs::read_to_string(TxRejectionLog::json_path(p.csv_summary_dir())).unwrap();
+    assert_eq!(string, "[]");
+
+    TestHelper::clean(&config);
+}
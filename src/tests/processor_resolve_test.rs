@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -21,22 +20,23 @@ fn process_resolve_base_test() {
     // deposit,9,6,12
     // resolve,9,1
 
+    let config = TestHelper::config("process_resolve_base_test");
     let client_id = 9;
-    let result = Processor::new("src/tests/csv/resolve_base.csv");
+    let result = Processor::new("src/tests/csv/resolve_base.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -54,22 +54,23 @@ fn process_resolve_tx_dne_test() {
     // resolve,10,50
     // deposit,10,6,12
 
+    let config = TestHelper::config("process_resolve_tx_dne_test");
     let client_id = 10;
-    let result = Processor::new("src/tests/csv/resolve_tx_dne.csv");
+    let result = Processor::new("src/tests/csv/resolve_tx_dne.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -90,22 +91,26 @@ fn process_resolve_dispute_on_resolved_account_test() {
     // deposit,11,7,1
     // dispute,11,1
 
+    let config = TestHelper::config("process_resolve_dispute_on_resolved_account_test");
     let client_id = 11;
-    let result = Processor::new("src/tests/csv/resolve_dispute_on_resolved_account.csv");
+    let result = Processor::new(
+        "src/tests/csv/resolve_dispute_on_resolved_account.csv",
+        &config,
+    );
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(35, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(35, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -128,8 +133,9 @@ fn process_resolve_multi_test() {
     // resolve,25,1
     // deposit,25,6,12
 
+    let config = TestHelper::config("process_resolve_multi_test");
     let client_id = 25;
-    let result = Processor::new("src/tests/csv/resolve_multi_0.csv");
+    let result = Processor::new("src/tests/csv/resolve_multi_0.csv", &config);
     assert!(result.is_ok());
 
     let mut p = result.unwrap();
@@ -148,12 +154,12 @@ fn process_resolve_multi_test() {
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(34, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(34, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }
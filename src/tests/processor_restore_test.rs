@@ -0,0 +1,206 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::thread;
+use std::time::Duration;
+
+use super::helpers::helper::TestHelper;
+use crate::common::config::Config;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::restore::Restore;
+use crate::models::statement::Statement;
+use crate::models::tx_index::TxIndex;
+use crate::models::tx_input::TxInputReport;
+
+// --------- //
+// input csv //
+// --------- //
+
+// restore_base.csv
+// type,client,tx,amount
+// deposit,65,1,10
+// deposit,66,2,5
+
+// restore_bad.csv
+// type,client,tx,amount
+// deposit,65,3,100
+// dispute,65,1,
+// deposit,67,4,1
+
+// restore_fixed.csv
+// type,client,tx,amount
+// deposit,65,3,1
+
+// restore_transfer_base.csv
+// type,client,tx,amount,to
+// deposit,94,1,10,
+// deposit,95,2,1,
+
+// restore_transfer_bad.csv
+// type,client,tx,amount,to
+// transfer,94,3,4,95
+// deposit,94,4,1,
+
+// restore_transfer_dispute.csv
+// type,client,tx,amount,to
+// dispute,94,3,,
+
+fn process(path: &str, config: &Config) -> TxInputReport {
+    let result = Processor::new(path, config);
+    assert!(result.is_ok());
+    let result = result.unwrap().process_data(false);
+    assert!(result.is_ok());
+    result.unwrap().pop().unwrap()
+}
+
+// a time between two publishes
+fn mark() -> i64 {
+    thread::sleep(Duration::from_millis(5));
+    let millis = Utc::now().timestamp_millis();
+    thread::sleep(Duration::from_millis(5));
+    millis
+}
+
+#[test]
+fn process_restore_test() {
+    let config = TestHelper::config("process_restore_test");
+    process("src/tests/csv/restore_base.csv", &config);
+    let before_bad = mark();
+    process("src/tests/csv/restore_bad.csv", &config);
+
+    let account = Account::find(65, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(110, 0));
+    assert_eq!(account.held, Decimal::new(10, 0));
+
+    // only the given client is restored
+    let before_restore = mark();
+    let result = Restore::run(before_bad, Some(65), &config);
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![65]);
+
+    let account = Account::find(65, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(10, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(10, 0));
+
+    // the history is restored with the account
+    let lines = Statement::lines(65, &config).unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].state, "none");
+    assert!(Account::find(67, &config).is_some());
+
+    // the registry keeps the undone run, its other rows would be applied again to the clients that were not restored
+    assert!(process("src/tests/csv/restore_bad.csv", &config).skipped);
+
+    // clients without a publish after the time are left alone,
+    // a client with no account at the time has it removed.
+    // restoring 65 again to the same time gives the same account.
+    let result = Restore::run(before_bad, None, &config);
+    assert_eq!(result.unwrap(), vec![65, 67]);
+    assert!(Account::find(67, &config).is_none());
+    assert_eq!(
+        Account::find(65, &config).unwrap().total,
+        Decimal::new(10, 0)
+    );
+    assert_eq!(
+        Account::find(66, &config).unwrap().total,
+        Decimal::new(5, 0)
+    );
+
+    // a restore can be undone by restoring to before it
+    let result = Restore::run(before_restore, Some(65), &config);
+    assert_eq!(result.unwrap(), vec![65]);
+    let account = Account::find(65, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(110, 0));
+    assert_eq!(Statement::lines(65, &config).unwrap().len(), 2);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_restore_reapply_test() {
+    let config = TestHelper::config("process_restore_reapply_test");
+    process("src/tests/csv/restore_base.csv", &config);
+    let before_bad = mark();
+    process("src/tests/csv/restore_bad.csv", &config);
+
+    // the stores aren't rewritten while a run holds the tx index
+    let index_lock = TxIndex::acquire(&config).unwrap();
+    let result = Restore::run(before_bad, None, &config);
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::CONCURRENCY);
+    drop(index_lock);
+
+    assert!(Restore::run(before_bad, None, &config).is_ok());
+
+    // the tx ids of the undone rows are free again
+    let report = process("src/tests/csv/restore_fixed.csv", &config);
+    assert!(report.duplicates.is_empty());
+    assert_eq!(
+        Account::find(65, &config).unwrap().total,
+        Decimal::new(11, 0)
+    );
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_restore_transfer_test() {
+    let config = TestHelper::config("process_restore_transfer_test");
+    process("src/tests/csv/restore_transfer_base.csv", &config);
+    let before_bad = mark();
+    process("src/tests/csv/restore_transfer_bad.csv", &config);
+    let before_restore = mark();
+
+    // the recipient of the transfer is restored with the sender
+    let result = Restore::run(before_bad, Some(94), &config);
+    assert_eq!(result.unwrap(), vec![94, 95]);
+    assert_eq!(
+        Account::find(94, &config).unwrap().total,
+        Decimal::new(10, 0)
+    );
+    assert_eq!(
+        Account::find(95, &config).unwrap().total,
+        Decimal::new(1, 0)
+    );
+
+    // + the sender with the recipient, the transfer is claimed back as a transfer
+    let result = Restore::run(before_restore, Some(95), &config);
+    assert_eq!(result.unwrap(), vec![94, 95]);
+    process("src/tests/csv/restore_transfer_dispute.csv", &config);
+
+    let account = Account::find(94, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(7, 0));
+    let account = Account::find(95, &config).unwrap();
+    assert_eq!(account.held, Decimal::new(4, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    // the undone runs are no longer registered, so the file can be applied again
+    assert!(Restore::run(before_bad, None, &config).is_ok());
+    let report = process("src/tests/csv/restore_transfer_bad.csv", &config);
+    assert!(!report.skipped);
+    assert!(report.duplicates.is_empty());
+    assert_eq!(
+        Account::find(94, &config).unwrap().total,
+        Decimal::new(7, 0)
+    );
+    assert_eq!(
+        Account::find(95, &config).unwrap().total,
+        Decimal::new(5, 0)
+    );
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_restore_time_test() {
+    assert_eq!(Restore::parse_time("1646136000000").unwrap(), 1646136000000);
+    assert_eq!(
+        Restore::parse_time("2022-03-01T12:00:00Z").unwrap(),
+        1646136000000
+    );
+
+    let result = Restore::parse_time("yesterday");
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::PARSE);
+}
@@ -0,0 +1,181 @@
+use rust_decimal::Decimal;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_history::TxHistory;
+
+#[test]
+fn process_rollback_update_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,29,1,5
+    // deposit,30,2,5
+
+    // type,client,tx,amount
+    // deposit,29,3,10
+    // dispute,29,1
+    // deposit,30,4,1
+
+    let config = TestHelper::config("process_rollback_update_test");
+    let result = Processor::new("src/tests/csv/rollback_base.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    // force the publish of client 30 to fail in stage 2
+    let account_30 = [&config.account_dir(), "/30.csv"].join("");
+    let _ = fs::remove_file(&account_30);
+    assert!(fs::create_dir_all(&account_30).is_ok());
+
+    let result = Processor::new("src/tests/csv/rollback_update.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_err());
+
+    // the account file of client 30 can't be replaced
+    let err = result.err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::IO);
+    assert!(err.source().is_some());
+
+    // account of client 29 is untouched
+    let account = Account::new(29, &config.account_dir(), &config);
+    assert_eq!(account.client_id, 29);
+    assert_eq!(account.available, Decimal::new(5, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+    assert!(!account.locked);
+
+    // transaction history of client 29 is untouched
+    {
+        let db = TxHistory::open_committed(&29, &config).unwrap();
+        assert!(db.contains_key(b"1").unwrap());
+        assert!(!db.contains_key(b"3").unwrap());
+        assert!(!db.contains_key(b"c_1").unwrap());
+    }
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_rollback_publish_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,32,1,5
+
+    // type,client,tx,amount
+    // deposit,32,2,10
+    // dispute,32,1
+
+    let config = TestHelper::config("process_rollback_publish_test");
+    let client_id = 32;
+    let result = Processor::new("src/tests/csv/rollback_publish_base.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    let result = Processor::new("src/tests/csv/rollback_publish_update.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.cluster_transactions();
+    assert!(result.is_ok());
+
+    // publish the staged data by hand
+    let summary_dir = [&config.summary_dir(), "/rollback_publish_update"].join("");
+    let result = Account::publish(&summary_dir, "32.csv", &config);
+    assert!(result.is_ok());
+
+    let account = Account::new(client_id, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(10, 0));
+    assert_eq!(account.held, Decimal::new(5, 0));
+    assert_eq!(account.total, Decimal::new(15, 0));
+
+    // roll it back
+    let result = Account::rollback(&summary_dir, "32.csv", &config);
+    assert!(result.is_ok());
+
+    let account = Account::new(client_id, &config.account_dir(), &config);
+    assert_eq!(account.available, Decimal::new(5, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    {
+        let db = TxHistory::open_committed(&32, &config).unwrap();
+        assert!(db.contains_key(b"1").unwrap());
+        assert!(!db.contains_key(b"2").unwrap());
+        assert!(!db.contains_key(b"c_1").unwrap());
+    }
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_rollback_invalid_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,31,1,5
+    // deposit,31,2,x
+
+    let config = TestHelper::config("process_rollback_invalid_test");
+    let result = Processor::new("src/tests/csv/rollback_invalid.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    let result = p.process_data(false);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::PARSE);
+
+    // nothing was written to the official data
+    assert!(!Path::new(&[&config.account_dir(), "/31.csv"].join("")).exists());
+    assert!(!Path::new(&[&config.transaction_dir(), "/31_db"].join("")).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_rollback_history_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,86,1,5
+
+    let config = TestHelper::config("process_rollback_history_test");
+    let result = Processor::new("src/tests/csv/rollback_history.csv", &config);
+    assert!(result.is_ok());
+
+    // a file where the staged history of client 86 goes can't be opened as a db
+    let p = result.unwrap();
+    let staged_path = TxHistory::staged_path(&86, p.csv_summary_dir());
+    assert!(fs::create_dir_all(Path::new(&staged_path).parent().unwrap()).is_ok());
+    assert!(fs::write(&staged_path, "").is_ok());
+
+    // the worker reports the error instead of panicking
+    let result = p.process_data(false);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::STORAGE);
+    assert!(Account::find(86, &config).is_none());
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,201 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::config::Config;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+use crate::models::tx_index::TxIndex;
+use crate::models::tx_record::ClientId;
+use crate::models::tx_rejection::TxRejectionLog;
+use crate::models::tx_schedule::TxSchedule;
+
+// ------------- //
+// schedule file //
+// ------------- //
+
+// # monthly fees + interest
+// fee,flat,2.5,,1000
+// interest,rate,0.01,100
+// fee,flat,5,,3
+
+// --------- //
+// input csv //
+// --------- //
+
+// schedule_base.csv
+// type,client,tx,amount
+// deposit,76,1,200
+// deposit,77,2,50
+// deposit,78,3,2
+
+// schedule_run.csv
+// type,client,tx,amount
+// deposit,79,4,5
+
+// schedule_next.csv
+// type,client,tx,amount
+// deposit,79,5,10
+
+// schedule_fee.csv
+// type,client,tx,amount
+// deposit,79,6,10
+// fee,79,7,1
+
+const GENERATED_TX_BASE: u64 = 1 << 62;
+
+fn process(path: &str, schedule: Option<TxSchedule>, config: &Config) -> Processor {
+    let result = Processor::new(path, config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    p.set_schedule(schedule);
+    assert!(p.process_data(false).is_ok());
+    p
+}
+
+// the clients the schedule has to go back to + whether a generated id is claimed, read from the committed index
+fn index(tx_id: u64, config: &Config) -> (Vec<ClientId>, bool) {
+    let dir = [config.data_dir(), "/index_view"].join("");
+    let result = {
+        let tx_index = TxIndex::new(&dir, config).unwrap();
+        let due = tx_index.schedule_due().unwrap();
+        (due, !tx_index.claim(&tx_id, &0).unwrap())
+    };
+    fs::remove_dir_all(&dir).unwrap();
+    result
+}
+
+#[test]
+fn process_schedule_test() {
+    let config = TestHelper::config("process_schedule_test");
+    let mut schedule = TxSchedule::from_file("src/tests/csv/schedule.txt").unwrap();
+    schedule.set_period("2022-03");
+
+    // no fees without a schedule
+    process("src/tests/csv/schedule_base.csv", None, &config);
+    let account = Account::find(76, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(200, 0));
+    // the accounts of a run without the schedule are left for the next run with it
+    assert_eq!(index(GENERATED_TX_BASE, &config).0, vec![76, 77, 78]);
+
+    // published accounts get the rules too, not only those of the run
+    let p = process(
+        "src/tests/csv/schedule_run.csv",
+        Some(schedule.clone()),
+        &config,
+    );
+
+    let account = Account::find(76, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(199475, 3));
+    let account = Account::find(77, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(475, 1));
+    let account = Account::find(78, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(2, 0));
+    let account = Account::find(79, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(25, 1));
+
+    // fees that can't be paid are rejected under the rule that made them
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/schedule.txt";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        format!(
+            "{},2,fee,78,{},2.5000,insufficient_funds",
+            file,
+            GENERATED_TX_BASE + 3
+        ),
+        format!(
+            "{},4,fee,78,{},5.0000,insufficient_funds",
+            file,
+            GENERATED_TX_BASE + 4
+        ),
+        format!(
+            "{},4,fee,79,{},5.0000,insufficient_funds",
+            file,
+            GENERATED_TX_BASE + 6
+        ),
+    ];
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines, expected);
+
+    // only the ids of applied rows are claimed, the clients with a rejected row are gone back to
+    assert!(index(GENERATED_TX_BASE, &config).1);
+    assert_eq!(index(GENERATED_TX_BASE + 3, &config), (vec![78, 79], false));
+
+    // the generated ids go on from the last run, the rate is rounded half to even
+    schedule.set_period("2022-04");
+    process(
+        "src/tests/csv/schedule_next.csv",
+        Some(schedule.clone()),
+        &config,
+    );
+    let account = Account::find(76, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(1989448, 4));
+
+    // a run of the same period again charges + pays nothing more,
+    // only the fees that couldn't be paid are tried again
+    let p = process("src/tests/csv/schedule_fee.csv", Some(schedule), &config);
+    let account = Account::find(76, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(1989448, 4));
+    let account = Account::find(79, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(20, 0));
+    assert_eq!(index(GENERATED_TX_BASE, &config).0, vec![78]);
+
+    // fees + interest are never read from an input
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let lines: Vec<&str> = string.lines().skip(1).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "src/tests/csv/schedule_fee.csv,3,fee,79,7,1.0000,schedule_only"
+    );
+    assert!(lines[1..]
+        .iter()
+        .all(|l| l.contains(",fee,78,") && l.ends_with(",insufficient_funds")));
+
+    // the rows are in the history like any other
+    let lines = Statement::lines(76, &config).unwrap();
+    let v: Vec<(u64, &str, &str, &str)> = lines
+        .iter()
+        .map(|l| {
+            (
+                l.tx,
+                l.type_id.as_str(),
+                l.amount.as_str(),
+                l.total.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        v,
+        vec![
+            (1, "deposit", "200.0000", "200.0000"),
+            (GENERATED_TX_BASE, "fee", "2.5000", "197.5000"),
+            (GENERATED_TX_BASE + 1, "interest", "1.9750", "199.4750"),
+            (GENERATED_TX_BASE + 7, "fee", "2.5000", "196.9750"),
+            (GENERATED_TX_BASE + 8, "interest", "1.9698", "198.9448"),
+        ]
+    );
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_schedule_invalid_test() {
+    let config = TestHelper::config("process_schedule_invalid_test");
+    fs::create_dir_all(config.data_dir()).unwrap();
+
+    let path = [config.data_dir(), "/schedule.txt"].join("");
+    fs::write(&path, "fee,flat,1\ndeposit,flat,1\n").unwrap();
+    let result = TxSchedule::from_file(&path);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::PARSE);
+
+    fs::write(&path, "interest,rate,-0.01\n").unwrap();
+    assert!(TxSchedule::from_file(&path).is_err());
+
+    TestHelper::clean(&config);
+}
@@ -0,0 +1,93 @@
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account_detail::AccountDetail;
+use crate::models::output::OutputFormat;
+use crate::models::processor::Processor;
+
+// --------- //
+// input csv //
+// --------- //
+
+// show_base.csv
+// type,client,tx,amount
+// deposit,63,1,4
+// deposit,63,2,2
+// deposit,63,3,1
+
+// show_update.csv
+// type,client,tx,amount
+// dispute,63,2,
+// dispute,63,3,
+// resolve,63,3,
+// deposit,63,4,1
+
+#[test]
+fn process_show_test() {
+    let config = TestHelper::config("process_show_test");
+    for path in [
+        "src/tests/csv/show_base.csv",
+        "src/tests/csv/show_update.csv",
+    ] {
+        let result = Processor::new(path, &config);
+        assert!(result.is_ok());
+        assert!(result.unwrap().process_data(false).is_ok());
+    }
+
+    // the lists are only loaded when asked for
+    let result = AccountDetail::load(63, &config, false, false, false);
+    assert!(result.is_ok());
+    let detail = result.unwrap();
+    assert_eq!(detail.account.available, "6.0000");
+    assert_eq!(detail.account.held, "2.0000");
+    assert!(detail.disputes.is_none());
+    assert!(detail.backups.is_none());
+
+    // the resolved dispute is not open anymore
+    let detail = AccountDetail::load(63, &config, true, true, false).unwrap();
+    let disputes = detail.disputes.as_ref().unwrap();
+    assert_eq!(disputes.len(), 1);
+    assert_eq!(disputes[0].tx, 2);
+    assert_eq!(disputes[0].amount, "2.0000");
+
+    // the second run backed up the account of the first
+    let backups = detail.backups.as_ref().unwrap();
+    assert_eq!(backups.len(), 1);
+    let backup = fs::read_to_string(&backups[0].file).unwrap();
+    assert!(backup.contains("63,7.0000,0.0000,7.0000,false"));
+
+    let output = [config.data_dir(), "/show.csv"].join("");
+    assert!(detail.write(Some(&output), OutputFormat::CSV).is_ok());
+    let string = fs::read_to_string(&output).unwrap();
+    assert!(string.starts_with(
+        "client,available,held,total,locked,state\n\
+         63,6.0000,2.0000,8.0000,false,active\n\
+         \n\
+         tx,type,amount\n\
+         2,deposit,2.0000\n\
+         \n\
+         file,time\n"
+    ));
+
+    let output = [config.data_dir(), "/show.json"].join("");
+    assert!(detail.write(Some(&output), OutputFormat::JSON).is_ok());
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(json["account"]["total"], "8.0000");
+    assert_eq!(json["disputes"][0]["tx"], 2);
+    assert_eq!(json["backups"].as_array().unwrap().len(), 1);
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_show_unknown_client_test() {
+    let config = TestHelper::config("process_show_unknown_client_test");
+
+    let result = AccountDetail::load(64, &config, true, true, true);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::RULE);
+
+    TestHelper::clean(&config);
+}
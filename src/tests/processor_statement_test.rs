@@ -0,0 +1,88 @@
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::output::OutputFormat;
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount
+// deposit,61,1,10
+// deposit,61,2,5
+// withdrawal,61,3,3
+// dispute,61,2,
+// deposit,61,4,2
+// withdrawal,61,5,100
+// dispute,61,4,
+// resolve,61,4,
+
+#[test]
+fn process_statement_test() {
+    let config = TestHelper::config("process_statement_test");
+    let result = Processor::new("src/tests/csv/statement.csv", &config);
+    assert!(result.is_ok());
+
+    let p = result.unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let result = Statement::lines(61, &config);
+    assert!(result.is_ok());
+
+    // the rejected withdrawal is not part of the history
+    let lines = result.unwrap();
+    assert_eq!(lines.len(), 4);
+
+    let v: Vec<(u64, &str, &str, &str, &str, &str)> = lines
+        .iter()
+        .map(|l| {
+            (
+                l.tx,
+                l.state.as_str(),
+                l.amount.as_str(),
+                l.available.as_str(),
+                l.held.as_str(),
+                l.total.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        v,
+        vec![
+            (1, "none", "10.0000", "10.0000", "0.0000", "10.0000"),
+            (2, "dispute", "5.0000", "10.0000", "5.0000", "15.0000"),
+            (3, "none", "3.0000", "7.0000", "5.0000", "12.0000"),
+            (4, "resolve", "2.0000", "9.0000", "5.0000", "14.0000"),
+        ]
+    );
+    assert_eq!(lines[2].type_id, "withdrawal");
+
+    // the last line has the balances of the account
+    let account = Account::find(61, &config).unwrap();
+    assert_eq!(format!("{:.4}", account.available), lines[3].available);
+    assert_eq!(format!("{:.4}", account.held), lines[3].held);
+    assert_eq!(format!("{:.4}", account.total), lines[3].total);
+
+    let output = [config.data_dir(), "/statement.csv"].join("");
+    assert!(Statement::write(61, &config, Some(&output), OutputFormat::CSV).is_ok());
+    let string = fs::read_to_string(&output).unwrap();
+    assert!(string.starts_with("tx,type,amount,state,available,held,total\n"));
+    assert!(string.contains("\n2,deposit,5.0000,dispute,10.0000,5.0000,15.0000\n"));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_statement_unknown_client_test() {
+    let config = TestHelper::config("process_statement_unknown_client_test");
+
+    let result = Statement::lines(62, &config);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::RULE);
+
+    TestHelper::clean(&config);
+}
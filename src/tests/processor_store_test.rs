@@ -0,0 +1,119 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::common::error::ErrorKind;
+use crate::models::account::Account;
+use crate::models::account_detail::AccountDetail;
+use crate::models::engine::Engine;
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+use crate::models::store::{Store, STORE_VERSION};
+use crate::models::tx_dispute::DisputeLog;
+
+#[test]
+fn process_wide_id_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,70000,5000000000,3
+    // deposit,70000,5000000001,2
+    // dispute,70000,5000000001,
+
+    let config = TestHelper::config("process_wide_id_test");
+    let result = Processor::new("src/tests/csv/wide.csv", &config);
+    assert!(result.is_ok());
+    assert!(result.unwrap().process_data(false).is_ok());
+
+    let account = Account::find(70000, &config).unwrap();
+    assert_eq!(account.client_id, 70000);
+    assert_eq!(account.available, Decimal::new(3, 0));
+    assert_eq!(account.held, Decimal::new(2, 0));
+
+    // a new data root starts at the current version
+    assert_eq!(Store::version(&config).unwrap(), Some(STORE_VERSION));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_store_migrate_test() {
+    // --------- //
+    // input csv //
+    // --------- //
+
+    // type,client,tx,amount
+    // deposit,59,1,5
+    // deposit,59,2,2
+
+    let config = TestHelper::config("process_store_migrate_test");
+
+    // a v1 data root: no version file, tx 1 already applied to client 59
+    fs::create_dir_all(config.account_dir()).unwrap();
+    fs::write(
+        [&config.account_dir(), "/59.csv"].join(""),
+        "59,5.0000,0.0000,5.0000,false\n",
+    )
+    .unwrap();
+    {
+        let tx_index = sled::open(config.tx_index_dir()).unwrap();
+        tx_index.insert("1", "59").unwrap();
+        tx_index.flush().unwrap();
+    }
+    assert_eq!(Store::version(&config).unwrap(), None);
+
+    let result = Processor::new("src/tests/csv/store.csv", &config);
+    assert!(result.is_ok());
+    assert_eq!(Store::version(&config).unwrap(), Some(2));
+
+    // the migrated data is read as it was
+    let reports = result.unwrap().process_data(false).unwrap();
+    assert_eq!(reports[0].duplicates.len(), 1);
+
+    let account = Account::find(59, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(7, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_store_newer_test() {
+    let config = TestHelper::config("process_store_newer_test");
+    fs::create_dir_all(config.data_dir()).unwrap();
+    fs::write([config.data_dir(), "/version"].join(""), "99").unwrap();
+
+    let result = Processor::new("src/tests/csv/store.csv", &config);
+    assert!(result.is_err());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::RULE);
+
+    // the entry points that only read refuse it as well
+    let err = Statement::lines(59, &config).unwrap_err();
+    assert_eq!(err.context().method, "check");
+    let err = DisputeLog::lines(59, &config).unwrap_err();
+    assert_eq!(err.context().method, "check");
+    let err = AccountDetail::load(59, &config, false, false, false)
+        .err()
+        .unwrap();
+    assert_eq!(err.context().method, "check");
+    let err = Engine::new(&config).accounts().unwrap_err();
+    assert_eq!(err.context().method, "check");
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_store_migrate_unreadable_test() {
+    let config = TestHelper::config("process_store_migrate_unreadable_test");
+
+    // a v1 data root with an account file that can't be read is not marked as v2
+    fs::create_dir_all(config.account_dir()).unwrap();
+    fs::write([&config.account_dir(), "/59.csv"].join(""), "59,x\n").unwrap();
+
+    let result = Processor::new("src/tests/csv/store.csv", &config);
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::PARSE);
+    assert_eq!(Store::version(&config).unwrap(), None);
+
+    TestHelper::clean(&config);
+}
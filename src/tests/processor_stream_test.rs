@@ -0,0 +1,120 @@
+use flate2::write::GzEncoder;
+use flate2::Compression as GzCompression;
+use rust_decimal::Decimal;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::Account;
+use crate::models::processor::Processor;
+use crate::models::tx_input::TxInput;
+use crate::models::tx_reader::{InputFormat, TxReader};
+
+const CSV: &str = "type,client,tx,amount\ndeposit,53,1,5\nwithdrawal,53,2,2\n";
+const ZSTD_CSV: &str = "type,client,tx,amount\ndeposit,54,3,4\n";
+
+#[test]
+fn process_compressed_test() {
+    let config = TestHelper::config("process_compressed_test");
+    fs::create_dir_all(config.data_dir()).unwrap();
+
+    let gz_path = [config.data_dir(), "/drop.csv.gz"].join("");
+    let mut encoder = GzEncoder::new(Vec::new(), GzCompression::default());
+    encoder.write_all(CSV.as_bytes()).unwrap();
+    fs::write(&gz_path, encoder.finish().unwrap()).unwrap();
+
+    let zst_path = [config.data_dir(), "/drop_next.csv.zst"].join("");
+    fs::write(&zst_path, zstd::encode_all(ZSTD_CSV.as_bytes(), 0).unwrap()).unwrap();
+
+    let result = Processor::new_batch(vec![gz_path.clone(), zst_path.clone()], &config);
+    assert!(result.is_ok());
+
+    // the summary dir is named after the files without their extensions
+    let p = result.unwrap();
+    assert!(p.csv_summary_dir().ends_with("/drop-drop_next"));
+
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+    let reports = result.unwrap();
+    assert_eq!(reports[0].rows, 2);
+    assert_eq!(reports[1].rows, 1);
+
+    let account = Account::find(53, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(3, 0));
+    let account = Account::find(54, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(4, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_spool_test() {
+    let config = TestHelper::config("process_spool_test");
+
+    // a stream is copied to the data root, then read like a file
+    let result = TxInput::spool(Cursor::new(CSV), &config.stdin_dir());
+    assert!(result.is_ok());
+
+    let spool = result.unwrap();
+    let path = spool.path().to_string();
+    assert!(path.starts_with(&[&config.stdin_dir(), "/stdin_"].join("")));
+    assert!(path.ends_with(".csv"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), CSV);
+
+    let result = Processor::new(&path, &config);
+    assert!(result.is_ok());
+    assert!(result.unwrap().process_data(false).is_ok());
+
+    let account = Account::find(53, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(3, 0));
+
+    // nothing is left of the stream after the run
+    drop(spool);
+    assert!(!Path::new(&path).exists());
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn process_spool_sniff_test() {
+    let config = TestHelper::config("process_spool_sniff_test");
+
+    // the content tells the format, + the compression, of a stream
+    let ndjson = "\n{\"type\":\"deposit\",\"client\":56,\"tx\":1,\"amount\":\"2\"}\n";
+    let spool = TxInput::spool(Cursor::new(ndjson), &config.stdin_dir()).unwrap();
+    assert!(spool.path().ends_with(".ndjson"));
+
+    let gz = {
+        let mut encoder = GzEncoder::new(Vec::new(), GzCompression::default());
+        encoder
+            .write_all(b"type,client,tx,amount\ndeposit,56,2,3\n")
+            .unwrap();
+        encoder.finish().unwrap()
+    };
+    let gz_spool = TxInput::spool(Cursor::new(gz), &config.stdin_dir()).unwrap();
+    assert!(gz_spool.path().ends_with(".csv.gz"));
+
+    let paths = vec![spool.path().to_string(), gz_spool.path().to_string()];
+    let p = Processor::new_batch(paths, &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let account = Account::find(56, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(5, 0));
+
+    TestHelper::clean(&config);
+}
+
+#[test]
+fn reader_from_reader_test() {
+    let ndjson = "{\"type\":\"deposit\",\"client\":55,\"tx\":1,\"amount\":\"1.5\"}\n";
+    let mut reader =
+        TxReader::from_reader("stream", Box::new(Cursor::new(ndjson)), InputFormat::NDJSON);
+
+    assert!(reader.next_record());
+    assert_eq!(*reader.tx_record_client(), 55);
+    assert_eq!(*reader.tx_record_amount(), Decimal::new(15, 1));
+    assert_eq!(reader.line(), 1);
+    assert!(!reader.next_record());
+    assert!(reader.take_error().is_none());
+}
@@ -0,0 +1,219 @@
+use rust_decimal::Decimal;
+use std::fs;
+
+use super::helpers::helper::TestHelper;
+use crate::models::account::{Account, AccountState};
+use crate::models::account_audit::AccountAuditLog;
+use crate::models::processor::Processor;
+use crate::models::statement::Statement;
+use crate::models::tx_reader::ParseMode;
+use crate::models::tx_rejection::TxRejectionLog;
+
+// --------- //
+// input csv //
+// --------- //
+
+// type,client,tx,amount,to
+// deposit,72,1,10,
+// deposit,73,2,1,
+// transfer,72,3,4,73
+// transfer,72,4,100,73
+// freeze,74,5,,
+// transfer,72,6,1,74
+// dispute,73,3,,
+// dispute,72,3,,
+// withdrawal,73,7,2,
+// resolve,72,3,,
+// transfer,73,8,2,72
+// dispute,73,8,,
+// chargeback,73,8,,
+// transfer,72,11,2,75
+// transfer,72,9,1,
+// transfer,72,10,1,72
+
+#[test]
+fn process_transfer_test() {
+    let config = TestHelper::config("process_transfer_test");
+    let result = Processor::new("src/tests/csv/transfer.csv", &config);
+    assert!(result.is_ok());
+
+    let mut p = result.unwrap();
+    p.set_parse_mode(ParseMode::LENIENT);
+    let result = p.process_data(false);
+    assert!(result.is_ok());
+
+    // a transfer without a recipient, or to the sender, is malformed
+    let reports = result.unwrap();
+    let lines: Vec<u64> = reports[0].quarantined.iter().map(|q| q.line).collect();
+    assert_eq!(lines, vec![16, 17]);
+
+    let account = Account::find(72, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(4, 0));
+    assert_eq!(account.total, Decimal::new(4, 0));
+    assert_eq!(account.state, AccountState::ACTIVE);
+
+    // charged back the transfer to 72, so locked
+    let account = Account::find(73, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(5, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(5, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    let account = Account::find(74, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(0, 0));
+
+    let account = Account::find(75, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(2, 0));
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/transfer.csv";
+    let expected = [
+        "file,line,type,client,tx,amount,reason".to_string(),
+        [file, ",5,transfer,72,4,100.0000,insufficient_funds"].join(""),
+        [file, ",7,transfer,72,6,1.0000,recipient_refused"].join(""),
+        [file, ",8,dispute,73,3,0.0000,unknown_tx"].join(""),
+        [file, ",10,withdrawal,73,7,2.0000,insufficient_funds"].join(""),
+    ];
+    let lines: Vec<&str> = string.lines().collect();
+    assert_eq!(lines, expected);
+
+    // the recipient has the credit side of the transfer in its history
+    let lines = Statement::lines(73, &config).unwrap();
+    let v: Vec<(u64, &str, &str, &str, &str)> = lines
+        .iter()
+        .map(|l| {
+            (
+                l.tx,
+                l.type_id.as_str(),
+                l.state.as_str(),
+                l.available.as_str(),
+                l.total.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        v,
+        vec![
+            (2, "deposit", "none", "1.0000", "1.0000"),
+            (3, "transfer_in", "resolve", "5.0000", "5.0000"),
+            (8, "transfer", "chargeback", "5.0000", "5.0000"),
+        ]
+    );
+
+    let audits = AccountAuditLog::read(Some(73), &config).unwrap();
+    assert_eq!(audits.len(), 1);
+    assert_eq!(audits[0].action, "chargeback");
+    assert_eq!(audits[0].line, 14);
+
+    // disputes on a transfer of an earlier run
+    let result = Processor::new("src/tests/csv/transfer_next.csv", &config);
+    assert!(result.is_ok());
+    assert!(result.unwrap().process_data(false).is_ok());
+
+    let account = Account::find(72, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(6, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    let account = Account::find(75, &config).unwrap();
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(0, 0));
+
+    TestHelper::clean(&config);
+}
+
+// transfer_chain.csv
+// type,client,tx,amount,to
+// deposit,90,1,10,
+// deposit,91,2,1,
+// deposit,92,3,1,
+// deposit,93,4,1,
+// transfer,90,5,10,91
+// withdrawal,91,6,11,
+// deposit,91,7,3,
+// transfer,91,8,3,92
+// transfer,92,9,4,93
+// withdrawal,93,10,5,
+// transfer,93,11,1,90
+// deposit,90,12,2,
+
+#[test]
+fn process_transfer_chain_test() {
+    // the clients start on different workers + each row depends on the transfer before it
+    let config = TestHelper::config("process_transfer_chain_test");
+    let p = Processor::new("src/tests/csv/transfer_chain.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    let totals: Vec<Decimal> = (90..94)
+        .map(|client_id| Account::find(client_id, &config).unwrap().total)
+        .collect();
+    assert_eq!(
+        totals,
+        vec![
+            Decimal::new(2, 0),
+            Decimal::new(0, 0),
+            Decimal::new(0, 0),
+            Decimal::new(0, 0)
+        ]
+    );
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let lines: Vec<&str> = string.lines().skip(1).collect();
+    assert_eq!(
+        lines,
+        vec!["src/tests/csv/transfer_chain.csv,12,transfer,93,11,1.0000,insufficient_funds"]
+    );
+
+    TestHelper::clean(&config);
+}
+
+// transfer_refused.csv
+// type,client,tx,amount,to
+// deposit,97,1,10,
+// deposit,98,2,1,
+// transfer,97,3,4,98
+// dispute,98,2,,
+// chargeback,98,2,,
+// dispute,97,3,,
+// transfer,97,4,1,98
+
+// transfer_refused_next.csv
+// type,client,tx,amount,to
+// unlock,98,5,,
+// transfer,97,4,1,98
+
+#[test]
+fn process_transfer_refused_test() {
+    let config = TestHelper::config("process_transfer_refused_test");
+    let p = Processor::new("src/tests/csv/transfer_refused.csv", &config).unwrap();
+    assert!(p.process_data(false).is_ok());
+
+    // the locked recipient refuses the dispute on the transfer, so its balances don't move
+    let account = Account::find(98, &config).unwrap();
+    assert_eq!(account.available, Decimal::new(4, 0));
+    assert_eq!(account.held, Decimal::new(0, 0));
+    assert_eq!(account.total, Decimal::new(4, 0));
+    assert_eq!(account.state, AccountState::LOCKED);
+
+    let string = fs::read_to_string(TxRejectionLog::csv_path(p.csv_summary_dir())).unwrap();
+    let file = "src/tests/csv/transfer_refused.csv";
+    let lines: Vec<&str> = string.lines().skip(1).collect();
+    assert_eq!(
+        lines,
+        vec![
+            [file, ",7,dispute,97,3,0.0000,recipient_refused"].join(""),
+            [file, ",8,transfer,97,4,1.0000,recipient_refused"].join(""),
+        ]
+    );
+
+    // the tx id of the refused transfer is free, so it can be sent again
+    let p = Processor::new("src/tests/csv/transfer_refused_next.csv", &config).unwrap();
+    let reports = p.process_data(false).unwrap();
+    assert!(reports[0].duplicates.is_empty());
+
+    let account = Account::find(97, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(5, 0));
+    let account = Account::find(98, &config).unwrap();
+    assert_eq!(account.total, Decimal::new(5, 0));
+
+    TestHelper::clean(&config);
+}
@@ -1,7 +1,6 @@
 use rust_decimal::Decimal;
 
 use super::helpers::helper::TestHelper;
-use crate::lib::constants::ACCOUNT_DIR;
 use crate::models::account::Account;
 use crate::models::processor::Processor;
 
@@ -21,22 +20,28 @@ fn process_withdraw_test() {
     // withdrawal,7,7,1
     // deposit,7,1,5
 
-    let result = Processor::new("src/tests/csv/withdraw.csv");
+    let config = TestHelper::config("process_withdraw_test");
+    let result = Processor::new("src/tests/csv/withdraw.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
+    // tx 1 was already used by the first withdrawal
+    let reports = result.unwrap();
+    assert_eq!(reports[0].duplicates.len(), 1);
+    assert_eq!(reports[0].duplicates[0].tx_id, 1);
+
     // check balance
-    let account = Account::new(7, ACCOUNT_DIR);
+    let account = Account::new(7, &config.account_dir(), &config);
     assert_eq!(account.client_id, 7);
-    assert_eq!(account.available, Decimal::new(9, 0));
+    assert_eq!(account.available, Decimal::new(4, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
-    assert_eq!(account.total, Decimal::new(9, 0));
+    assert_eq!(account.total, Decimal::new(4, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&7);
+    TestHelper::clean(&config);
 }
 
 #[test]
@@ -57,27 +62,28 @@ fn process_withdraw_multi_test() {
     // withdrawal,23,7,1
     // deposit,23,8,5
 
+    let config = TestHelper::config("process_withdraw_multi_test");
     let client_id = 23;
-    let result = Processor::new("src/tests/csv/withdraw_multi_0.csv");
+    let result = Processor::new("src/tests/csv/withdraw_multi_0.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let result = Processor::new("src/tests/csv/withdraw_multi_1.csv");
+    let result = Processor::new("src/tests/csv/withdraw_multi_1.csv", &config);
     assert!(result.is_ok());
 
     let p = result.unwrap();
     let result = p.process_data(false);
     assert!(result.is_ok());
 
-    let account = Account::new(client_id, ACCOUNT_DIR);
+    let account = Account::new(client_id, &config.account_dir(), &config);
     assert_eq!(account.client_id, client_id);
     assert_eq!(account.available, Decimal::new(9, 0));
     assert_eq!(account.held, Decimal::new(0, 0));
     assert_eq!(account.total, Decimal::new(9, 0));
     assert!(!account.locked);
 
-    TestHelper::clean(&client_id);
+    TestHelper::clean(&config);
 }